@@ -0,0 +1,61 @@
+//! Tone and color adjustments for finished images, all done in place
+use crate::{ImagePPM, Pixel};
+
+/// Builds a 0..=255 lookup table, rounding and clamping whatever `f` returns
+fn table(f: impl Fn(f64) -> f64) -> [u8; 256] {
+    std::array::from_fn(|v| f(v as f64).round().clamp(0.0, 255.0) as u8)
+}
+
+impl ImagePPM {
+    /// Runs every channel of every pixel through `table`
+    fn map_channels(&mut self, table: &[u8; 256]) {
+        for p in &mut self.atoms {
+            p.r = table[p.r as usize];
+            p.g = table[p.g as usize];
+            p.b = table[p.b as usize];
+        }
+    }
+
+    /// Adds `delta` to every channel, clamping to 0..=255
+    pub fn adjust_brightness(&mut self, delta: i16) {
+        self.map_channels(&table(|v| v + delta as f64));
+    }
+
+    /// Scales the distance of every channel from the middle gray (128): factors above 1 add
+    /// contrast, below 1 take it away, 0 makes everything gray
+    pub fn adjust_contrast(&mut self, factor: f64) {
+        self.map_channels(&table(|v| (v - 128.0)*factor + 128.0));
+    }
+
+    /// Gamma correction, `out = in^(1/gamma)` with channels in 0..=1. Above 1 brightens the
+    /// midtones, below 1 darkens them. Black and white stay put. Panics if `gamma` isn't positive
+    pub fn apply_gamma(&mut self, gamma: f64) {
+        assert!(gamma > 0.0, "gamma must be positive, got {gamma}");
+        self.map_channels(&table(|v| 255.0 * (v / 255.0).powf(1.0 / gamma)));
+    }
+
+    /// Replaces every pixel with its luminance (Rec. 601, like `ImagePGM::from`), keeping the
+    /// image RGB
+    pub fn to_grayscale(&mut self) {
+        for p in &mut self.atoms {
+            let l = p.luminance();
+            *p = Pixel::new(l, l, l);
+        }
+    }
+
+    /// Photographic negative
+    pub fn invert(&mut self) { self.map_channels(&table(|v| 255.0 - v)); }
+
+    /// Old-photo brown tint, using the usual sepia matrix
+    pub fn sepia(&mut self) {
+        for p in &mut self.atoms {
+            let (r, g, b) = (p.r as f64, p.g as f64, p.b as f64);
+            let f = |x: f64| x.round().min(255.0) as u8;
+            *p = Pixel::new(
+                f(0.393*r + 0.769*g + 0.189*b),
+                f(0.349*r + 0.686*g + 0.168*b),
+                f(0.272*r + 0.534*g + 0.131*b),
+            );
+        }
+    }
+}
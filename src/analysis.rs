@@ -0,0 +1,208 @@
+//! Picking images apart: turning them into masks, finding blobs and measuring them, and cleaning
+//! masks up with morphology
+use crate::{Coord, Draw, ICoord, Image, ImagePBM, ImagePGM, ImagePPM, PixelLike};
+
+/// One connected blob of pixels, as found by `connected_components`
+#[derive(Clone, Copy, Debug)]
+pub struct Region {
+    /// Position in the list `connected_components` returns
+    pub label: usize,
+    /// Bottom left corner of the bounding box
+    pub min: Coord,
+    /// Top right corner of the bounding box (inclusive)
+    pub max: Coord,
+    pub pixel_count: usize,
+}
+
+impl Region {
+    pub fn width(&self) -> usize { self.max.x - self.min.x + 1 }
+    pub fn height(&self) -> usize { self.max.y - self.min.y + 1 }
+}
+
+/// Neighbourhood that morphological operations look at around every pixel, as offsets from it
+#[derive(Clone, Debug)]
+pub struct StructuringElement {
+    offsets: Vec<ICoord>,
+}
+
+impl StructuringElement {
+    /// Any shape at all. The pixel itself is only included if `(0, 0)` is among the offsets
+    pub fn new(offsets: Vec<ICoord>) -> Self { Self { offsets } }
+
+    /// `(2*radius + 1)` pixels wide square
+    pub fn square(radius: usize) -> Self { Self::within(radius, |_, _| true) }
+
+    /// Every offset at most `radius` away
+    pub fn disc(radius: usize) -> Self { Self::within(radius, |x, y| x*x + y*y <= (radius*radius) as isize) }
+
+    /// Plus sign with arms `radius` long
+    pub fn cross(radius: usize) -> Self { Self::within(radius, |x, y| x == 0 || y == 0) }
+
+    fn within(radius: usize, keep: impl Fn(isize, isize) -> bool) -> Self {
+        let r = radius as isize;
+        Self { offsets: (-r..=r).flat_map(|y| (-r..=r).map(move |x| (x, y))).filter(|&(x, y)| keep(x, y)).map(|(x, y)| ICoord::new(x, y)).collect() }
+    }
+
+    pub fn offsets(&self) -> &[ICoord] { &self.offsets }
+}
+
+impl<P: PixelLike + Ord> Image<P> {
+    /// Every pixel replaced by `pick` of its neighbourhood. Neighbours outside the image are left
+    /// out
+    fn morph(&self, se: &StructuringElement, pick: impl Fn(P, P) -> P) -> Self {
+        let (w, h) = (self.width as isize, self.height as isize);
+        Image::from_fn(self.width, self.height, |c| {
+            se.offsets.iter()
+                .map(|o| (c.x as isize + o.x, c.y as isize + o.y))
+                .filter(|&(x, y)| (0..w).contains(&x) && (0..h).contains(&y))
+                .map(|(x, y)| *self.get(x as usize, y as usize).unwrap())
+                .reduce(&pick)
+                .unwrap_or(*self.get(c.x, c.y).unwrap())
+        })
+    }
+
+    /// Erosion: every pixel becomes the smallest in its neighbourhood. Shrinks white shapes in
+    /// masks, removing specks smaller than `se`
+    pub fn erode(&self, se: &StructuringElement) -> Self { self.morph(se, P::min) }
+
+    /// Dilation: every pixel becomes the largest in its neighbourhood. Grows white shapes in
+    /// masks, filling holes smaller than `se`
+    pub fn dilate(&self, se: &StructuringElement) -> Self { self.morph(se, P::max) }
+
+    /// Erosion then dilation: gets rid of small specks and thin bits while leaving bigger shapes
+    /// about the same
+    pub fn open(&self, se: &StructuringElement) -> Self { self.erode(se).dilate(se) }
+
+    /// Dilation then erosion: fills small holes and gaps while leaving bigger shapes about the same
+    pub fn close(&self, se: &StructuringElement) -> Self { self.dilate(se).erode(se) }
+}
+
+impl<P: PixelLike> Image<P> {
+    /// Every group of pixels for which `predicate` holds that touch each other, up, down, left
+    /// or right (diagonals don't count). Labels go in the order regions are first met going
+    /// along each row, from y = 0 up
+    pub fn connected_components(&self, mut predicate: impl FnMut(P) -> bool) -> Vec<Region> {
+        let (w, h) = (self.width, self.height);
+        let inside: Vec<bool> = (0..w*h).map(|i| predicate(*self.get(i % w, i / w).unwrap())).collect();
+        let mut seen = vec![false; w*h];
+        let mut regions = Vec::new();
+        let mut stack = Vec::new();
+        for start in 0..w*h {
+            if !inside[start] || seen[start] { continue; }
+            seen[start] = true;
+            stack.push(start);
+            let mut region = Region { label: regions.len(), min: Coord::new(w, h), max: Coord::new(0, 0), pixel_count: 0 };
+            // Flood fill with an explicit stack, so big blobs can't overflow the call stack
+            while let Some(i) = stack.pop() {
+                let (x, y) = (i % w, i / w);
+                region.min = Coord::new(region.min.x.min(x), region.min.y.min(y));
+                region.max = Coord::new(region.max.x.max(x), region.max.y.max(y));
+                region.pixel_count += 1;
+                let neighbours = [(x > 0).then(|| i - 1), (x + 1 < w).then(|| i + 1), (y > 0).then(|| i - w), (y + 1 < h).then(|| i + w)];
+                for n in neighbours.into_iter().flatten() {
+                    if inside[n] && !seen[n] {
+                        seen[n] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+            regions.push(region);
+        }
+        regions
+    }
+}
+
+/// One dimensional squared distance transform (Felzenszwalb & Huttenlocher): for every `q`, the
+/// smallest `(q - p)² + f[p]`. Works by keeping the lower envelope of the parabolas rooted at each
+/// `p`
+fn distance_1d(f: &[f64]) -> Vec<f64> {
+    let n = f.len();
+    // Only finite parabolas go in the envelope, so infinities never get subtracted
+    let roots: Vec<usize> = (0..n).filter(|&p| f[p].is_finite()).collect();
+    let Some(&first) = roots.first() else { return vec![f64::INFINITY; n] };
+    // Parabola `hull[k]` is the lowest from `starts[k]` on
+    let (mut hull, mut starts) = (vec![first], vec![f64::NEG_INFINITY]);
+    for &q in &roots[1..] {
+        loop {
+            let p = *hull.last().unwrap();
+            let s = ((f[q] + (q*q) as f64) - (f[p] + (p*p) as f64)) / (2.0*q as f64 - 2.0*p as f64);
+            if s > *starts.last().unwrap() {
+                hull.push(q);
+                starts.push(s);
+                break;
+            }
+            hull.pop();
+            starts.pop();
+        }
+    }
+    let mut k = 0;
+    (0..n).map(|q| {
+        while k + 1 < hull.len() && starts[k + 1] < q as f64 { k += 1; }
+        let d = q as f64 - hull[k] as f64;
+        d*d + f[hull[k]]
+    }).collect()
+}
+
+impl ImagePBM {
+    /// Euclidean distance from every pixel to the closest set one (0 for set pixels themselves),
+    /// indexed `x + y*width` like `ImagePPM::from_scalar_field` wants. Everything is infinitely
+    /// far if no pixel is set. Exact, and linear in the number of pixels
+    pub fn distance_transform(&self) -> Vec<f64> {
+        let (w, h) = (self.width, self.height);
+        let mut d: Vec<f64> = (0..w*h).map(|i| if *self.get(i % w, i / w).unwrap() { 0.0 } else { f64::INFINITY }).collect();
+        // Squared distances along each column, then along each row on top of those
+        for x in 0..w {
+            let col: Vec<f64> = (0..h).map(|y| d[x + y*w]).collect();
+            for (y, v) in distance_1d(&col).into_iter().enumerate() { d[x + y*w] = v; }
+        }
+        for row in d.chunks_mut(w.max(1)) {
+            let done = distance_1d(row);
+            row.copy_from_slice(&done);
+        }
+        d.iter().map(|v| v.sqrt()).collect()
+    }
+}
+
+impl ImagePPM {
+    /// Mask of the pixels whose luminance is at least `level`, same as `ImagePBM::from_threshold`
+    pub fn threshold(&self, level: u8) -> ImagePBM { ImagePBM::from_threshold(self, level) }
+
+    /// Mask of the pixels brighter than the average of the `window`x`window` square around
+    /// them (clipped to the image). Unlike a fixed level it copes with uneven lighting, like
+    /// shadows across a scanned page
+    pub fn threshold_adaptive(&self, window: usize) -> ImagePBM {
+        let (w, h) = (self.width, self.height);
+        let luma = ImagePGM::from(self);
+        let table = luma.integral_image();
+        ImagePBM::from_fn(w, h, |c| {
+            let (min, max) = table.window(c, window / 2);
+            let n = ((max.x - min.x)*(max.y - min.y)) as u64;
+            luma[(c.x, c.y)] as u64 * n > table.sum(min, max)[0]
+        })
+    }
+
+    /// Otsu's method: the level that splits the luminance histogram into two classes as far
+    /// apart as possible. Works best when the image has a clear foreground and background
+    pub fn otsu_level(&self) -> u8 {
+        let mut hist = [0u64; 256];
+        for p in &self.atoms { hist[p.luminance() as usize] += 1; }
+        let total = self.atoms.len() as f64;
+        let sum_all: f64 = hist.iter().enumerate().map(|(v, &n)| v as f64 * n as f64).sum();
+        let (mut best, mut best_var) = (0, -1.0);
+        let (mut below, mut sum_below) = (0.0, 0.0);
+        // Level t puts values under t in the background and the rest (t included) in the foreground
+        for t in 1..256 {
+            below += hist[t - 1] as f64;
+            sum_below += (t - 1) as f64 * hist[t - 1] as f64;
+            let above = total - below;
+            if below == 0.0 || above == 0.0 { continue; }
+            let diff = sum_below / below - (sum_all - sum_below) / above;
+            let var = below * above * diff * diff;
+            if var > best_var { (best, best_var) = (t, var); }
+        }
+        best as u8
+    }
+
+    /// `threshold` at the level `otsu_level` picks
+    pub fn threshold_otsu(&self) -> ImagePBM { self.threshold(self.otsu_level()) }
+}
@@ -0,0 +1,242 @@
+//! Writing animations, one image per frame
+use std::{fs::{self, File}, io::{BufWriter, Write}, path::PathBuf};
+
+use crate::{Draw, Image, ImagePPM, Pixel, PixelLike, PpmError, PpmFormat};
+
+/// Writes numbered frames (`frame_0001.ppm`, `frame_0002.ppm`, ...) into a directory, ready for
+/// `ffmpeg -i frame_%04d.ppm` and friends. Frames are written in the binary variant of the format
+/// by default, and the bytes of each one are put together in a buffer that's reused between frames
+#[derive(Clone, Debug)]
+pub struct FrameSequence {
+    dir: PathBuf,
+    prefix: String,
+    digits: usize,
+    binary: bool,
+    next: usize,
+    buffer: Vec<u8>,
+}
+
+impl FrameSequence {
+    /// Creates `dir` (and its parents) if it isn't there yet
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, PpmError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, prefix: "frame_".into(), digits: 4, binary: true, next: 1, buffer: vec![] })
+    }
+
+    /// What goes before the number, `"frame_"` by default
+    pub fn with_prefix(self, prefix: impl Into<String>) -> Self { Self { prefix: prefix.into(), ..self } }
+
+    /// Minimum amount of digits in the number (padded with zeros), 4 by default
+    pub fn with_digits(self, digits: usize) -> Self { Self { digits, ..self } }
+
+    /// Number of the first frame, 1 by default
+    pub fn starting_at(self, first: usize) -> Self { Self { next: first, ..self } }
+
+    /// Whether to use the binary (default) or the plain variant of the format
+    pub fn with_binary(self, binary: bool) -> Self { Self { binary, ..self } }
+
+    /// Number the next written frame will get
+    pub fn next_frame(&self) -> usize { self.next }
+
+    /// Where a frame with the given number and extension goes
+    pub fn path_for(&self, frame: usize, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}{:0width$}.{}", self.prefix, frame, extension, width = self.digits))
+    }
+
+    /// Writes `img` as the next frame, returning the path it was written to
+    pub fn write<P: PixelLike>(&mut self, img: &Image<P>) -> Result<PathBuf, PpmError> {
+        let path = self.path_for(self.next, P::EXTENSION);
+        self.buffer.clear();
+        if self.binary { img.write_binary_to(&mut self.buffer)?; } else { img.write_to(&mut self.buffer)?; }
+        fs::write(&path, &self.buffer)?;
+        self.next += 1;
+        Ok(path)
+    }
+
+    /// Lets `draw` update `frame` (which keeps whatever the last frame left in it) and writes it.
+    /// `draw` also gets the frame number
+    pub fn render<P: PixelLike>(&mut self, frame: &mut Image<P>, draw: impl FnOnce(&mut Image<P>, usize)) -> Result<PathBuf, PpmError> {
+        draw(frame, self.next);
+        self.write(frame)
+    }
+}
+
+/// LZW as GIF wants it: variable width codes (up to 12 bits) packed LSB first. `min_size` is the
+/// bits needed for the color indices (at least 2)
+fn lzw_encode(indices: &[u8], min_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_size;
+    let mut out = vec![];
+    let (mut acc, mut nbits) = (0u32, 0u32);
+    let mut emit = |code: u16, size: u8, out: &mut Vec<u8>| {
+        acc |= (code as u32) << nbits;
+        nbits += size as u32;
+        while nbits >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            nbits -= 8;
+        }
+    };
+
+    let mut dict = std::collections::HashMap::new();
+    let mut size = min_size + 1;
+    let mut next = clear + 2;
+    emit(clear, size, &mut out);
+    let Some((&first, rest)) = indices.split_first() else {
+        emit(clear + 1, size, &mut out);
+        if nbits > 0 { out.push(acc as u8); }
+        return out;
+    };
+    let mut current = first as u16;
+    for &i in rest {
+        if let Some(&code) = dict.get(&(current, i)) {
+            current = code;
+            continue;
+        }
+        emit(current, size, &mut out);
+        if next < 4096 {
+            if next == 1 << size { size += 1; }
+            dict.insert((current, i), next);
+            next += 1;
+        } else {
+            // Table's full, start over
+            emit(clear, size, &mut out);
+            dict.clear();
+            size = min_size + 1;
+            next = clear + 2;
+        }
+        current = i as u16;
+    }
+    emit(current, size, &mut out);
+    emit(clear + 1, size, &mut out);
+    if nbits > 0 { out.push(acc as u8); }
+    out
+}
+
+/// Writes a looping GIF89a animation frame by frame. GIF only allows 256 colors per frame, so each
+/// frame gets quantized (with `ImagePPM::quantize`) to its own palette; frames with few colors
+/// keep them exactly
+pub struct AnimatedGif<W: Write> {
+    w: W,
+    width: u16,
+    height: u16,
+    /// Time each frame stays on screen, in hundredths of a second
+    delay: u16,
+}
+
+impl AnimatedGif<BufWriter<File>> {
+    pub fn create(filepath: impl Into<PathBuf>, width: usize, height: usize, delay: u16) -> Result<Self, PpmError> {
+        Self::new(BufWriter::new(File::create(filepath.into())?), width, height, delay)
+    }
+}
+
+impl<W: Write> AnimatedGif<W> {
+    /// Writes the header right away. `delay` is in hundredths of a second. Fails if a side
+    /// doesn't fit in 16 bits
+    pub fn new(mut w: W, width: usize, height: usize, delay: u16) -> Result<Self, PpmError> {
+        let too_big = || PpmError::InvalidDimensions("GIF sides can't go over 65535".into());
+        let (width, height) = (u16::try_from(width).map_err(|_| too_big())?, u16::try_from(height).map_err(|_| too_big())?);
+        w.write_all(b"GIF89a")?;
+        w.write_all(&width.to_le_bytes())?;
+        w.write_all(&height.to_le_bytes())?;
+        // No global color table, every frame brings its own
+        w.write_all(&[0, 0, 0])?;
+        // Loop forever
+        w.write_all(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+        Ok(Self { w, width, height, delay })
+    }
+
+    /// Fails if `frame` isn't the same size as the animation
+    pub fn add_frame(&mut self, frame: &ImagePPM) -> Result<(), PpmError> {
+        if (frame.width(), frame.height()) != (self.width as usize, self.height as usize) {
+            return Err(PpmError::InvalidDimensions(format!(
+                "frame is {}x{} but the animation is {}x{}", frame.width(), frame.height(), self.width, self.height
+            )));
+        }
+        let (img, palette) = frame.quantize(256);
+        let mut lookup = std::collections::HashMap::new();
+        for (i, p) in palette.iter().enumerate().rev() { lookup.insert([p.r, p.g, p.b], i as u8); }
+        let indices: Vec<u8> = img.atoms().iter().map(|p| lookup[&[p.r, p.g, p.b]]).collect();
+
+        // Smallest power of two (at least 2) that fits the palette
+        let bits = (1..=8).find(|b| 1 << b >= palette.len()).unwrap();
+
+        let [d0, d1] = self.delay.to_le_bytes();
+        self.w.write_all(&[0x21, 0xF9, 0x04, 0x04, d0, d1, 0x00, 0x00])?;
+        self.w.write_all(&[0x2C, 0, 0, 0, 0])?;
+        self.w.write_all(&self.width.to_le_bytes())?;
+        self.w.write_all(&self.height.to_le_bytes())?;
+        self.w.write_all(&[0x80 | (bits - 1)])?;
+        for i in 0..1 << bits {
+            let p = palette.get(i).copied().unwrap_or(Pixel::BLACK);
+            self.w.write_all(&[p.r, p.g, p.b])?;
+        }
+
+        let min_size = bits.max(2);
+        self.w.write_all(&[min_size])?;
+        for block in lzw_encode(&indices, min_size).chunks(255) {
+            self.w.write_all(&[block.len() as u8])?;
+            self.w.write_all(block)?;
+        }
+        Ok(self.w.write_all(&[0])?)
+    }
+
+    /// Writes the trailer and gives the writer back
+    pub fn finish(mut self) -> Result<W, PpmError> {
+        self.w.write_all(&[0x3B])?;
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+/// Streams frames as an uncompressed YUV4MPEG2 (`.y4m`) video, which ffmpeg, mpv and most
+/// encoders read straight from a pipe: `cargo run | ffmpeg -i - out.mp4`. Colors are converted to
+/// studio range BT.601 with no chroma subsampling (4:4:4)
+pub struct Y4mWriter<W: Write> {
+    w: W,
+    width: usize,
+    height: usize,
+    /// Y, U and V planes of the frame being written, kept around between frames
+    planes: Vec<u8>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// Writes the stream header right away
+    pub fn new(mut w: W, width: usize, height: usize, fps: u32) -> Result<Self, PpmError> {
+        writeln!(w, "YUV4MPEG2 W{width} H{height} F{fps}:1 Ip A1:1 C444")?;
+        Ok(Self { w, width, height, planes: vec![] })
+    }
+
+    /// Fails if `frame` isn't the same size as the stream
+    pub fn write_frame(&mut self, frame: &ImagePPM) -> Result<(), PpmError> {
+        if (frame.width(), frame.height()) != (self.width, self.height) {
+            return Err(PpmError::InvalidDimensions(format!(
+                "frame is {}x{} but the stream is {}x{}", frame.width(), frame.height(), self.width, self.height
+            )));
+        }
+        let n = self.width*self.height;
+        self.planes.resize(3*n, 0);
+        for (i, p) in frame.atoms().iter().enumerate() {
+            let (r, g, b) = (p.r as f64, p.g as f64, p.b as f64);
+            self.planes[i] = (16.0 + (65.481*r + 128.553*g + 24.966*b)/255.0).round() as u8;
+            self.planes[n + i] = (128.0 + (-37.797*r - 74.203*g + 112.0*b)/255.0).round() as u8;
+            self.planes[2*n + i] = (128.0 + (112.0*r - 93.786*g - 18.214*b)/255.0).round() as u8;
+        }
+        self.w.write_all(b"FRAME\n")?;
+        Ok(self.w.write_all(&self.planes)?)
+    }
+
+    /// Flushes and gives the writer back
+    pub fn finish(mut self) -> Result<W, PpmError> {
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+impl ImagePPM {
+    /// Writes just the pixels, three bytes each, top row first and no header. Writing frame after
+    /// frame to stdout makes a stream for `ffmpeg -f rawvideo -pix_fmt rgb24 -s WxH -i -`
+    pub fn write_raw_rgb24(&self, mut w: impl Write) -> Result<(), PpmError> {
+        Ok(w.write_all(&self.to_rgb_bytes())?)
+    }
+}
@@ -0,0 +1,46 @@
+//! BMP export, plain uncompressed 24 bits per pixel
+use std::{fs::File, io::{BufWriter, Write}, path::PathBuf};
+
+use crate::{ImagePPM, PpmError};
+
+impl ImagePPM {
+    /// Write the image as a 24 bit BMP. BMP stores rows bottom up, same as our coordinates
+    pub fn write_bmp_to(&self, mut w: impl Write) -> Result<(), PpmError> {
+        let too_big = || PpmError::InvalidDimensions("image too big for a BMP".into());
+        let stride = (self.width*3).div_ceil(4) * 4;
+        let raster_size = u32::try_from(stride*self.height).map_err(|_| too_big())?;
+        let (width, height) = (i32::try_from(self.width).map_err(|_| too_big())?, i32::try_from(self.height).map_err(|_| too_big())?);
+        let file_size = raster_size.checked_add(54).ok_or_else(too_big)?;
+
+        // File header
+        w.write_all(b"BM")?;
+        w.write_all(&file_size.to_le_bytes())?;
+        w.write_all(&[0; 4])?;
+        w.write_all(&54u32.to_le_bytes())?;
+        // BITMAPINFOHEADER: no compression, 2835 pixels per meter (72 DPI)
+        w.write_all(&40u32.to_le_bytes())?;
+        w.write_all(&width.to_le_bytes())?;
+        w.write_all(&height.to_le_bytes())?;
+        w.write_all(&1u16.to_le_bytes())?;
+        w.write_all(&24u16.to_le_bytes())?;
+        w.write_all(&0u32.to_le_bytes())?;
+        w.write_all(&raster_size.to_le_bytes())?;
+        w.write_all(&2835i32.to_le_bytes())?;
+        w.write_all(&2835i32.to_le_bytes())?;
+        w.write_all(&[0; 8])?;
+
+        let mut row = Vec::with_capacity(stride);
+        for y in (0..self.height).rev() {
+            row.clear();
+            for p in &self.atoms[y*self.width..(y + 1)*self.width] { row.extend([p.b, p.g, p.r]); }
+            row.resize(stride, 0);
+            w.write_all(&row)?;
+        }
+        Ok(w.flush()?)
+    }
+
+    /// Save the image as a BMP at `filepath` (the suffix is not automatically added)
+    pub fn save_as_bmp(&self, filepath: impl Into<PathBuf>) -> Result<(), PpmError> {
+        self.write_bmp_to(BufWriter::new(File::create(filepath.into())?))
+    }
+}
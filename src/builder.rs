@@ -0,0 +1,46 @@
+//! Builder for images, for when `new`'s three arguments aren't enough
+use crate::{origin::TopLeft, Image, Pixel16, PixelLike, PpmError};
+
+/// Collects the options for a new image and checks them all at once in `build`. Made with
+/// `Image::builder()` (e.g. `ImagePPM::builder()`)
+#[derive(Clone, Debug)]
+pub struct ImageBuilder<P> {
+    width: usize,
+    height: usize,
+    background: P,
+    maxval: u16,
+}
+
+impl<P: PixelLike> Image<P> {
+    /// Starts out as a 0x0 image of `P::BLANK` pixels, so at least the size has to be set
+    pub fn builder() -> ImageBuilder<P> {
+        ImageBuilder { width: 0, height: 0, background: P::BLANK, maxval: P::MAXVAL }
+    }
+}
+
+impl<P: PixelLike> ImageBuilder<P> {
+    pub fn width(self, width: usize) -> Self { Self { width, ..self } }
+    pub fn height(self, height: usize) -> Self { Self { height, ..self } }
+    pub fn size(self, width: usize, height: usize) -> Self { Self { width, height, ..self } }
+    pub fn background(self, background: P) -> Self { Self { background, ..self } }
+
+    /// Fails with `InvalidDimensions` if a side (or the maxval) is zero or the image wouldn't fit
+    /// in memory
+    pub fn build(self) -> Result<Image<P>, PpmError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(PpmError::InvalidDimensions(format!("{}x{} has no pixels", self.width, self.height)));
+        }
+        if self.maxval == 0 { return Err(PpmError::InvalidDimensions("maxval must be at least 1".into())); }
+        let mut img = Image::try_new(self.width, self.height, self.background)?;
+        img.maxval = self.maxval;
+        Ok(img)
+    }
+
+    /// Same as `build`, but with (0, 0) at the top left corner
+    pub fn build_top_left(self) -> Result<TopLeft<Image<P>>, PpmError> { self.build().map(TopLeft) }
+}
+
+impl ImageBuilder<Pixel16> {
+    /// Only 16 bit images let the maxval be picked. Zero is rejected by `build`
+    pub fn maxval(self, maxval: u16) -> Self { Self { maxval, ..self } }
+}
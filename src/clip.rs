@@ -0,0 +1,89 @@
+//! Clipping geometry to rectangles, so shapes that stick out of the image (or are nowhere near
+//! it) only cost what's actually visible
+use crate::CoordF;
+
+/// Axis-aligned rectangle, edges included
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub min: CoordF,
+    pub max: CoordF,
+}
+
+impl Rect {
+    /// The rectangle with corners `a` and `b`, in any order
+    pub fn new(a: impl Into<CoordF>, b: impl Into<CoordF>) -> Self {
+        let (a, b) = (a.into(), b.into());
+        Self { min: CoordF::new(a.x.min(b.x), a.y.min(b.y)), max: CoordF::new(a.x.max(b.x), a.y.max(b.y)) }
+    }
+
+    /// Everything a `width`x`height` image covers, with pixel (x, y) being the unit square
+    /// starting at (x, y)
+    pub fn of_image(width: usize, height: usize) -> Self { Self::new((0.0, 0.0), (width as f64, height as f64)) }
+
+    pub fn contains(&self, p: impl Into<CoordF>) -> bool {
+        let p = p.into();
+        (self.min.x..=self.max.x).contains(&p.x) && (self.min.y..=self.max.y).contains(&p.y)
+    }
+
+    /// Cohen–Sutherland outcode: which sides of the rectangle `p` is beyond
+    fn outcode(&self, p: CoordF) -> u8 {
+        (p.x < self.min.x) as u8 | ((p.x > self.max.x) as u8) << 1 | ((p.y < self.min.y) as u8) << 2 | ((p.y > self.max.y) as u8) << 3
+    }
+}
+
+const LEFT: u8 = 1;
+const RIGHT: u8 = 2;
+const BOTTOM: u8 = 4;
+
+/// The part of segment `ab` inside `rect`, same direction as `ab`. `None` if it misses it
+/// entirely (Cohen–Sutherland)
+pub fn clip_line(a: impl Into<CoordF>, b: impl Into<CoordF>, rect: Rect) -> Option<(CoordF, CoordF)> {
+    let (mut a, mut b) = (a.into(), b.into());
+    let (mut code_a, mut code_b) = (rect.outcode(a), rect.outcode(b));
+    loop {
+        if code_a | code_b == 0 { return Some((a, b)); }
+        if code_a & code_b != 0 { return None; }
+        // Move whichever end is outside onto the edge it's beyond
+        let code = if code_a != 0 { code_a } else { code_b };
+        let d = b - a;
+        let p = if code & LEFT != 0 {
+            CoordF::new(rect.min.x, a.y + d.y * (rect.min.x - a.x) / d.x)
+        } else if code & RIGHT != 0 {
+            CoordF::new(rect.max.x, a.y + d.y * (rect.max.x - a.x) / d.x)
+        } else if code & BOTTOM != 0 {
+            CoordF::new(a.x + d.x * (rect.min.y - a.y) / d.y, rect.min.y)
+        } else {
+            CoordF::new(a.x + d.x * (rect.max.y - a.y) / d.y, rect.max.y)
+        };
+        if code == code_a { (a, code_a) = (p, rect.outcode(p)); } else { (b, code_b) = (p, rect.outcode(p)); }
+    }
+}
+
+/// The part of the polygon `points` inside `rect` (Sutherland–Hodgman), empty if there's none.
+/// Concave polygons may come out with edges running along the rectangle's sides, which fills
+/// don't mind
+pub fn clip_polygon<P: Into<CoordF> + Copy>(points: &[P], rect: Rect) -> Vec<CoordF> {
+    let mut poly: Vec<CoordF> = points.iter().map(|&p| p.into()).collect();
+    // One pass per side, each given as how far a point is inside it (negative is outside)
+    let sides: [fn(CoordF, &Rect) -> f64; 4] = [
+        |p, r| p.x - r.min.x,
+        |p, r| r.max.x - p.x,
+        |p, r| p.y - r.min.y,
+        |p, r| r.max.y - p.y,
+    ];
+    for depth in sides {
+        let input = std::mem::take(&mut poly);
+        for (i, &b) in input.iter().enumerate() {
+            let a = input[(i + input.len() - 1) % input.len()];
+            let (da, db) = (depth(a, &rect), depth(b, &rect));
+            let cross = || a.lerp(b, da / (da - db));
+            match (da >= 0.0, db >= 0.0) {
+                (true, true) => poly.push(b),
+                (true, false) => poly.push(cross()),
+                (false, true) => poly.extend([cross(), b]),
+                (false, false) => {},
+            }
+        }
+    }
+    poly
+}
@@ -0,0 +1,146 @@
+//! Color spaces and other color utilities. Hues are in degrees (0..360), everything else in 0..=1
+use crate::{ImagePPM, Pixel, PpmError};
+
+/// Shared by HSV and HSL: turns chroma, hue and the lightness offset into a pixel
+fn from_chroma(h: f64, c: f64, m: f64) -> Pixel {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u8 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let to_u8 = |v: f64| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    Pixel::new(to_u8(r), to_u8(g), to_u8(b))
+}
+
+impl Pixel {
+    /// Hue in degrees (wraps around), saturation and value in 0..=1
+    pub fn from_hsv(h: f64, s: f64, v: f64) -> Self {
+        let (s, v) = (s.clamp(0.0, 1.0), v.clamp(0.0, 1.0));
+        let c = v * s;
+        from_chroma(h, c, v - c)
+    }
+
+    /// Hue in degrees (wraps around), saturation and lightness in 0..=1
+    pub fn from_hsl(h: f64, s: f64, l: f64) -> Self {
+        let (s, l) = (s.clamp(0.0, 1.0), l.clamp(0.0, 1.0));
+        let c = (1.0 - (2.0*l - 1.0).abs()) * s;
+        from_chroma(h, c, l - c/2.0)
+    }
+
+    /// Hue (0 for grays), max channel and min channel, all in 0..=1 except the hue
+    fn hue_max_min(&self) -> (f64, f64, f64) {
+        let (r, g, b) = (self.r as f64 / 255.0, self.g as f64 / 255.0, self.b as f64 / 255.0);
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let d = max - min;
+        let h = if d == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b)/d).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r)/d + 2.0)
+        } else {
+            60.0 * ((r - g)/d + 4.0)
+        };
+        (h, max, min)
+    }
+
+    /// `(hue, saturation, value)`, the inverse of `from_hsv`
+    pub fn to_hsv(&self) -> (f64, f64, f64) {
+        let (h, max, min) = self.hue_max_min();
+        let s = if max == 0.0 { 0.0 } else { (max - min)/max };
+        (h, s, max)
+    }
+
+    /// `(hue, saturation, lightness)`, the inverse of `from_hsl`
+    pub fn to_hsl(&self) -> (f64, f64, f64) {
+        let (h, max, min) = self.hue_max_min();
+        let l = (max + min)/2.0;
+        let s = if max == min { 0.0 } else { (max - min)/(1.0 - (2.0*l - 1.0).abs()) };
+        (h, s, l)
+    }
+
+    /// Same color with the hue turned by `degrees`
+    pub fn hue_rotate(&self, degrees: f64) -> Self {
+        let (h, s, v) = self.to_hsv();
+        Self::from_hsv(h + degrees, s, v)
+    }
+}
+
+/// Color ramp built from stops, sampled with `t` (usually in 0..=1). Colors between stops are
+/// linearly interpolated, and `t` outside of the stops gets the closest end color
+#[derive(Clone, Debug)]
+pub struct Gradient {
+    stops: Vec<(f64, Pixel)>,
+}
+
+impl Gradient {
+    /// Stops are `(position, color)`, in any order. Panics if there are none
+    pub fn new(stops: impl IntoIterator<Item = (f64, Pixel)>) -> Self {
+        let mut stops: Vec<_> = stops.into_iter().collect();
+        assert!(!stops.is_empty(), "a gradient needs at least one stop");
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { stops }
+    }
+
+    /// Colors spread evenly from 0 to 1
+    pub fn evenly_spaced(colors: &[Pixel]) -> Self {
+        let last = (colors.len().max(2) - 1) as f64;
+        Self::new(colors.iter().enumerate().map(|(i, &c)| (i as f64 / last, c)))
+    }
+
+    pub fn stops(&self) -> &[(f64, Pixel)] { &self.stops }
+
+    pub fn sample(&self, t: f64) -> Pixel {
+        let i = self.stops.partition_point(|&(pos, _)| pos <= t);
+        match (self.stops.get(i.wrapping_sub(1)), self.stops.get(i)) {
+            (Some(&(p0, c0)), Some(&(p1, c1))) => c0.lerp(c1, (t - p0)/(p1 - p0)),
+            (Some(&(_, c)), None) | (None, Some(&(_, c))) => c,
+            (None, None) => unreachable!("gradients always have stops"),
+        }
+    }
+
+    /// Black to white
+    pub fn grayscale() -> Self { Self::evenly_spaced(&[Pixel::BLACK, Pixel::WHITE]) }
+
+    /// Black, red, yellow, white: like something heating up
+    pub fn heat() -> Self { Self::evenly_spaced(&[Pixel::BLACK, Pixel::RED, Pixel::new(255, 255, 0), Pixel::WHITE]) }
+
+    /// Matplotlib's perceptually uniform default colormap (sampled at 8 points)
+    pub fn viridis() -> Self {
+        Self::evenly_spaced(&[
+            Pixel::new(0x44, 0x01, 0x54), Pixel::new(0x46, 0x32, 0x7e), Pixel::new(0x36, 0x5c, 0x8d),
+            Pixel::new(0x27, 0x7f, 0x8e), Pixel::new(0x1f, 0xa1, 0x87), Pixel::new(0x4a, 0xc1, 0x6d),
+            Pixel::new(0xa0, 0xda, 0x39), Pixel::new(0xfd, 0xe7, 0x25),
+        ])
+    }
+}
+
+impl ImagePPM {
+    /// Heatmap of `values` (`values[x + y*width]`, same coordinates as `get`), stretched so the
+    /// smallest one gets the start of `gradient` and the largest its end. NaNs get the start too.
+    /// Fails if there aren't exactly `width*height` values
+    pub fn from_scalar_field(width: usize, height: usize, values: &[f64], gradient: &Gradient) -> Result<Self, PpmError> {
+        let finite = values.iter().copied().filter(|v| v.is_finite());
+        let min = finite.clone().fold(f64::INFINITY, f64::min);
+        let max = finite.fold(f64::NEG_INFINITY, f64::max);
+        Self::from_scalar_field_in(width, height, values, min, max, gradient)
+    }
+
+    /// Same as `from_scalar_field`, but with a fixed `min..=max` range (values outside of it get
+    /// the ends of `gradient`). Handy to keep colors comparable between frames of a simulation
+    pub fn from_scalar_field_in(width: usize, height: usize, values: &[f64], min: f64, max: f64, gradient: &Gradient) -> Result<Self, PpmError> {
+        if Some(values.len()) != width.checked_mul(height) {
+            return Err(PpmError::InvalidDimensions(format!("{} values don't make a {width}x{height} image", values.len())));
+        }
+        Ok(Self::from_fn(width, height, |c| {
+            let v = values[c.x + c.y*width];
+            let t = if max > min { (v - min) / (max - min) } else { 0.0 };
+            gradient.sample(if t.is_nan() { 0.0 } else { t })
+        }))
+    }
+}
@@ -0,0 +1,111 @@
+//! Measuring how different two images are, for golden-image tests and the like
+use std::path::PathBuf;
+
+use crate::{Draw, ImagePPM, Pixel, PpmFormat};
+
+/// Side of the windows SSIM is measured over
+const SSIM_WINDOW: usize = 8;
+
+impl ImagePPM {
+    /// Panics with a readable message if `other` isn't the same size
+    fn assert_same_size(&self, other: &ImagePPM) {
+        assert!(self.width == other.width && self.height == other.height,
+            "can't compare a {}x{} image with a {}x{} one", self.width, self.height, other.width, other.height);
+    }
+
+    /// Largest difference between two pixels in any one channel
+    fn channel_distance(a: Pixel, b: Pixel) -> u8 {
+        a.r.abs_diff(b.r).max(a.g.abs_diff(b.g)).max(a.b.abs_diff(b.b))
+    }
+
+    /// Same as `diff`, but pixels whose channels are all within `tolerance` count as unchanged.
+    /// Also returns how many pixels did change
+    pub(crate) fn diff_within(&self, other: &ImagePPM, tolerance: u8) -> (ImagePPM, usize) {
+        self.assert_same_size(other);
+        let mut changed = 0;
+        let out = self.zip_map(other, |a, b| {
+            if Self::channel_distance(a, b) > tolerance {
+                changed += 1;
+                Pixel::new(255, 0, 0)
+            } else {
+                let l = a.luminance() / 3;
+                Pixel::new(l, l, l)
+            }
+        });
+        (out, changed)
+    }
+
+    /// Picture of where `other` differs from the image: changed pixels in bright red, the rest
+    /// as a dim grayscale copy of the image for context. Panics if their sizes differ
+    pub fn diff(&self, other: &ImagePPM) -> ImagePPM { self.diff_within(other, 0).0 }
+
+    /// Peak signal-to-noise ratio against `other`, in decibels, over all three channels. Higher
+    /// is more similar (above ~40 is hard to tell apart), infinite for identical images. Panics
+    /// if their sizes differ
+    pub fn psnr(&self, other: &ImagePPM) -> f64 {
+        self.assert_same_size(other);
+        let sq = |a: u8, b: u8| (a as f64 - b as f64).powi(2);
+        let total: f64 = self.atoms.iter().zip(&other.atoms).map(|(a, b)| sq(a.r, b.r) + sq(a.g, b.g) + sq(a.b, b.b)).sum();
+        let mse = total / (3 * self.atoms.len()).max(1) as f64;
+        if mse == 0.0 { f64::INFINITY } else { 10.0 * (255.0 * 255.0 / mse).log10() }
+    }
+
+    /// Structural similarity against `other`, from -1 to 1 (identical). Unlike `psnr` it cares
+    /// about local structure rather than raw error, so it's closer to what looks different.
+    /// Simple version: luminance only, averaged over 8x8 windows that overlap by half. Panics
+    /// if their sizes differ
+    pub fn ssim(&self, other: &ImagePPM) -> f64 {
+        self.assert_same_size(other);
+        let (w, h) = (self.width, self.height);
+        if w == 0 || h == 0 { return 1.0; }
+        let luma = |img: &ImagePPM| img.atoms.iter().map(|p| p.luminance() as f64).collect::<Vec<_>>();
+        let (a, b) = (luma(self), luma(other));
+        let (c1, c2) = ((0.01f64 * 255.0).powi(2), (0.03f64 * 255.0).powi(2));
+
+        // Window corners every half window, plus one flush against the far edge so it's all covered
+        let starts = |len: usize| {
+            let side = SSIM_WINDOW.min(len);
+            let mut v: Vec<usize> = (0..=len - side).step_by((side / 2).max(1)).collect();
+            if v.last() != Some(&(len - side)) { v.push(len - side); }
+            (v, side)
+        };
+        let ((xs, sw), (ys, sh)) = (starts(w), starts(h));
+        let n = (sw * sh) as f64;
+        let mut total = 0.0;
+        for &y0 in &ys { for &x0 in &xs {
+            let idx = || (y0..y0 + sh).flat_map(move |y| (x0..x0 + sw).map(move |x| x + y*w));
+            let (ma, mb) = (idx().map(|i| a[i]).sum::<f64>() / n, idx().map(|i| b[i]).sum::<f64>() / n);
+            let (mut va, mut vb, mut cov) = (0.0, 0.0, 0.0);
+            for i in idx() {
+                let (da, db) = (a[i] - ma, b[i] - mb);
+                va += da*da;
+                vb += db*db;
+                cov += da*db;
+            }
+            let (va, vb, cov) = (va / n, vb / n, cov / n);
+            total += ((2.0*ma*mb + c1) * (2.0*cov + c2)) / ((ma*ma + mb*mb + c1) * (va + vb + c2));
+        }}
+        total / (xs.len() * ys.len()) as f64
+    }
+
+    /// Snapshot testing: panics unless the image matches the PPM at `path`, with every channel of
+    /// every pixel within `tolerance`. On a mismatch the `diff` (with the tolerance) is saved
+    /// next to the reference as `<name>.diff.ppm`, so the failure can be looked at
+    pub fn assert_matches_reference(&self, path: impl Into<PathBuf>, tolerance: u8) {
+        let path = path.into();
+        let reference = ImagePPM::load_from_file(&path)
+            .unwrap_or_else(|e| panic!("couldn't load reference image {}: {e}", path.display()));
+        assert!(self.width == reference.width && self.height == reference.height,
+            "image is {}x{} but reference {} is {}x{}", self.width, self.height, path.display(), reference.width, reference.height);
+        let (diff, changed) = self.diff_within(&reference, tolerance);
+        if changed == 0 { return; }
+
+        let diff_path = path.with_extension("diff.ppm");
+        let saved = match diff.save_to_file_binary(&diff_path) {
+            Ok(()) => format!("diff saved to {}", diff_path.display()),
+            Err(e) => format!("couldn't save diff to {}: {e}", diff_path.display()),
+        };
+        panic!("{changed} of {} pixels differ from reference {} by more than {tolerance} (PSNR {:.2} dB), {saved}",
+            self.width() * self.height(), path.display(), self.psnr(&reference));
+    }
+}
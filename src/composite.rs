@@ -0,0 +1,50 @@
+//! Layering one image over another with the usual blend modes
+use crate::{ImagePPM, Pixel};
+
+/// How `composite` mixes a pixel of the top layer into the one below it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The top layer replaces the bottom one
+    Normal,
+    /// Channels are summed, clamping at white
+    Add,
+    /// Channels are multiplied (as 0..=1), which only ever darkens
+    Multiply,
+    /// Inverse of multiplying the inverses, which only ever lightens
+    Screen,
+    /// Multiply where the bottom is dark, screen where it's light, boosting contrast
+    Overlay,
+    /// Absolute difference of the channels: identical layers go black
+    Difference,
+}
+
+impl BlendMode {
+    /// Mixes a whole pixel, `top` over `bottom`
+    pub fn blend(self, bottom: Pixel, top: Pixel) -> Pixel {
+        match self {
+            BlendMode::Normal => top,
+            BlendMode::Add => bottom.additive(top),
+            BlendMode::Multiply => bottom.multiply(top),
+            BlendMode::Screen => bottom.screen(top),
+            BlendMode::Overlay => bottom.zip_channels(top, overlay),
+            BlendMode::Difference => bottom.zip_channels(top, u8::abs_diff),
+        }
+    }
+}
+
+/// One channel of `Overlay`: the `Multiply` formula (doubled) where the bottom is dark, the
+/// `Screen` one where it's light
+fn overlay(bottom: u8, top: u8) -> u8 {
+    let (b, t) = (bottom as u32, top as u32);
+    let v = if b < 128 { (2*b*t + 127) / 255 } else { 255 - (2*(255 - b)*(255 - t) + 127) / 255 };
+    v as u8
+}
+
+impl ImagePPM {
+    /// Blends `other` over the image, pixel by pixel. Panics if their sizes differ
+    pub fn composite(&mut self, other: &ImagePPM, mode: BlendMode) {
+        assert!(self.width == other.width && self.height == other.height,
+            "can't composite a {}x{} layer onto a {}x{} image", other.width, other.height, self.width, self.height);
+        for (p, &top) in self.atoms.iter_mut().zip(&other.atoms) { *p = mode.blend(*p, top); }
+    }
+}
@@ -0,0 +1,95 @@
+//! Delaunay triangulations of point sets, for meshes and low-poly art
+use crate::{CoordF, Draw};
+
+/// Triangles whose circumcircles have no other point inside, so they're as fat as they can be.
+/// Built with Bowyer–Watson, which is quadratic but plenty for a few thousand points
+#[derive(Clone, Debug)]
+pub struct Triangulation {
+    points: Vec<CoordF>,
+    /// Indices into `points`, each triangle counterclockwise
+    triangles: Vec<[usize; 3]>,
+}
+
+/// Center and squared radius of the circle through all three points
+fn circumcircle(a: CoordF, b: CoordF, c: CoordF) -> (CoordF, f64) {
+    let d = 2.0 * (b - a).cross(c - a);
+    let (ab, ac) = (b - a, c - a);
+    let center = a + CoordF::new(
+        (ac.y*ab.dot(ab) - ab.y*ac.dot(ac)) / d,
+        (ab.x*ac.dot(ac) - ac.x*ab.dot(ab)) / d,
+    );
+    (center, center.distance(a).powi(2))
+}
+
+impl Triangulation {
+    /// Triangulates `points`. Repeated points are ignored, and fewer than three (or all of them
+    /// on a line) give no triangles
+    pub fn new<P: Into<CoordF> + Copy>(points: &[P]) -> Self {
+        let mut pts: Vec<CoordF> = points.iter().map(|&p| p.into()).collect();
+        let n = pts.len();
+        if n < 3 { return Self { points: pts, triangles: Vec::new() }; }
+
+        // A triangle far bigger than everything, removed at the end along with anything touching it
+        let (min, max) = pts.iter().fold((pts[0], pts[0]), |(lo, hi), p| {
+            (CoordF::new(lo.x.min(p.x), lo.y.min(p.y)), CoordF::new(hi.x.max(p.x), hi.y.max(p.y)))
+        });
+        let mid = min.lerp(max, 0.5);
+        let size = (max.x - min.x).max(max.y - min.y).max(1.0) * 20.0;
+        pts.extend([mid + CoordF::new(-size, -size), mid + CoordF::new(size, -size), mid + CoordF::new(0.0, size)]);
+
+        // Triangles carry their circumcircle around, as it gets checked against every new point
+        let with_circle = |t: [usize; 3], pts: &[CoordF]| {
+            let (c, r2) = circumcircle(pts[t[0]], pts[t[1]], pts[t[2]]);
+            (t, c, r2)
+        };
+        let mut tris = vec![with_circle([n, n + 1, n + 2], &pts)];
+        for i in 0..n {
+            let p = pts[i];
+            let (bad, good): (Vec<_>, Vec<_>) = tris.into_iter().partition(|(_, c, r2)| p.distance(*c).powi(2) < *r2 * (1.0 - 1e-12));
+            tris = good;
+            // The hole's outline is every edge of a bad triangle that no other bad triangle shares
+            let edges: Vec<(usize, usize)> = bad.iter().flat_map(|(t, ..)| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])]).collect();
+            for &(a, b) in &edges {
+                if edges.iter().any(|&(c, d)| (c, d) == (b, a)) { continue; }
+                // The edges keep their counterclockwise order, so so do the new triangles
+                tris.push(with_circle([a, b, i], &pts));
+            }
+        }
+        pts.truncate(n);
+        // Points in a line can leave slivers with no area behind
+        let triangles = tris.into_iter().map(|(t, ..)| t)
+            .filter(|t| t.iter().all(|&v| v < n) && (pts[t[1]] - pts[t[0]]).cross(pts[t[2]] - pts[t[0]]) > 1e-9)
+            .collect();
+        Self { points: pts, triangles }
+    }
+
+    pub fn points(&self) -> &[CoordF] { &self.points }
+
+    /// Indices into `points`, counterclockwise
+    pub fn triangles(&self) -> &[[usize; 3]] { &self.triangles }
+
+    /// Every edge once, as pairs of indices into `points` (smaller first)
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        let mut edges: Vec<(usize, usize)> = self.triangles.iter()
+            .flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+        edges
+    }
+
+    /// Draws the mesh's edges as one pixel wide lines
+    pub fn draw_edges<I: Draw + ?Sized>(&self, img: &mut I, col: I::Atom) {
+        for (a, b) in self.edges() { img.draw_line(self.points[a], self.points[b], col); }
+    }
+
+    /// Fills every triangle with whatever `color` says for its corners. For low-poly art, sample
+    /// a source image at the triangle's centroid
+    pub fn fill<I: Draw + ?Sized>(&self, img: &mut I, mut color: impl FnMut([CoordF; 3]) -> I::Atom) {
+        for t in &self.triangles {
+            let corners = t.map(|i| self.points[i]);
+            img.fill_polygon(&corners, color(corners));
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Finishing touches: drop shadows and glows layered around shapes (given as masks, which
+//! `mask_of` can make from everything that isn't background), and post effects for whole images
+use crate::{random::Rng, Draw, ICoord, ImagePBM, ImagePPM, Pixel, PpmFormat};
+
+impl ImagePPM {
+    /// Mask of every pixel that isn't exactly `background`
+    pub fn mask_of(&self, background: Pixel) -> ImagePBM {
+        self.map_pixels(|p| (p.r, p.g, p.b) != (background.r, background.g, background.b))
+    }
+
+    /// Paints `coverage` (0 to 1 per pixel, same layout as the atoms) in `col` under the shape:
+    /// pixels in `mask` are left alone
+    fn paint_under(&mut self, mask: &ImagePBM, coverage: &[f64], col: Pixel) {
+        for ((p, &inside), &alpha) in self.atoms.iter_mut().zip(mask.atoms()).zip(coverage) {
+            if !inside && alpha > 0.0 { *p = p.blend(col, alpha.min(1.0)); }
+        }
+    }
+
+    /// Shadow of the shape in `mask`, moved by `offset` and blurred about `blur_radius` pixels
+    /// outwards, painted behind it in `col`. Panics if the mask isn't the same size as the image
+    pub fn drop_shadow(&mut self, mask: &ImagePBM, offset: impl Into<ICoord>, blur_radius: usize, col: Pixel) {
+        assert!(mask.width() == self.width && mask.height() == self.height, "mask must be the same size as the image");
+        let offset = offset.into();
+        let white = Pixel::new(255, 255, 255);
+        // The shadow as a grayscale image, so it can go through the usual blur
+        let shadow = ImagePPM::from_fn(self.width, self.height, |c| {
+            let src = ICoord::new(c.x as isize - offset.x, c.y as isize - offset.y);
+            let hit = usize::try_from(src.x).ok().zip(usize::try_from(src.y).ok()).and_then(|(x, y)| mask.get(x, y).copied());
+            if hit == Some(true) { white } else { Pixel::BLACK }
+        }).gaussian_blur(blur_radius as f64 / 3.0);
+        let coverage: Vec<f64> = shadow.atoms.iter().map(|p| p.r as f64 / 255.0).collect();
+        self.paint_under(mask, &coverage, col);
+    }
+
+    /// Halo of `col` around the shape in `mask`, fading out over `radius` pixels. Panics if the
+    /// mask isn't the same size as the image
+    pub fn outer_glow(&mut self, mask: &ImagePBM, radius: usize, col: Pixel) {
+        assert!(mask.width() == self.width && mask.height() == self.height, "mask must be the same size as the image");
+        let (w, h) = (self.width, self.height);
+        // Strength at each pixel from its distance to the closest pixel of the shape
+        let distances = mask.distance_transform();
+        let coverage: Vec<f64> = (0..w*h).map(|i| {
+            let d = distances[i % w + (h - 1 - i / w)*w];
+            if d <= radius as f64 { 1.0 - d / (radius as f64 + 1.0) } else { 0.0 }
+        }).collect();
+        self.paint_under(mask, &coverage, col);
+    }
+
+    /// Darkens the image towards its corners, leaving the center alone. `strength` is how dark
+    /// the corners get, from 0 (no change) to 1 (black)
+    pub fn vignette(&mut self, strength: f64) {
+        let center = ((self.width as f64 - 1.0) / 2.0, (self.height as f64 - 1.0) / 2.0);
+        let corner2 = (center.0*center.0 + center.1*center.1).max(1.0);
+        for (c, p) in self.enumerate_pixels_mut() {
+            let (dx, dy) = (c.x as f64 - center.0, c.y as f64 - center.1);
+            // Quadratic falloff, so most of the image stays bright
+            let factor = 1.0 - strength.clamp(0.0, 1.0) * (dx*dx + dy*dy) / corner2;
+            *p = *p * factor as f32;
+        }
+    }
+
+    /// Film grain: every pixel gets brighter or darker by a random amount of up to `amount` (the
+    /// same for all three channels, so colors don't get speckled). Same seed, same grain
+    pub fn add_film_grain(&mut self, amount: u8, seed: u64) {
+        let mut rng = Rng::new(seed);
+        for p in &mut self.atoms {
+            let delta = rng.below(2*amount as usize + 1) as i16 - amount as i16;
+            let f = |v: u8| (v as i16 + delta).clamp(0, 255) as u8;
+            *p = Pixel::new(f(p.r), f(p.g), f(p.b));
+        }
+    }
+
+    /// Retro lens look: the red channel moved `offset` pixels left and the blue one right, green
+    /// staying put. Channels that would come from past the edges repeat the edge pixel
+    pub fn chromatic_aberration(&mut self, offset: usize) {
+        let (w, src) = (self.width, self.atoms.clone());
+        for (i, p) in self.atoms.iter_mut().enumerate() {
+            let (row, x) = (i - i % w, i % w);
+            p.r = src[row + (x + offset).min(w - 1)].r;
+            p.b = src[row + x.saturating_sub(offset)].b;
+        }
+    }
+
+    /// CRT look: every other row, starting from the top, darkened by `darkness` (0 leaves it
+    /// alone, 1 makes it black)
+    pub fn scanlines(&mut self, darkness: f64) {
+        let factor = (1.0 - darkness.clamp(0.0, 1.0)) as f32;
+        for row in self.atoms.chunks_mut(self.width.max(1)).skip(1).step_by(2) {
+            for p in row { *p = *p * factor; }
+        }
+    }
+
+    /// Replaces every `block_size`x`block_size` block, starting from the top left corner, with its
+    /// average color. Blocks along the right and bottom edges may be smaller. Panics if
+    /// `block_size` is 0
+    pub fn pixelate(&mut self, block_size: usize) {
+        assert!(block_size > 0, "blocks need to be at least one pixel wide");
+        let (w, h) = (self.width, self.height);
+        for by in (0..h).step_by(block_size) {
+        for bx in (0..w).step_by(block_size) {
+            let idx = || (by..(by + block_size).min(h)).flat_map(move |r| (bx..(bx + block_size).min(w)).map(move |x| x + r*w));
+            let (mut sum, mut n) = ([0u32; 3], 0);
+            for i in idx() {
+                let p = self.atoms[i];
+                sum = [sum[0] + p.r as u32, sum[1] + p.g as u32, sum[2] + p.b as u32];
+                n += 1;
+            }
+            let avg = sum.map(|s| ((s + n/2) / n) as u8);
+            for i in idx() { self.atoms[i] = Pixel::new(avg[0], avg[1], avg[2]); }
+        }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! The crate's error type
+use std::{error::Error, fmt, io};
+
+use crate::Coord;
+
+/// Everything that can go wrong when reading, writing or building images
+#[derive(Debug)]
+pub enum PpmError {
+    /// The underlying reader or writer failed
+    Io(io::Error),
+    /// A coordinate fell outside of a `width`x`height` image
+    OutOfBounds { coord: Coord, width: usize, height: usize },
+    /// Sizes that don't work: too big for the format, not matching what was expected, etc.
+    InvalidDimensions(String),
+    /// The data being read isn't a valid file of its format
+    Parse(String),
+}
+
+impl fmt::Display for PpmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PpmError::Io(e) => write!(f, "I/O error: {e}"),
+            PpmError::OutOfBounds { coord, width, height } => write!(f, "({}, {}) is out of bounds of a {width}x{height} image", coord.x, coord.y),
+            PpmError::InvalidDimensions(msg) => write!(f, "invalid dimensions: {msg}"),
+            PpmError::Parse(msg) => write!(f, "parse error: {msg}"),
+        }
+    }
+}
+
+impl Error for PpmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PpmError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PpmError {
+    fn from(e: io::Error) -> Self { PpmError::Io(e) }
+}
+
+/// So code that only deals in `io::Error` can keep using `?`
+impl From<PpmError> for io::Error {
+    fn from(e: PpmError) -> Self {
+        match e {
+            PpmError::Io(e) => e,
+            PpmError::Parse(_) => io::Error::new(io::ErrorKind::InvalidData, e),
+            _ => io::Error::new(io::ErrorKind::InvalidInput, e),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! farbfeld (https://tools.suckless.org/farbfeld/): a magic string, the size and then 16 bit
+//! big endian RGBA for every pixel, top row first
+use std::{fs::File, io::{BufReader, BufWriter, Read, Write}, path::PathBuf};
+
+use crate::{parse::invalid, Image, ImagePPM, ImagePPM16, ImageRGBA, Pixel, Pixel16, PixelRgba, PpmError};
+
+type Rgba16 = [u16; 4];
+
+fn encode(mut w: impl Write, width: usize, height: usize, pixels: impl Iterator<Item = Rgba16>) -> Result<(), PpmError> {
+    let too_big = || PpmError::InvalidDimensions("farbfeld sides can't go over u32::MAX".into());
+    w.write_all(b"farbfeld")?;
+    w.write_all(&u32::try_from(width).map_err(|_| too_big())?.to_be_bytes())?;
+    w.write_all(&u32::try_from(height).map_err(|_| too_big())?.to_be_bytes())?;
+    for px in pixels {
+        for s in px { w.write_all(&s.to_be_bytes())?; }
+    }
+    Ok(w.flush()?)
+}
+
+fn decode(mut r: impl Read) -> Result<(usize, usize, Vec<Rgba16>), PpmError> {
+    let mut header = [0; 16];
+    r.read_exact(&mut header).map_err(|_| invalid("not a farbfeld file"))?;
+    if &header[..8] != b"farbfeld" { return Err(invalid("not a farbfeld file")); }
+    let width = u32::from_be_bytes(header[8..12].try_into().unwrap()) as usize;
+    let height = u32::from_be_bytes(header[12..16].try_into().unwrap()) as usize;
+
+    let mut data = Vec::new();
+    r.read_to_end(&mut data)?;
+    if Some(data.len()) != width.checked_mul(height).and_then(|n| n.checked_mul(8)) {
+        return Err(invalid(format!("expected {width}x{height} pixels, got {} bytes", data.len())));
+    }
+    let pixels = data.chunks_exact(8)
+        .map(|c| std::array::from_fn(|i| u16::from_be_bytes([c[2*i], c[2*i + 1]])))
+        .collect();
+    Ok((width, height, pixels))
+}
+
+/// 16 bit sample to 8 bits, rounding
+fn to_8(s: u16) -> u8 { ((s as u32 + 128) / 257) as u8 }
+
+impl ImageRGBA {
+    /// Write the image as farbfeld. Samples are scaled up to 16 bits
+    pub fn write_farbfeld_to(&self, w: impl Write) -> Result<(), PpmError> {
+        encode(w, self.width, self.height, self.atoms.iter().map(|p| [p.r, p.g, p.b, p.a].map(|s| s as u16 * 257)))
+    }
+
+    /// Read a farbfeld image, scaling samples down to 8 bits
+    pub fn from_farbfeld_reader(reader: impl Read) -> Result<Self, PpmError> {
+        let (width, height, pixels) = decode(reader)?;
+        Ok(Image::from_atoms(width, height, pixels.into_iter().map(|p| {
+            let [r, g, b, a] = p.map(to_8);
+            PixelRgba::new(r, g, b, a)
+        }).collect()))
+    }
+
+    /// Save the image as farbfeld at `filepath` (the suffix is not automatically added)
+    pub fn save_as_farbfeld(&self, filepath: impl Into<PathBuf>) -> Result<(), PpmError> {
+        self.write_farbfeld_to(BufWriter::new(File::create(filepath.into())?))
+    }
+
+    pub fn load_farbfeld(filepath: impl Into<PathBuf>) -> Result<Self, PpmError> {
+        Self::from_farbfeld_reader(BufReader::new(File::open(filepath.into())?))
+    }
+}
+
+impl ImagePPM16 {
+    /// Write the image as farbfeld, fully opaque. Samples are rescaled from the maxval to 65535,
+    /// so no precision is lost
+    pub fn write_farbfeld_to(&self, w: impl Write) -> Result<(), PpmError> {
+        let m = self.maxval as u32;
+        let scale = |s: u16| ((s.min(self.maxval) as u32 * 65535 + m/2) / m) as u16;
+        encode(w, self.width, self.height, self.atoms.iter().map(|p| [scale(p.r), scale(p.g), scale(p.b), u16::MAX]))
+    }
+
+    /// Read a farbfeld image (with maxval 65535), dropping the alpha channel
+    pub fn from_farbfeld_reader(reader: impl Read) -> Result<Self, PpmError> {
+        let (width, height, pixels) = decode(reader)?;
+        Ok(Image::from_atoms(width, height, pixels.into_iter().map(|[r, g, b, _]| Pixel16::new(r, g, b)).collect()))
+    }
+
+    /// Save the image as farbfeld at `filepath` (the suffix is not automatically added)
+    pub fn save_as_farbfeld(&self, filepath: impl Into<PathBuf>) -> Result<(), PpmError> {
+        self.write_farbfeld_to(BufWriter::new(File::create(filepath.into())?))
+    }
+
+    pub fn load_farbfeld(filepath: impl Into<PathBuf>) -> Result<Self, PpmError> {
+        Self::from_farbfeld_reader(BufReader::new(File::open(filepath.into())?))
+    }
+}
+
+impl ImagePPM {
+    /// Write the image as farbfeld, fully opaque
+    pub fn write_farbfeld_to(&self, w: impl Write) -> Result<(), PpmError> {
+        encode(w, self.width, self.height, self.atoms.iter().map(|p| [p.r as u16 * 257, p.g as u16 * 257, p.b as u16 * 257, u16::MAX]))
+    }
+
+    /// Read a farbfeld image, scaling samples down to 8 bits and dropping the alpha channel
+    pub fn from_farbfeld_reader(reader: impl Read) -> Result<Self, PpmError> {
+        let (width, height, pixels) = decode(reader)?;
+        Ok(Image::from_atoms(width, height, pixels.into_iter().map(|[r, g, b, _]| Pixel::new(to_8(r), to_8(g), to_8(b))).collect()))
+    }
+
+    /// Save the image as farbfeld at `filepath` (the suffix is not automatically added)
+    pub fn save_as_farbfeld(&self, filepath: impl Into<PathBuf>) -> Result<(), PpmError> {
+        self.write_farbfeld_to(BufWriter::new(File::create(filepath.into())?))
+    }
+
+    pub fn load_farbfeld(filepath: impl Into<PathBuf>) -> Result<Self, PpmError> {
+        Self::from_farbfeld_reader(BufReader::new(File::open(filepath.into())?))
+    }
+}
@@ -0,0 +1,59 @@
+//! What to fill shapes with, when a single color isn't enough
+use crate::{color::Gradient, CoordF, Draw, ImagePPM, Pixel};
+
+/// Solid color, gradient or repeating texture. Used by the `fill_*_styled` drawing methods
+#[derive(Clone, Debug)]
+pub enum FillStyle {
+    Solid(Pixel),
+    /// `gradient` from 0 at `from` to 1 at `to`, flat past the ends
+    Linear { from: CoordF, to: CoordF, gradient: Gradient },
+    /// `gradient` from 0 at `center` to 1 at `radius` away and further
+    Radial { center: CoordF, radius: f64, gradient: Gradient },
+    /// The image repeated over and over, with its (0, 0) on the target's (0, 0)
+    Texture(ImagePPM),
+}
+
+impl FillStyle {
+    /// Color for pixel (x, y) of the image being filled
+    pub fn color_at(&self, x: usize, y: usize) -> Pixel {
+        let p = CoordF::new(x as f64, y as f64);
+        match self {
+            FillStyle::Solid(col) => *col,
+            FillStyle::Linear { from, to, gradient } => {
+                let dir = *to - *from;
+                let len2 = dir.dot(dir);
+                gradient.sample(if len2 == 0.0 { 0.0 } else { (p - *from).dot(dir) / len2 })
+            },
+            FillStyle::Radial { center, radius, gradient } => {
+                gradient.sample(if *radius <= 0.0 { 1.0 } else { p.distance(*center) / radius })
+            },
+            FillStyle::Texture(tex) => match tex.get(x % tex.width().max(1), y % tex.height().max(1)) {
+                Some(col) => *col,
+                None => Pixel::BLACK,
+            },
+        }
+    }
+}
+
+/// Stand-in for an image that hands the fill primitives a throwaway pixel to write their color to,
+/// after painting the real one with the style. Fills only ever write, so that's all it takes
+pub(crate) struct Styled<'a, I: ?Sized> {
+    pub(crate) img: &'a mut I,
+    pub(crate) style: &'a FillStyle,
+    pub(crate) sink: Pixel,
+}
+
+impl<I: Draw<Atom = Pixel> + ?Sized> Draw for Styled<'_, I> {
+    type Atom = Pixel;
+
+    fn width(&self) -> usize { self.img.width() }
+    fn height(&self) -> usize { self.img.height() }
+    fn origin(&self) -> crate::Origin { self.img.origin() }
+    fn wrap_mode(&self) -> crate::tile::WrapMode { self.img.wrap_mode() }
+    fn get(&self, x: usize, y: usize) -> Option<&Pixel> { self.img.get(x, y) }
+
+    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Pixel> {
+        *self.img.get_mut(x, y)? = self.style.color_at(x, y);
+        Some(&mut self.sink)
+    }
+}
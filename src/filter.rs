@@ -0,0 +1,273 @@
+//! Convolution kernels and the filters built on top of them
+use crate::{Coord, Draw, ImagePGM, ImagePPM, Pixel};
+
+/// Rectangular convolution kernel with odd sides. Weights are row-major and written like they'd
+/// look on screen: the first row is the top one
+#[derive(Clone, Debug)]
+pub struct Kernel {
+    width: usize,
+    height: usize,
+    weights: Vec<f64>,
+    /// Added to every channel after weighing, like the 128 in emboss
+    bias: f64,
+}
+
+impl Kernel {
+    /// Panics if the sides aren't odd or `weights` doesn't have `width*height` elements
+    pub fn new(width: usize, height: usize, weights: Vec<f64>) -> Self {
+        assert!(width % 2 == 1 && height % 2 == 1, "kernel sides must be odd, got {width}x{height}");
+        assert_eq!(weights.len(), width*height, "kernel needs exactly width*height weights");
+        Self { width, height, weights, bias: 0.0 }
+    }
+
+    pub fn with_bias(self, bias: f64) -> Self { Self { bias, ..self } }
+
+    /// Scales the weights so they add up to one (unless they add up to zero, like edge detectors)
+    pub fn normalized(mut self) -> Self {
+        let sum: f64 = self.weights.iter().sum();
+        if sum != 0.0 { self.weights.iter_mut().for_each(|w| *w /= sum); }
+        self
+    }
+
+    pub fn width(&self) -> usize { self.width }
+    pub fn height(&self) -> usize { self.height }
+    pub fn weights(&self) -> &[f64] { &self.weights }
+
+    /// Plain average of the `(2*radius + 1)` square around each pixel
+    pub fn box_blur(radius: usize) -> Self {
+        let side = 2*radius + 1;
+        Self::new(side, side, vec![1.0; side*side]).normalized()
+    }
+
+    /// Gaussian blur, covering three standard deviations on each side. A `sigma` that isn't
+    /// positive and finite gives the 1x1 identity kernel, so filtering leaves the image as is
+    pub fn gaussian(sigma: f64) -> Self {
+        if !(sigma.is_finite() && sigma > 0.0) { return Self::new(1, 1, vec![1.0]); }
+        let radius = (3.0*sigma).ceil().max(1.0) as isize;
+        let side = (2*radius + 1) as usize;
+        let weights = (-radius..=radius)
+            .flat_map(|y| (-radius..=radius).map(move |x| (x, y)))
+            .map(|(x, y)| (-((x*x + y*y) as f64)/(2.0*sigma*sigma)).exp())
+            .collect();
+        Self::new(side, side, weights).normalized()
+    }
+
+    pub fn sharpen() -> Self {
+        Self::new(3, 3, vec![
+             0.0, -1.0,  0.0,
+            -1.0,  5.0, -1.0,
+             0.0, -1.0,  0.0,
+        ])
+    }
+
+    /// Horizontal gradient, responds to vertical edges
+    pub fn sobel_x() -> Self {
+        Self::new(3, 3, vec![
+            -1.0, 0.0, 1.0,
+            -2.0, 0.0, 2.0,
+            -1.0, 0.0, 1.0,
+        ])
+    }
+
+    /// Vertical gradient (pointing up), responds to horizontal edges
+    pub fn sobel_y() -> Self {
+        Self::new(3, 3, vec![
+             1.0,  2.0,  1.0,
+             0.0,  0.0,  0.0,
+            -1.0, -2.0, -1.0,
+        ])
+    }
+
+    pub fn emboss() -> Self {
+        Self::new(3, 3, vec![
+            -2.0, -1.0, 0.0,
+            -1.0,  1.0, 1.0,
+             0.0,  1.0, 2.0,
+        ]).with_bias(128.0)
+    }
+}
+
+/// Summed-area table: the per-channel total over any rectangle of the image in four lookups,
+/// however big the rectangle. Built with `integral_image`
+#[derive(Clone, Debug)]
+pub struct IntegralImage<const C: usize> {
+    width: usize,
+    height: usize,
+    /// `sums[x + y*(width + 1)]` adds up every pixel left of `x` and below `y`
+    sums: Vec<[u64; C]>,
+}
+
+impl<const C: usize> IntegralImage<C> {
+    fn new(width: usize, height: usize, channels: impl Fn(usize, usize) -> [u64; C]) -> Self {
+        let row = width + 1;
+        let mut sums = vec![[0; C]; row*(height + 1)];
+        for y in 0..height {
+        for x in 0..width {
+            let v = channels(x, y);
+            sums[(x + 1) + (y + 1)*row] = std::array::from_fn(|i| {
+                v[i] + sums[x + (y + 1)*row][i] + sums[(x + 1) + y*row][i] - sums[x + y*row][i]
+            });
+        }
+        }
+        Self { width, height, sums }
+    }
+
+    pub fn width(&self) -> usize { self.width }
+    pub fn height(&self) -> usize { self.height }
+
+    /// Per-channel sum of the pixels with `min.x <= x < max.x` and `min.y <= y < max.y`. The
+    /// rectangle is clipped to the image
+    pub fn sum(&self, min: Coord, max: Coord) -> [u64; C] {
+        let (x1, y1) = (max.x.min(self.width), max.y.min(self.height));
+        let (x0, y0) = (min.x.min(x1), min.y.min(y1));
+        let at = |x: usize, y: usize| self.sums[x + y*(self.width + 1)];
+        let (a, b, c, d) = (at(x1, y1), at(x0, y0), at(x0, y1), at(x1, y0));
+        std::array::from_fn(|i| a[i] + b[i] - c[i] - d[i])
+    }
+
+    /// Per-channel average over the same rectangle as `sum`, or `None` if it has no pixels
+    pub fn mean(&self, min: Coord, max: Coord) -> Option<[f64; C]> {
+        let n = max.x.min(self.width).saturating_sub(min.x) * max.y.min(self.height).saturating_sub(min.y);
+        let sum = self.sum(min, max);
+        (n > 0).then(|| std::array::from_fn(|i| sum[i] as f64 / n as f64))
+    }
+
+    /// Corners (as taken by `sum`) of the `(2*radius + 1)` square around `c`, clipped to the image
+    pub(crate) fn window(&self, c: Coord, radius: usize) -> (Coord, Coord) {
+        let min = Coord::new(c.x.saturating_sub(radius), c.y.saturating_sub(radius));
+        let max = Coord::new((c.x + radius + 1).min(self.width), (c.y + radius + 1).min(self.height));
+        (min, max)
+    }
+}
+
+impl ImagePGM {
+    pub fn integral_image(&self) -> IntegralImage<1> {
+        IntegralImage::new(self.width, self.height, |x, y| [self[(x, y)] as u64])
+    }
+}
+
+impl ImagePPM {
+    /// Convolve every channel with `k`, returning the raw (unclamped, before bias) sums. Pixels
+    /// past the edges are taken to be copies of the closest edge pixel
+    fn convolve(&self, k: &Kernel) -> Vec<[f64; 3]> {
+        let (hw, hh) = ((k.width / 2) as isize, (k.height / 2) as isize);
+        let (w, h) = (self.width as isize, self.height as isize);
+
+        let mut out = Vec::with_capacity(self.atoms.len());
+        for y in (0..h).rev() {
+        for x in 0..w {
+            let mut acc = [0.0; 3];
+            for (i, &weight) in k.weights.iter().enumerate() {
+                if weight == 0.0 { continue; }
+                let (dx, dy) = (i as isize % k.width as isize - hw, hh - i as isize / k.width as isize);
+                let p = self.get((x + dx).clamp(0, w - 1) as usize, (y + dy).clamp(0, h - 1) as usize).unwrap();
+                acc[0] += weight * p.r as f64;
+                acc[1] += weight * p.g as f64;
+                acc[2] += weight * p.b as f64;
+            }
+            out.push(acc);
+        }
+        }
+        out
+    }
+
+    /// Filtered copy of the image. Edges are handled by extending the border pixels outwards
+    pub fn apply_kernel(&self, k: &Kernel) -> ImagePPM {
+        let to_u8 = |v: f64| (v + k.bias).round().clamp(0.0, 255.0) as u8;
+        let atoms = self.convolve(k).into_iter().map(|[r, g, b]| Pixel::new(to_u8(r), to_u8(g), to_u8(b))).collect();
+        ImagePPM::from_atoms(self.width, self.height, atoms)
+    }
+
+    /// Gaussian blur done as two 1D passes (horizontal then vertical), which is O(r) per pixel
+    /// instead of the O(r²) of `apply_kernel(&Kernel::gaussian(sigma))`. Same edge handling
+    pub fn gaussian_blur(&self, sigma: f64) -> ImagePPM {
+        if self.atoms.is_empty() || sigma <= 0.0 { return self.clone(); }
+        let radius = (3.0*sigma).ceil() as isize;
+        let weights: Vec<f64> = (-radius..=radius).map(|d| (-((d*d) as f64)/(2.0*sigma*sigma)).exp()).collect();
+        let total: f64 = weights.iter().sum();
+
+        let (w, h) = (self.width as isize, self.height as isize);
+        let pass = |src: &[[f64; 3]], step: (isize, isize)| -> Vec<[f64; 3]> {
+            let mut dst = vec![[0.0; 3]; src.len()];
+            for y in 0..h {
+            for x in 0..w {
+                let acc = &mut dst[(x + y*w) as usize];
+                for (i, &weight) in weights.iter().enumerate() {
+                    let d = i as isize - radius;
+                    let (sx, sy) = ((x + d*step.0).clamp(0, w - 1), (y + d*step.1).clamp(0, h - 1));
+                    let p = src[(sx + sy*w) as usize];
+                    for c in 0..3 { acc[c] += weight/total * p[c]; }
+                }
+            }
+            }
+            dst
+        };
+
+        let src: Vec<[f64; 3]> = self.atoms.iter().map(|p| [p.r as f64, p.g as f64, p.b as f64]).collect();
+        let blurred = pass(&pass(&src, (1, 0)), (0, 1));
+        let to_u8 = |v: f64| v.round().clamp(0.0, 255.0) as u8;
+        let atoms = blurred.into_iter().map(|[r, g, b]| Pixel::new(to_u8(r), to_u8(g), to_u8(b))).collect();
+        ImagePPM::from_atoms(self.width, self.height, atoms)
+    }
+
+    /// Edge detection: magnitude of the Sobel gradient, per channel
+    pub fn sobel_edges(&self) -> ImagePPM {
+        let (gx, gy) = (self.convolve(&Kernel::sobel_x()), self.convolve(&Kernel::sobel_y()));
+        let mag = |a: f64, b: f64| (a*a + b*b).sqrt().round().min(255.0) as u8;
+        let atoms = gx.iter().zip(&gy).map(|(a, b)| Pixel::new(mag(a[0], b[0]), mag(a[1], b[1]), mag(a[2], b[2]))).collect();
+        ImagePPM::from_atoms(self.width, self.height, atoms)
+    }
+
+    /// Red, green and blue summed-area table of the image
+    pub fn integral_image(&self) -> IntegralImage<3> {
+        IntegralImage::new(self.width, self.height, |x, y| {
+            let p = self[(x, y)];
+            [p.r as u64, p.g as u64, p.b as u64]
+        })
+    }
+
+    /// Same as `apply_kernel(&Kernel::box_blur(radius))` but O(1) per pixel whatever the radius,
+    /// using `integral_image`. Near the edges it averages only the part of the square inside the
+    /// image instead of extending the border pixels
+    pub fn box_blur_sat(&self, radius: usize) -> ImagePPM {
+        let table = self.integral_image();
+        ImagePPM::from_fn(self.width, self.height, |c| {
+            let (min, max) = table.window(c, radius);
+            let [r, g, b] = table.mean(min, max).unwrap().map(|v| v.round() as u8);
+            Pixel::new(r, g, b)
+        })
+    }
+
+    /// Half the size (rounding up), each pixel being the average of the 2x2 block it replaces.
+    /// Blocks on the last row or column of odd-sized images only average what's there
+    pub fn half_size(&self) -> ImagePPM {
+        let (w, h) = (self.width, self.height);
+        ImagePPM::from_fn(w.div_ceil(2), h.div_ceil(2), |c| {
+            let (x0, y0) = (2*c.x, 2*c.y);
+            let (x1, y1) = ((x0 + 2).min(w), (y0 + 2).min(h));
+            let mut sum = [0u32; 3];
+            for y in y0..y1 {
+            for x in x0..x1 {
+                let p = self[(x, y)];
+                sum[0] += p.r as u32; sum[1] += p.g as u32; sum[2] += p.b as u32;
+            }
+            }
+            let n = ((x1 - x0)*(y1 - y0)) as u32;
+            let [r, g, b] = sum.map(|v| ((v + n/2) / n) as u8);
+            Pixel::new(r, g, b)
+        })
+    }
+
+    /// The image followed by up to `levels` successive `half_size` versions of it, stopping
+    /// early once they're down to a single pixel. Handy for thumbnails and multi-scale effects
+    pub fn pyramid(&self, levels: usize) -> Vec<ImagePPM> {
+        let mut out = vec![self.clone()];
+        for _ in 0..levels {
+            let last = out.last().unwrap();
+            if last.width <= 1 && last.height <= 1 { break; }
+            let next = last.half_size();
+            out.push(next);
+        }
+        out
+    }
+}
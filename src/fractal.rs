@@ -0,0 +1,63 @@
+//! Escape time fractals, rendered over all cores. Points that escape are colored by a gradient
+//! (with smooth coloring, so there's no banding); points in the set are black
+use crate::{color::Gradient, CoordF, ImagePPM, Pixel};
+
+/// Rectangle of the complex plane that gets stretched over the whole image: real part along x,
+/// imaginary part along y (growing upwards, like the image's y)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub min: CoordF,
+    pub max: CoordF,
+}
+
+impl Viewport {
+    pub fn new(min: impl Into<CoordF>, max: impl Into<CoordF>) -> Self { Self { min: min.into(), max: max.into() } }
+
+    /// `width` units wide around `center`, and as tall as it takes for a `w`x`h` image to have
+    /// square pixels
+    pub fn centered(center: impl Into<CoordF>, width: f64, w: usize, h: usize) -> Self {
+        let center = center.into();
+        let half = CoordF::new(width / 2.0, width * h as f64 / w.max(1) as f64 / 2.0);
+        Self { min: center - half, max: center + half }
+    }
+
+    /// The whole Mandelbrot set
+    pub const MANDELBROT: Self = Self { min: CoordF::new(-2.5, -1.25), max: CoordF::new(1.0, 1.25) };
+
+    /// Point of the plane at the center of pixel `(x, y)` of a `w`x`h` image
+    pub fn point_at(&self, x: usize, y: usize, w: usize, h: usize) -> CoordF {
+        CoordF::new(
+            self.min.x + (x as f64 + 0.5) / w as f64 * (self.max.x - self.min.x),
+            self.min.y + (y as f64 + 0.5) / h as f64 * (self.max.y - self.min.y),
+        )
+    }
+}
+
+/// Iterates `z = z² + c` and colors by how fast it escapes. The fractional part smooths the bands
+/// between iteration counts out
+fn escape_color(mut z: CoordF, c: CoordF, max_iter: usize, gradient: &Gradient) -> Pixel {
+    for i in 0..max_iter {
+        z = CoordF::new(z.x*z.x - z.y*z.y + c.x, 2.0*z.x*z.y + c.y);
+        let norm2 = z.dot(z);
+        // A big bailout radius makes the smoothing accurate
+        if norm2 > 256.0 {
+            // i + 1 - log2(ln |z|), with |z| still squared
+            let smooth = i as f64 + 2.0 - norm2.ln().ln() / std::f64::consts::LN_2;
+            return gradient.sample(smooth / max_iter as f64);
+        }
+    }
+    Pixel::BLACK
+}
+
+/// The Mandelbrot set: `c` is the pixel's point, `z` starts at 0
+pub fn mandelbrot(width: usize, height: usize, viewport: Viewport, max_iter: usize, gradient: &Gradient) -> ImagePPM {
+    ImagePPM::par_from_fn(width, height, |p| {
+        escape_color(CoordF::new(0.0, 0.0), viewport.point_at(p.x, p.y, width, height), max_iter, gradient)
+    })
+}
+
+/// The Julia set for `c`: `z` starts at the pixel's point. Try `c = (-0.8, 0.156)`
+pub fn julia(width: usize, height: usize, viewport: Viewport, c: impl Into<CoordF>, max_iter: usize, gradient: &Gradient) -> ImagePPM {
+    let c = c.into();
+    ImagePPM::par_from_fn(width, height, |p| escape_color(viewport.point_at(p.x, p.y, width, height), c, max_iter, gradient))
+}
@@ -1,5 +1,53 @@
 pub mod utils;
-use std::{fs::File, io::{BufWriter, Write}, ops::{self, Add, Sub}, path::PathBuf};
+pub mod adjust;
+pub mod analysis;
+pub mod anim;
+mod bmp;
+pub mod builder;
+pub mod clip;
+pub mod color;
+mod compare;
+pub mod composite;
+pub mod delaunay;
+mod effects;
+mod error;
+mod farbfeld;
+pub mod fill;
+pub mod filter;
+pub mod palette;
+mod font;
+mod obj;
+pub mod fractal;
+mod parse;
+pub mod parallel;
+pub mod path;
+mod patterns;
+pub mod plot;
+pub mod noise;
+pub mod origin;
+#[cfg(feature = "png")]
+pub mod png;
+#[cfg(feature = "qoi")]
+mod qoi;
+pub mod random;
+pub mod render3d;
+pub mod scene;
+mod seam;
+pub mod sprite;
+pub mod stats;
+pub mod tile;
+pub mod transform;
+pub mod turtle;
+pub mod view;
+pub mod voronoi;
+mod warp;
+use std::{fs::File, io::{BufReader, BufWriter, Read, Write}, ops::{self, Add, Sub}, path::PathBuf};
+pub use error::PpmError;
+pub use origin::Origin;
+use fill::{FillStyle, Styled};
+use path::{Path, StrokeStyle};
+use tile::WrapMode;
+use view::{ImageView, ImageViewMut};
 
 /// Basic RGB Pixel struct
 #[derive(Clone, Copy, Debug)]
@@ -19,12 +67,149 @@ impl Coord {
     pub fn new(x: usize, y:usize) -> Self { Self { x, y } }
     pub fn abs(&self) -> f64 { ((self.x*self.x + self.y*self.y) as f64).sqrt() }
     pub fn distance(&self, rhs: Self) -> f64 {
-        let dx = (self.x as isize - rhs.x as isize).abs() as usize;
-        let dy = (self.y as isize - rhs.y as isize).abs() as usize;
+        let dx = (self.x as isize - rhs.x as isize).unsigned_abs();
+        let dy = (self.y as isize - rhs.y as isize).unsigned_abs();
         Coord::new(dx, dy).abs()
     }
 }
 
+impl Coord {
+    /// `None` if either component would go below zero
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        Some(Self { x: self.x.checked_sub(rhs.x)?, y: self.y.checked_sub(rhs.y)? })
+    }
+
+    /// Components that would go below zero stop at zero instead
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self { x: self.x.saturating_sub(rhs.x), y: self.y.saturating_sub(rhs.y) }
+    }
+}
+
+/// Signed version of `Coord`, for geometry that can go past the left or bottom edges
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ICoord {
+    pub x: isize,
+    pub y: isize
+}
+
+impl ICoord {
+    pub fn new(x: isize, y: isize) -> Self { Self { x, y } }
+    pub fn abs(&self) -> f64 { ((self.x*self.x + self.y*self.y) as f64).sqrt() }
+    pub fn distance(&self, rhs: Self) -> f64 { (*self - rhs).abs() }
+}
+
+impl From<Coord> for ICoord {
+    fn from(c: Coord) -> Self { Self { x: c.x as isize, y: c.y as isize } }
+}
+
+/// Fails if either component is negative
+impl TryFrom<ICoord> for Coord {
+    type Error = std::num::TryFromIntError;
+    fn try_from(c: ICoord) -> Result<Self, Self::Error> { Ok(Self { x: c.x.try_into()?, y: c.y.try_into()? }) }
+}
+
+impl Add for ICoord {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output { Self { x: self.x + rhs.x, y: self.y + rhs.y, } }
+}
+impl Sub for ICoord {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output { Self { x: self.x - rhs.x, y: self.y - rhs.y, } }
+}
+impl ops::Neg for ICoord {
+    type Output = Self;
+    fn neg(self) -> Self::Output { Self { x: -self.x, y: -self.y } }
+}
+
+/// Floating point coordinates, for sub-pixel geometry. Pixel (x, y) sits at exactly (x, y)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CoordF {
+    pub x: f64,
+    pub y: f64
+}
+
+impl CoordF {
+    pub const fn new(x: f64, y: f64) -> Self { Self { x, y } }
+    pub fn length(&self) -> f64 { self.x.hypot(self.y) }
+    pub fn distance(&self, rhs: Self) -> f64 { (*self - rhs).length() }
+    pub fn dot(&self, rhs: Self) -> f64 { self.x*rhs.x + self.y*rhs.y }
+    /// z component of the 3D cross product, positive if `rhs` is counterclockwise from `self`
+    pub fn cross(&self, rhs: Self) -> f64 { self.x*rhs.y - self.y*rhs.x }
+
+    /// Linear interpolation: `self` at t = 0, `rhs` at t = 1
+    pub fn lerp(&self, rhs: Self, t: f64) -> Self { *self + (rhs - *self)*t }
+
+    /// Rotate `angle` radians counterclockwise around the origin
+    pub fn rotate(&self, angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(self.x*cos - self.y*sin, self.x*sin + self.y*cos)
+    }
+
+    /// Rotate `angle` radians counterclockwise around `center`
+    pub fn rotate_around(&self, center: Self, angle: f64) -> Self { (*self - center).rotate(angle) + center }
+
+    /// Rounds components that are a hair away from an integer (like `cos(PI/2)` isn't quite 0) to
+    /// it, so they don't land a pixel off once floored
+    pub(crate) fn snapped(self) -> Self {
+        let snap = |v: f64| if (v - v.round()).abs() < 1e-9 { v.round() } else { v };
+        Self::new(snap(self.x), snap(self.y))
+    }
+
+    /// Nearest pixel, which may be negative
+    pub fn round(&self) -> ICoord { ICoord::new(self.x.round() as isize, self.y.round() as isize) }
+
+    /// Nearest pixel, `None` if it'd be negative
+    pub fn to_coord(&self) -> Option<Coord> { self.round().try_into().ok() }
+}
+
+impl From<Coord> for CoordF {
+    fn from(c: Coord) -> Self { Self::new(c.x as f64, c.y as f64) }
+}
+impl From<ICoord> for CoordF {
+    fn from(c: ICoord) -> Self { Self::new(c.x as f64, c.y as f64) }
+}
+impl From<(f64, f64)> for CoordF {
+    fn from((x, y): (f64, f64)) -> Self { Self::new(x, y) }
+}
+
+impl Add for CoordF {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output { Self { x: self.x + rhs.x, y: self.y + rhs.y, } }
+}
+impl Sub for CoordF {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output { Self { x: self.x - rhs.x, y: self.y - rhs.y, } }
+}
+impl ops::Mul<f64> for CoordF {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self::Output { Self { x: self.x * rhs, y: self.y * rhs } }
+}
+impl ops::Neg for CoordF {
+    type Output = Self;
+    fn neg(self) -> Self::Output { Self { x: -self.x, y: -self.y } }
+}
+
+/// Indices of the pixels whose (integer) position lies within `lo..=hi`, clipped to `0..len`
+fn span(lo: f64, hi: f64, len: usize) -> ops::Range<usize> {
+    let start = lo.ceil().max(0.0) as usize;
+    let end = (hi.floor() + 1.0).clamp(0.0, len as f64) as usize;
+    start..end.max(start)
+}
+
+/// `span` for drawing along an axis `len` pixels long. When wrapping around nothing is clipped,
+/// since every position lands somewhere in the image, but it's cut to at most `len` positions so
+/// huge shapes don't cost more than the image itself
+fn draw_span(lo: f64, hi: f64, len: usize, mode: WrapMode) -> ops::Range<isize> {
+    match mode {
+        WrapMode::Clip => { let s = span(lo, hi, len); s.start as isize..s.end as isize },
+        WrapMode::Wrap => {
+            let start = lo.ceil() as isize;
+            let end = ((hi.floor() + 1.0) as isize).max(start);
+            start..end.min(start.saturating_add(len as isize))
+        },
+    }
+}
+
 impl Add for Coord {
     type Output = Self;
     fn add(self, rhs: Self) -> Self::Output { Self { x: self.x + rhs.x, y: self.y + rhs.y, } }
@@ -34,9 +219,31 @@ impl Sub for Coord {
     fn sub(self, rhs: Self) -> Self::Output { Self { x: self.x - rhs.x, y: self.y - rhs.y, } }
 }
 
+/// Saturates at 255 instead of overflowing
 impl ops::Mul<u8> for Pixel {
     type Output = Self;
-    fn mul(self, rhs: u8) -> Self::Output { Self { r : self.r * rhs, g : self.g * rhs, b : self.b * rhs, } }
+    fn mul(self, rhs: u8) -> Self::Output { self.saturating_mul(rhs) }
+}
+
+/// Scales every channel (think brightness), rounding and clamping to 0..=255
+impl ops::Mul<f32> for Pixel {
+    type Output = Self;
+    fn mul(self, rhs: f32) -> Self::Output {
+        let f = |c: u8| (c as f32 * rhs).round().clamp(0.0, 255.0) as u8;
+        Self::new(f(self.r), f(self.g), f(self.b))
+    }
+}
+
+/// Channel by channel, saturating at 255
+impl Add for Pixel {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output { self.additive(rhs) }
+}
+
+/// Channel by channel, saturating at 0
+impl Sub for Pixel {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output { self.zip_channels(rhs, u8::saturating_sub) }
 }
 
 
@@ -50,60 +257,266 @@ impl Pixel {
     pub const PURPLE: Self = Self::new(255, 0, 255);
 
     pub const fn new(r: u8, g: u8, b: u8) -> Self { Self { r, g, b } }
+
+    /// Perceived brightness, using the Rec. 601 weights (same ones `ppmtopgm` uses)
+    pub fn luminance(&self) -> u8 {
+        (0.299*self.r as f64 + 0.587*self.g as f64 + 0.114*self.b as f64).round() as u8
+    }
+
+    /// Applies `f` channel by channel
+    fn zip_channels(self, other: Self, f: impl Fn(u8, u8) -> u8) -> Self {
+        Self::new(f(self.r, other.r), f(self.g, other.g), f(self.b, other.b))
+    }
+
+    /// Linear interpolation: `t = 0` is `self`, `t = 1` is `other`. `t` is clamped to 0..=1
+    pub fn lerp(self, other: Self, t: f64) -> Self { Blend::blend(self, other, t) }
+
+    /// Paints `over` on top of `self` with the given opacity (0..=1). Same as `lerp`
+    pub fn blend(self, over: Self, alpha: f64) -> Self { Blend::blend(self, over, alpha) }
+
+    pub fn saturating_mul(self, rhs: u8) -> Self {
+        Self::new(self.r.saturating_mul(rhs), self.g.saturating_mul(rhs), self.b.saturating_mul(rhs))
+    }
+
+    /// Additive blending (light adding up), saturating at 255
+    pub fn additive(self, other: Self) -> Self { self.zip_channels(other, u8::saturating_add) }
+
+    /// Multiplicative blending: white leaves colors untouched, black makes everything black
+    pub fn multiply(self, other: Self) -> Self {
+        self.zip_channels(other, |a, b| ((a as u16 * b as u16 + 127) / 255) as u8)
+    }
+
+    /// The inverse of `multiply`: black leaves colors untouched, white makes everything white
+    pub fn screen(self, other: Self) -> Self {
+        self.zip_channels(other, |a, b| 255 - (((255 - a as u16) * (255 - b as u16) + 127) / 255) as u8)
+    }
+}
+
+/// RGB pixel with up to 16 bits per channel, for `ImagePPM16`
+#[derive(Clone, Copy, Debug)]
+pub struct Pixel16 {
+    pub r: u16,
+    pub g: u16,
+    pub b: u16
+}
+
+impl Pixel16 {
+    pub const fn new(r: u16, g: u16, b: u16) -> Self { Self { r, g, b } }
+}
+
+/// RGB pixel with an alpha channel (0 is fully transparent, 255 fully opaque). Colors are
+/// straight, not premultiplied
+#[derive(Clone, Copy, Debug)]
+pub struct PixelRgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8
+}
+
+impl PixelRgba {
+    pub const TRANSPARENT: Self = Self::new(0, 0, 0, 0);
+
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self { Self { r, g, b, a } }
+    pub const fn rgb(&self) -> Pixel { Pixel::new(self.r, self.g, self.b) }
+
+    /// Source-over compositing of `self` on top of an opaque pixel
+    pub fn over(self, dst: Pixel) -> Pixel { dst.blend(self.rgb(), self.a as f64 / 255.0) }
+
+    /// Source-over compositing of `self` on top of another, possibly transparent, pixel
+    pub fn over_rgba(self, dst: PixelRgba) -> PixelRgba {
+        let (sa, da) = (self.a as f64 / 255.0, dst.a as f64 / 255.0);
+        let out_a = sa + da*(1.0 - sa);
+        if out_a == 0.0 { return Self::TRANSPARENT; }
+
+        let mix = |s: u8, d: u8| ((s as f64*sa + d as f64*da*(1.0 - sa))/out_a).round() as u8;
+        Self::new(mix(self.r, dst.r), mix(self.g, dst.g), mix(self.b, dst.b), (out_a*255.0).round() as u8)
+    }
+}
+
+impl From<Pixel> for PixelRgba {
+    fn from(p: Pixel) -> Self { Self::new(p.r, p.g, p.b, 255) }
+}
+
+/// Gray value with an alpha channel, the grayscale counterpart of `PixelRgba` (straight alpha)
+#[derive(Clone, Copy, Debug)]
+pub struct PixelGrayAlpha {
+    pub v: u8,
+    pub a: u8
+}
+
+impl PixelGrayAlpha {
+    pub const TRANSPARENT: Self = Self::new(0, 0);
+
+    pub const fn new(v: u8, a: u8) -> Self { Self { v, a } }
+}
+
+impl From<u8> for PixelGrayAlpha {
+    fn from(v: u8) -> Self { Self::new(v, 255) }
+}
+
+/// How the ends of thick lines are finished
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    /// Stops right at the endpoints
+    Butt,
+    /// Extends past the endpoints by half the width
+    Square,
+    /// Half a disc around each endpoint
+    Round,
 }
 
-pub trait PpmFormat {
+/// Glyphs for marking points, drawn with `draw_marker`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkerShape {
+    /// Filled circle
+    Dot,
+    /// Filled square
+    Square,
+    /// Circle outline
+    Circle,
+    /// Diagonal cross, like an x
+    Cross,
+    /// Upright cross, like a +
+    Plus,
+    /// Filled square standing on a corner
+    Diamond,
+    /// Filled five pointed star, pointing up
+    Star,
+}
+
+/// How to sample the source image when resizing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResizeFilter {
+    /// Blocky, keeps the exact original colors
+    Nearest,
+    /// Linear interpolation between the four closest pixels
+    Bilinear,
+}
+
+/// Atoms that can be partially painted over, e.g. for anti-aliasing
+pub trait Blend: Copy {
+    /// Mix `over` on top of `self` with opacity `alpha` (0.0 leaves `self` untouched, 1.0 is
+    /// fully `over`)
+    fn blend(self, over: Self, alpha: f64) -> Self;
+}
+
+impl Blend for u8 {
+    fn blend(self, over: Self, alpha: f64) -> Self {
+        let alpha = alpha.clamp(0.0, 1.0);
+        (self as f64 * (1.0 - alpha) + over as f64 * alpha).round() as u8
+    }
+}
+
+impl Blend for Pixel {
+    fn blend(self, over: Self, alpha: f64) -> Self {
+        Pixel::new(self.r.blend(over.r, alpha), self.g.blend(over.g, alpha), self.b.blend(over.b, alpha))
+    }
+}
+
+/// Treats `over` as a layer with its own alpha further multiplied by `alpha`
+impl Blend for PixelRgba {
+    fn blend(self, over: Self, alpha: f64) -> Self {
+        let alpha = alpha.clamp(0.0, 1.0);
+        PixelRgba { a: (over.a as f64 * alpha).round() as u8, ..over }.over_rgba(self)
+    }
+}
+
+/// Same as for `PixelRgba`, going through it
+impl Blend for PixelGrayAlpha {
+    fn blend(self, over: Self, alpha: f64) -> Self {
+        let rgba = |p: Self| PixelRgba::new(p.v, p.v, p.v, p.a);
+        let out = rgba(self).blend(rgba(over), alpha);
+        Self::new(out.r, out.a)
+    }
+}
+
+impl Blend for Pixel16 {
+    fn blend(self, over: Self, alpha: f64) -> Self {
+        let alpha = alpha.clamp(0.0, 1.0);
+        let mix = |a: u16, b: u16| (a as f64 * (1.0 - alpha) + b as f64 * alpha).round() as u16;
+        Pixel16::new(mix(self.r, over.r), mix(self.g, over.g), mix(self.b, over.b))
+    }
+}
+
+/// Bits can't be half painted, so they snap to whichever side has more weight
+impl Blend for bool {
+    fn blend(self, over: Self, alpha: f64) -> Self { if alpha >= 0.5 { over } else { self } }
+}
+
+/// Anything that can be drawn on: all it takes is a size and access to single pixels. Images get
+/// it through `PpmFormat`, and views of part of an image (see `view`) implement it directly
+pub trait Draw {
     type Atom: Copy;
 
     // Minimum implementation
-    fn new(width: usize, height: usize, bg_color: Self::Atom) -> Self;
     fn width(&self) -> usize;
     fn height(&self) -> usize;
-    fn atoms(&self) -> &Vec<Self::Atom>;
-    fn atoms_mut(&mut self) -> &mut Vec<Self::Atom>;
 
-    // Default implementations
     /// Get value of pixel at coordinates (bottom left is (0, 0)). None value means it was OOB
-    fn get(&self, x: usize, y: usize) -> Option<&Self::Atom> {
-        if x >= self.width() || y >= self.height() { return None; }
+    fn get(&self, x: usize, y: usize) -> Option<&Self::Atom>;
 
-        Some(&self.atoms()[x + (self.height() - y - 1)*self.width()])
+    /// Get mutable access to pixel at coordinates (bottom left is (0, 0)). None value means it was OOB
+    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Self::Atom>;
+
+    // Default implementations
+    /// Which corner (0, 0) is: the bottom left one unless wrapped in `origin::TopLeft`
+    fn origin(&self) -> Origin { Origin::BottomLeft }
+
+    /// What happens to shapes drawn past the edges: they're clipped unless wrapped in `tile::Tiled`
+    fn wrap_mode(&self) -> WrapMode { WrapMode::Clip }
+
+    /// Like `get_mut`, but with signed coordinates. Out of bounds ones are None, or come back in
+    /// from the opposite edge if the image wraps around
+    fn get_mut_signed(&mut self, x: isize, y: isize) -> Option<&mut Self::Atom> {
+        let (w, h) = (self.width() as isize, self.height() as isize);
+        let (x, y) = match self.wrap_mode() {
+            WrapMode::Wrap if w > 0 && h > 0 => (x.rem_euclid(w), y.rem_euclid(h)),
+            _ if x < 0 || y < 0 => return None,
+            _ => (x, y),
+        };
+        self.get_mut(x as usize, y as usize)
     }
 
-    /// Get mutable access to pixel at coordinates (bottom left is (0, 0)). None value means it was OOB
-    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Self::Atom> {
-        if x >= self.width() || y >= self.height() { return None; }
-        let i = x + (self.height() - y - 1)*self.width();
-        Some(&mut self.atoms_mut()[i])
+    /// Set the pixel at (x, y) if it's inside the image, silently do nothing otherwise (or wrap
+    /// around, see `wrap_mode`). Takes signed coordinates so shapes can hang off any edge: every
+    /// drawing primitive goes through this, so none of them panic for being partially outside
+    fn put(&mut self, x: isize, y: isize, col: Self::Atom) {
+        if let Some(p) = self.get_mut_signed(x, y) { *p = col; }
     }
 
-    /// Draw a circle (taxicab distance metric). Assumes that it will fit, will likely panic if it
-    /// doesn't
+    /// Draw a circle (taxicab distance metric). Whatever doesn't fit is clipped
     fn draw_circle(&mut self, center: Coord, radius: usize, col: Self::Atom) {
         let r = radius as isize / 2;
         for dx in -r..r {
         for dy in -r..r {
-            *self.get_mut(
-                (center.x as isize + dx).max(0) as usize,
-                (center.y as isize + dy).max(0) as usize
-            ).unwrap() = col;
+            self.put(center.x as isize + dx, center.y as isize + dy, col);
         }
         }
     }
 
-    /// Written by Gerard, uses the parametric equation to fill pixels
-    fn draw_line(&mut self, a: Coord, b: Coord, col: Self::Atom) {
-        let (ax, ay, bx, by) = (a.x as f64, a.y as f64, b.x as f64, b.y as f64);
+    /// Written by Gerard, uses the parametric equation to fill pixels. Endpoints can be anything
+    /// that converts to `CoordF`, so `Coord`, `ICoord` or sub-pixel positions. Only the part inside
+    /// the image gets walked, so endpoints can be as far off as they like (unless it wraps around)
+    fn draw_line(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, col: Self::Atom) {
+        let (a, b) = match self.wrap_mode() {
+            WrapMode::Clip => {
+                let Some(clipped) = clip::clip_line(a, b, clip::Rect::of_image(self.width(), self.height())) else { return };
+                clipped
+            },
+            WrapMode::Wrap => (a.into(), b.into()),
+        };
+        let (ax, ay, bx, by) = (a.x, a.y, b.x, b.y);
         let dist = ((ax-bx)*(ax-bx) + (ay-by)*(ay-by)).sqrt();
         let mut t = 0.0;
-        while t <= dist {
+        while t < dist {
             let x = ax + (bx - ax)*(t / dist);
             let y = ay + (by - ay)*(t / dist);
-            *self.get_mut(x as usize, y as usize).unwrap() = col;
+            self.put(x.floor() as isize, y.floor() as isize, col);
             t += 1.0;
         }
 
-        *self.get_mut(b.x, b.y).unwrap() = col;
+        self.put(bx.floor() as isize, by.floor() as isize, col);
     }
 
     /// Adapting Gerard's, uses the parametric equation to fill in circles instead of pixels
@@ -118,75 +531,1148 @@ pub trait PpmFormat {
             t += 1.0;
         }
 
-        *self.get_mut(b.x, b.y).unwrap() = col;
+        self.put(b.x as isize, b.y as isize, col);
+    }
+
+    /// Fill the axis-aligned rectangle with corners `a` and `b` (both inclusive, in any order).
+    /// Whatever falls outside of the image is ignored
+    fn fill_rect(&mut self, a: Coord, b: Coord, col: Self::Atom) {
+        let mode = self.wrap_mode();
+        let xs = draw_span(a.x.min(b.x) as f64, a.x.max(b.x) as f64, self.width(), mode);
+        let ys = draw_span(a.y.min(b.y) as f64, a.y.max(b.y) as f64, self.height(), mode);
+        for y in ys {
+        for x in xs.clone() {
+            self.put(x, y, col);
+        }
+        }
+    }
+
+    /// Draw the one pixel wide outline of the rectangle with corners `a` and `b` (both inclusive,
+    /// in any order)
+    fn draw_rect(&mut self, a: Coord, b: Coord, col: Self::Atom) {
+        let (lo, hi) = (Coord::new(a.x.min(b.x), a.y.min(b.y)), Coord::new(a.x.max(b.x), a.y.max(b.y)));
+        self.fill_rect(lo, Coord::new(hi.x, lo.y), col);
+        self.fill_rect(Coord::new(lo.x, hi.y), hi, col);
+        self.fill_rect(lo, Coord::new(lo.x, hi.y), col);
+        self.fill_rect(Coord::new(hi.x, lo.y), hi, col);
+    }
+
+    /// Fill the triangle `abc` (in any winding order), edges included. Uses the barycentric
+    /// (edge function) test over the bounding box, clipped to the image
+    fn fill_triangle(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, c: impl Into<CoordF>, col: Self::Atom) {
+        let (a, b, c) = (a.into(), b.into(), c.into());
+        let edge = |p: CoordF, q: CoordF, r: CoordF| (q - p).cross(r - p);
+        let area = edge(a, b, c);
+        if area == 0.0 { return self.draw_triangle(a, b, c, col); }
+
+        let mode = self.wrap_mode();
+        let xs = draw_span(a.x.min(b.x).min(c.x), a.x.max(b.x).max(c.x), self.width(), mode);
+        let ys = draw_span(a.y.min(b.y).min(c.y), a.y.max(b.y).max(c.y), self.height(), mode);
+        for y in ys {
+        for x in xs.clone() {
+            let p = CoordF::new(x as f64, y as f64);
+            let ws = [edge(b, c, p), edge(c, a, p), edge(a, b, p)];
+            if ws.iter().all(|&w| w*area.signum() >= 0.0) {
+                self.put(x, y, col);
+            }
+        }
+        }
+    }
+
+    /// Draw the outline of the triangle `abc`
+    fn draw_triangle(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, c: impl Into<CoordF>, col: Self::Atom) {
+        let (a, b, c) = (a.into(), b.into(), c.into());
+        self.draw_line(a, b, col);
+        self.draw_line(b, c, col);
+        self.draw_line(c, a, col);
+    }
+
+    /// Fill an arbitrary polygon (concave and self-intersecting ones too) using the even-odd
+    /// rule: a pixel is filled if a ray from its center crosses the outline an odd number of times.
+    /// The path is closed automatically, and clipped to the image first (unless it wraps around)
+    fn fill_polygon<P: Into<CoordF> + Copy>(&mut self, points: &[P], col: Self::Atom) {
+        if points.len() < 3 { return; }
+        let points = match self.wrap_mode() {
+            WrapMode::Clip => clip::clip_polygon(points, clip::Rect::of_image(self.width(), self.height())),
+            WrapMode::Wrap => points.iter().map(|&p| p.into()).collect(),
+        };
+        path::fill_even_odd(self, &[&points], col);
+    }
+
+    /// Outline every contour of `path`, `width` pixels wide with round joins and ends. A width of
+    /// 1 or less gives the same one pixel lines as `draw_line`
+    fn stroke_path(&mut self, path: &Path, width: f64, col: Self::Atom) { path::stroke(self, path, width, StrokeStyle::Solid, col); }
+
+    /// Same as `stroke_path`, but dashed or dotted
+    fn stroke_path_styled(&mut self, path: &Path, width: f64, style: StrokeStyle, col: Self::Atom) { path::stroke(self, path, width, style, col); }
+
+    /// Part of a circle outline, from angle `start` to `end` (radians, counterclockwise from the
+    /// positive x axis)
+    fn draw_arc(&mut self, center: impl Into<CoordF>, radius: f64, start: f64, end: f64, col: Self::Atom) {
+        self.stroke_path(&Path::new().arc(center, radius, start, end), 1.0, col);
+    }
+
+    /// Pie slice: the area between the center and the arc from `start` to `end`, same angles as
+    /// `draw_arc`
+    fn fill_pie(&mut self, center: impl Into<CoordF>, radius: f64, start: f64, end: f64, col: Self::Atom) {
+        let center = center.into();
+        self.fill_path(&Path::new().move_to(center).arc(center, radius, start, end).close(), col);
+    }
+
+    /// Fill the `w`x`h` rectangle whose bottom left corner is `origin`, with its corners rounded
+    /// off by `radius` pixels (at most half of the shorter side). Zero radius is a plain rectangle
+    fn fill_rounded_rect(&mut self, origin: Coord, w: usize, h: usize, radius: f64, col: Self::Atom) {
+        self.fill_path(&Path::new().rounded_rect(origin, w as f64, h as f64, radius), col);
+    }
+
+    /// One pixel wide outline of the same shape `fill_rounded_rect` fills
+    fn draw_rounded_rect(&mut self, origin: Coord, w: usize, h: usize, radius: f64, col: Self::Atom) {
+        if w == 0 || h == 0 { return; }
+        // Lines cover the pixel to the upper right of each point, so go through pixel centers
+        let corner = CoordF::from(origin) + CoordF::new(0.5, 0.5);
+        let outline = Path::new().rounded_rect(corner, w as f64 - 1.0, h as f64 - 1.0, radius - 0.5);
+        self.stroke_path(&outline, 1.0, col);
+    }
+
+    /// Same as `fill_rect`, but with a gradient, texture... instead of a single color
+    fn fill_rect_styled(&mut self, a: Coord, b: Coord, style: &FillStyle) where Self: Draw<Atom = Pixel> {
+        Styled { img: self, style, sink: Pixel::BLACK }.fill_rect(a, b, Pixel::BLACK);
+    }
+
+    /// Same as `fill_ellipse`, but with a gradient, texture... instead of a single color
+    fn fill_ellipse_styled(&mut self, center: Coord, rx: usize, ry: usize, style: &FillStyle) where Self: Draw<Atom = Pixel> {
+        Styled { img: self, style, sink: Pixel::BLACK }.fill_ellipse(center, rx, ry, Pixel::BLACK);
+    }
+
+    /// Same as `fill_polygon`, but with a gradient, texture... instead of a single color
+    fn fill_polygon_styled<P: Into<CoordF> + Copy>(&mut self, points: &[P], style: &FillStyle) where Self: Draw<Atom = Pixel> {
+        Styled { img: self, style, sink: Pixel::BLACK }.fill_polygon(points, Pixel::BLACK);
+    }
+
+    /// Same as `fill_path`, but with a gradient, texture... instead of a single color
+    fn fill_path_styled(&mut self, path: &Path, style: &FillStyle) where Self: Draw<Atom = Pixel> {
+        Styled { img: self, style, sink: Pixel::BLACK }.fill_path(path, Pixel::BLACK);
+    }
+
+    /// A `size` pixels wide marker centered on `center`
+    fn draw_marker(&mut self, center: impl Into<CoordF>, shape: MarkerShape, size: f64, col: Self::Atom) {
+        use std::f64::consts::{FRAC_PI_2, PI, TAU};
+        let (c, r) = (center.into(), size / 2.0);
+        // Fills go by pixel centers, half a pixel off from where lines put them
+        let mid = c + CoordF::new(0.5, 0.5);
+        let at = |p: CoordF, dx: f64, dy: f64| p + CoordF::new(dx, dy);
+        match shape {
+            MarkerShape::Dot => self.fill_path(&Path::new().arc(mid, r, 0.0, TAU), col),
+            MarkerShape::Square => self.fill_path(&Path::new().rounded_rect(at(mid, -r, -r), size, size, 0.0), col),
+            MarkerShape::Circle => self.stroke_path(&Path::new().arc(c, r, 0.0, TAU), 1.0, col),
+            MarkerShape::Cross => {
+                self.draw_line(at(c, -r, -r), at(c, r, r), col);
+                self.draw_line(at(c, -r, r), at(c, r, -r), col);
+            },
+            MarkerShape::Plus => {
+                self.draw_line(at(c, -r, 0.0), at(c, r, 0.0), col);
+                self.draw_line(at(c, 0.0, -r), at(c, 0.0, r), col);
+            },
+            MarkerShape::Diamond => self.fill_polygon(&[at(mid, r, 0.0), at(mid, 0.0, r), at(mid, -r, 0.0), at(mid, 0.0, -r)], col),
+            MarkerShape::Star => {
+                // Outer points every 72 degrees, with inner corners between them
+                let points: Vec<CoordF> = (0..10).map(|i| {
+                    let radius = if i % 2 == 0 { r } else { r * 0.4 };
+                    let angle = FRAC_PI_2 + i as f64 * PI / 5.0;
+                    (at(mid, angle.cos()*radius, angle.sin()*radius)).snapped()
+                }).collect();
+                self.fill_polygon(&points, col);
+            },
+        }
+    }
+
+    /// One pixel wide line from `a` to `b`, dashed or dotted
+    fn draw_line_styled(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, style: StrokeStyle, col: Self::Atom) {
+        path::stroke(self, &Path::new().move_to(a).line_to(b), 1.0, style, col);
+    }
+
+    /// Fill the inside of `path` with the same even-odd rule as `fill_polygon`, taking all of its
+    /// contours together (so a contour inside another one makes a hole). Open contours count as
+    /// closed
+    fn fill_path(&mut self, path: &Path, col: Self::Atom) {
+        let contours: Vec<&[CoordF]> = path.contours().map(|(points, _)| points).collect();
+        path::fill_even_odd(self, &contours, col);
+    }
+
+    /// Anti-aliased line using Wu's algorithm: each step along the major axis covers two pixels,
+    /// which get blended with what was already there proportionally to how close the ideal line
+    /// passes by
+    fn draw_line_aa(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, col: Self::Atom) where Self::Atom: Blend {
+        let (a, b) = (a.into(), b.into());
+        let (mut x0, mut y0, mut x1, mut y1) = (a.x, a.y, b.x, b.y);
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if steep { (x0, y0, x1, y1) = (y0, x0, y1, x1); }
+        if x0 > x1 { (x0, y0, x1, y1) = (x1, y1, x0, y0); }
+        let gradient = if x1 == x0 { 0.0 } else { (y1 - y0)/(x1 - x0) };
+
+        let mut plot = |x: f64, y: f64, coverage: f64| {
+            let (x, y) = if steep { (y, x) } else { (x, y) };
+            if coverage <= 0.0 { return; }
+            if let Some(p) = self.get_mut_signed(x.floor() as isize, y.floor() as isize) { *p = p.blend(col, coverage); }
+        };
+
+        for x in x0.round() as isize..=x1.round() as isize {
+            let y = y0 + gradient*(x as f64 - x0);
+            let (base, frac) = (y.floor(), y - y.floor());
+            plot(x as f64, base, 1.0 - frac);
+            plot(x as f64, base + 1.0, frac);
+        }
+    }
+
+    /// Stroke the segment `ab` with the given width (in pixels) and caps. A pixel gets painted if
+    /// its center is within `width/2` of the segment
+    fn draw_line_thick(&mut self, a: Coord, b: Coord, width: usize, cap: LineCap, col: Self::Atom) {
+        let half = width as f64 / 2.0;
+        let (ax, ay) = (a.x as f64, a.y as f64);
+        let (dx, dy) = (b.x as f64 - ax, b.y as f64 - ay);
+        let len = (dx*dx + dy*dy).sqrt();
+
+        let (reach, mode) = (half.ceil(), self.wrap_mode());
+        let xs = draw_span(a.x.min(b.x) as f64 - reach, a.x.max(b.x) as f64 + reach, self.width(), mode);
+        let ys = draw_span(a.y.min(b.y) as f64 - reach, a.y.max(b.y) as f64 + reach, self.height(), mode);
+        for y in ys {
+        for x in xs.clone() {
+            let (px, py) = (x as f64 - ax, y as f64 - ay);
+            // Position along the segment (in pixels) and distance away from it
+            let (along, across) = if len == 0.0 {
+                (0.0, 0.0)
+            } else {
+                ((px*dx + py*dy)/len, (px*dy - py*dx).abs()/len)
+            };
+            let inside = match cap {
+                LineCap::Butt   => (0.0..=len).contains(&along) && across <= half,
+                LineCap::Square => (-half..=len + half).contains(&along) && across <= half,
+                LineCap::Round  => {
+                    let overshoot = if along < 0.0 { -along } else if along > len { along - len } else { 0.0 };
+                    overshoot*overshoot + across*across <= half*half
+                },
+            };
+            if inside { self.put(x, y, col); }
+        }
+        }
+    }
+
+    /// One pixel wide circle outline, using the midpoint circle algorithm. Parts falling outside
+    /// of the image are skipped
+    fn draw_circle_outline(&mut self, center: Coord, radius: usize, col: Self::Atom) {
+        let (cx, cy) = (center.x as isize, center.y as isize);
+        let (mut x, mut y) = (radius as isize, 0isize);
+        let mut err = 1 - x;
+        while x >= y {
+            for (dx, dy) in [(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)] {
+                self.put(cx + dx, cy + dy, col);
+            }
+            y += 1;
+            if err < 0 {
+                err += 2*y + 1;
+            } else {
+                x -= 1;
+                err += 2*(y - x) + 1;
+            }
+        }
+    }
+
+    /// Circle outline `thickness` pixels wide, growing inwards from `radius`
+    fn draw_circle_outline_thick(&mut self, center: Coord, radius: usize, thickness: usize, col: Self::Atom) {
+        if thickness <= 1 { return self.draw_circle_outline(center, radius, col); }
+        let outer = (radius as f64 + 0.5).powi(2);
+        let inner = (radius as f64 - thickness as f64 + 0.5).max(0.0).powi(2);
+
+        let (c, r, mode) = (CoordF::from(center), radius as f64, self.wrap_mode());
+        for y in draw_span(c.y - r, c.y + r, self.height(), mode) {
+        for x in draw_span(c.x - r, c.x + r, self.width(), mode) {
+            let (dx, dy) = (x as f64 - c.x, y as f64 - c.y);
+            let d = dx*dx + dy*dy;
+            if d < outer && d >= inner { self.put(x, y, col); }
+        }
+        }
+    }
+
+    /// Fill the axis-aligned ellipse with radii `rx` (horizontal) and `ry` (vertical)
+    fn fill_ellipse(&mut self, center: Coord, rx: usize, ry: usize, col: Self::Atom) {
+        let (a, b) = (rx as f64 + 0.5, ry as f64 + 0.5);
+        let (c, mode) = (CoordF::from(center), self.wrap_mode());
+        for y in draw_span(c.y - ry as f64, c.y + ry as f64, self.height(), mode) {
+        for x in draw_span(c.x - rx as f64, c.x + rx as f64, self.width(), mode) {
+            let (dx, dy) = ((x as f64 - c.x)/a, (y as f64 - c.y)/b);
+            if dx*dx + dy*dy <= 1.0 { self.put(x, y, col); }
+        }
+        }
+    }
+
+    /// One pixel wide ellipse outline, using the midpoint ellipse algorithm (two regions, split
+    /// where the slope is -1)
+    fn draw_ellipse_outline(&mut self, center: Coord, rx: usize, ry: usize, col: Self::Atom) {
+        let (cx, cy) = (center.x as isize, center.y as isize);
+        // Flat ellipses are just a line (or a point), which the midpoint loops can't draw
+        if rx == 0 || ry == 0 {
+            let (rx, ry) = (rx as isize, ry as isize);
+            for y in cy - ry..=cy + ry {
+            for x in cx - rx..=cx + rx {
+                self.put(x, y, col);
+            }
+            }
+            return;
+        }
+        let mut put4 = |x: i64, y: i64| {
+            let (x, y) = (x as isize, y as isize);
+            for (px, py) in [(cx + x, cy + y), (cx - x, cy + y), (cx + x, cy - y), (cx - x, cy - y)] {
+                self.put(px, py, col);
+            }
+        };
+
+        let (a2, b2) = ((rx*rx) as i64, (ry*ry) as i64);
+        let (mut x, mut y) = (0i64, ry as i64);
+
+        // Region 1: slope > -1, step in x
+        let mut d = 4*b2 - 4*a2*ry as i64 + a2;
+        while b2*x <= a2*y {
+            put4(x, y);
+            if d >= 0 {
+                y -= 1;
+                d -= 8*a2*y;
+            }
+            x += 1;
+            d += 4*b2*(2*x + 1);
+        }
+
+        // Region 2: slope < -1, step in y
+        let mut d = b2*(2*x + 1)*(2*x + 1) + 4*a2*(y - 1)*(y - 1) - 4*a2*b2;
+        while y >= 0 {
+            put4(x, y);
+            if d <= 0 {
+                x += 1;
+                d += 8*b2*x;
+            }
+            y -= 1;
+            d += 4*a2*(1 - 2*y);
+        }
+    }
+
+    /// Join each point to the next one with a line. The path is left open
+    fn draw_polyline<P: Into<CoordF> + Copy>(&mut self, points: &[P], col: Self::Atom) {
+        for w in points.windows(2) { self.draw_line(w[0], w[1], col); }
+        if let [p] = points { self.draw_line(*p, *p, col); }
+    }
+
+    /// Same as `draw_polyline`, but also joins the last point back to the first one
+    fn draw_polygon_outline<P: Into<CoordF> + Copy>(&mut self, points: &[P], col: Self::Atom) {
+        self.draw_polyline(points, col);
+        if let (Some(&first), Some(&last)) = (points.first(), points.last()) { self.draw_line(last, first, col); }
+    }
+
+    /// Write `text` with the built-in 8x8 font, each font pixel being a `scale`x`scale` square.
+    /// `origin` is the bottom left corner of the first character; newlines start a new line below.
+    /// With a top left origin it's the top left corner instead, so text still reads upright. Only
+    /// printable ASCII is supported, anything else shows up as `?`
+    fn draw_text(&mut self, origin: Coord, text: &str, scale: usize, col: Self::Atom) {
+        if scale == 0 { return; }
+        let size = font::GLYPH_SIZE * scale;
+        let flipped = self.origin() == Origin::TopLeft;
+        for (line_no, line) in text.lines().enumerate() {
+            let base_y = if flipped { Some(origin.y + line_no*size) } else { origin.y.checked_sub(line_no*size) };
+            let Some(base_y) = base_y else { break };
+            for (i, c) in line.chars().enumerate() {
+                let base_x = origin.x + i*size;
+                if base_x >= self.width() && self.wrap_mode() == WrapMode::Clip { break; }
+                for (row, bits) in font::glyph(c).iter().enumerate() {
+                for bit in 0..font::GLYPH_SIZE {
+                    if bits >> bit & 1 == 0 { continue; }
+                    let row = if flipped { row } else { font::GLYPH_SIZE - 1 - row };
+                    let corner = Coord::new(base_x + bit*scale, base_y + row*scale);
+                    self.fill_rect(corner, corner + Coord::new(scale - 1, scale - 1), col);
+                }
+                }
+            }
+        }
+    }
+
+    /// Copy `src` into this image with its bottom left corner at `dest`. Whatever falls outside of
+    /// this image is ignored (or wraps around)
+    fn blit(&mut self, src: &Self, dest: Coord) {
+        let (d, mode) = (CoordF::from(dest), self.wrap_mode());
+        for y in draw_span(d.y, d.y + src.height() as f64 - 1.0, self.height(), mode) {
+        for x in draw_span(d.x, d.x + src.width() as f64 - 1.0, self.width(), mode) {
+            self.put(x, y, *src.get(x as usize - dest.x, y as usize - dest.y).unwrap());
+        }
+        }
+    }
+
+    /// Fills the whole image with a gradient going from `col_a` at `a` to `col_b` at `b`. Lines
+    /// perpendicular to `a`-`b` get the same color, and beyond the endpoints the colors are flat
+    fn fill_linear_gradient(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, col_a: Self::Atom, col_b: Self::Atom) where Self::Atom: Blend {
+        let (a, b) = (a.into(), b.into());
+        let dir = b - a;
+        let len2 = dir.dot(dir);
+        for y in 0..self.height() {
+        for x in 0..self.width() {
+            let t = if len2 == 0.0 { 0.0 } else { (CoordF::new(x as f64, y as f64) - a).dot(dir) / len2 };
+            *self.get_mut(x, y).unwrap() = col_a.blend(col_b, t);
+        }
+        }
+    }
+
+    /// Fills the whole image with a circular gradient: `inner` at `center`, `outer` at `radius`
+    /// away and further
+    fn fill_radial_gradient(&mut self, center: impl Into<CoordF>, radius: f64, inner: Self::Atom, outer: Self::Atom) where Self::Atom: Blend {
+        let center = center.into();
+        for y in 0..self.height() {
+        for x in 0..self.width() {
+            let t = if radius <= 0.0 { 1.0 } else { CoordF::new(x as f64, y as f64).distance(center) / radius };
+            *self.get_mut(x, y).unwrap() = inner.blend(outer, t);
+        }
+        }
+    }
+}
+
+pub trait PpmFormat: Draw {
+    // Minimum implementation
+    fn new(width: usize, height: usize, bg_color: Self::Atom) -> Self;
+    fn atoms(&self) -> &Vec<Self::Atom>;
+    fn atoms_mut(&mut self) -> &mut Vec<Self::Atom>;
+
+    // Default implementations
+    /// Blank image for a transformed copy of this one to be built into: same as `new`, but
+    /// keeping whatever else describes the image besides its pixels (like `Image`'s maxval)
+    fn new_like(&self, width: usize, height: usize, bg_color: Self::Atom) -> Self where Self: Sized {
+        Self::new(width, height, bg_color)
+    }
+
+    /// Pixel for new images when there's none to copy: `PixelLike::BLANK` for `Image`. None if
+    /// there isn't one, which is the default
+    fn blank(&self) -> Option<Self::Atom> { None }
+
+    /// All pixels in logical order: left to right, starting from the row at y = 0 (the bottom one)
+    fn pixels(&self) -> impl DoubleEndedIterator<Item = &Self::Atom> {
+        let w = self.width().max(1);
+        self.atoms().chunks(w).rev().flatten()
+    }
+
+    /// Mutable version of `pixels`, same order
+    fn pixels_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut Self::Atom> {
+        let w = self.width().max(1);
+        self.atoms_mut().chunks_mut(w).rev().flatten()
+    }
+
+    /// Pixels of row `y`, left to right. None if it's out of bounds. Rows are contiguous in
+    /// storage, so this is just a slice of it
+    fn row(&self, y: usize) -> Option<&[Self::Atom]> {
+        if y >= self.height() { return None; }
+        let (w, r) = (self.width(), self.storage_row(y));
+        Some(&self.atoms()[r*w..(r + 1)*w])
+    }
+
+    /// Mutable version of `row`
+    fn row_mut(&mut self, y: usize) -> Option<&mut [Self::Atom]> {
+        if y >= self.height() { return None; }
+        let (w, r) = (self.width(), self.storage_row(y));
+        Some(&mut self.atoms_mut()[r*w..(r + 1)*w])
+    }
+
+    /// Every row, starting from y = 0
+    fn rows(&self) -> impl DoubleEndedIterator<Item = &[Self::Atom]> {
+        let mut rows: Vec<_> = self.atoms().chunks(self.width().max(1)).collect();
+        if self.origin() == Origin::BottomLeft { rows.reverse(); }
+        rows.into_iter()
+    }
+
+    /// Mutable version of `rows`, same order
+    fn rows_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut [Self::Atom]> {
+        let bottom_left = self.origin() == Origin::BottomLeft;
+        let w = self.width().max(1);
+        let mut rows: Vec<_> = self.atoms_mut().chunks_mut(w).collect();
+        if bottom_left { rows.reverse(); }
+        rows.into_iter()
+    }
+
+    /// Where row `y` is in storage, which is always top row first
+    fn storage_row(&self, y: usize) -> usize {
+        match self.origin() {
+            Origin::BottomLeft => self.height() - y - 1,
+            Origin::TopLeft => y,
+        }
+    }
+
+    /// Borrow the `w`x`h` region whose bottom left corner (or top left, with a top left origin)
+    /// is `corner`. None if it's empty or doesn't fit in the image
+    fn view(&self, corner: Coord, w: usize, h: usize) -> Option<ImageView<'_, Self::Atom>> {
+        let (start, end) = view::region_bounds(self.width(), self.height(), self.origin(), corner, w, h)?;
+        Some(ImageView::new(&self.atoms()[start..end], self.width(), w, h, self.origin(), corner))
+    }
+
+    /// Same as `view`, but letting the region be drawn on
+    fn view_mut(&mut self, corner: Coord, w: usize, h: usize) -> Option<ImageViewMut<'_, Self::Atom>> {
+        let (stride, origin) = (self.width(), self.origin());
+        let (start, end) = view::region_bounds(stride, self.height(), origin, corner, w, h)?;
+        Some(ImageViewMut::new(&mut self.atoms_mut()[start..end], stride, w, h, origin, corner))
+    }
+
+    /// Split the image into (at most) `n` disjoint tiles that can be drawn on at the same time,
+    /// e.g. one per thread with `std::thread::scope`. Tiles are full width bands of rows, as even
+    /// as possible, starting from y = 0; each one knows where it is with `corner`. Once they're
+    /// dropped the image has everything that was drawn on them
+    fn split_into_tiles(&mut self, n: usize) -> Vec<ImageViewMut<'_, Self::Atom>> {
+        let (w, h, origin) = (self.width(), self.height(), self.origin());
+        if n == 0 || w == 0 || h == 0 { return vec![]; }
+        let band = h.div_ceil(n);
+        let mut tiles: Vec<_> = self.atoms_mut().chunks_mut(band*w).enumerate().map(|(i, atoms)| {
+            let rows = atoms.len() / w;
+            // Storage goes top row first
+            let y = match origin {
+                Origin::BottomLeft => h - i*band - rows,
+                Origin::TopLeft => i*band,
+            };
+            ImageViewMut::new(atoms, w, w, rows, origin, Coord::new(0, y))
+        }).collect();
+        if origin == Origin::BottomLeft { tiles.reverse(); }
+        tiles
+    }
+
+    /// Same as `pixels`, but along with the coordinates of each pixel
+    fn enumerate_pixels(&self) -> impl Iterator<Item = (Coord, &Self::Atom)> {
+        let w = self.width().max(1);
+        self.pixels().enumerate().map(move |(i, p)| (Coord::new(i % w, i / w), p))
+    }
+
+    /// Same as `pixels_mut`, but along with the coordinates of each pixel
+    fn enumerate_pixels_mut(&mut self) -> impl Iterator<Item = (Coord, &mut Self::Atom)> {
+        let w = self.width().max(1);
+        self.pixels_mut().enumerate().map(move |(i, p)| (Coord::new(i % w, i / w), p))
+    }
+
+    /// Copy out the `w`x`h` region whose bottom left corner is `origin`. The region gets clipped
+    /// to the image, so the result may be smaller than asked for. None if `origin` is outside
+    fn sub_image(&self, origin: Coord, w: usize, h: usize) -> Option<Self> where Self: Sized {
+        let &fill = self.get(origin.x, origin.y)?;
+        let (w, h) = (w.min(self.width() - origin.x), h.min(self.height() - origin.y));
+
+        let mut sub = self.new_like(w, h, fill);
+        for y in 0..h {
+        for x in 0..w {
+            *sub.get_mut(x, y).unwrap() = *self.get(origin.x + x, origin.y + y).unwrap();
+        }
+        }
+        Some(sub)
+    }
+
+    /// Return a resized copy. If this image is empty there's nothing to stretch, so the new one
+    /// is all `blank` pixels. Only panics for an empty image that has no `blank`
+    fn resize(&self, new_w: usize, new_h: usize, filter: ResizeFilter) -> Self where Self: Sized, Self::Atom: Blend {
+        if self.atoms().is_empty() || new_w == 0 || new_h == 0 {
+            let bg = self.atoms().first().copied().or_else(|| self.blank())
+                .expect("can't resize an empty image without a blank pixel to fill the new one with");
+            return self.new_like(new_w, new_h, bg);
+        }
+        let mut out = self.new_like(new_w, new_h, self.atoms()[0]);
+        let (sx, sy) = (self.width() as f64 / new_w as f64, self.height() as f64 / new_h as f64);
+        for y in 0..new_h {
+        for x in 0..new_w {
+            // Center of the destination pixel, in source coordinates
+            let (fx, fy) = (((x as f64 + 0.5)*sx - 0.5).max(0.0), ((y as f64 + 0.5)*sy - 0.5).max(0.0));
+            let at = |x: f64, y: f64| *self.get((x as usize).min(self.width() - 1), (y as usize).min(self.height() - 1)).unwrap();
+            *out.get_mut(x, y).unwrap() = match filter {
+                ResizeFilter::Nearest => at(fx + 0.5, fy + 0.5),
+                ResizeFilter::Bilinear => {
+                    let (tx, ty) = (fx.fract(), fy.fract());
+                    let bottom = at(fx, fy).blend(at(fx + 1.0, fy), tx);
+                    let top = at(fx, fy + 1.0).blend(at(fx + 1.0, fy + 1.0), tx);
+                    bottom.blend(top, ty)
+                },
+            };
+        }
+        }
+        out
+    }
+
+    /// Rotated copy, 90 degrees counterclockwise (y grows upwards, so this is the positive
+    /// direction). Panics if the image is empty, as do the other rotations and flips
+    fn rotate90(&self) -> Self where Self: Sized {
+        let (w, h) = (self.width(), self.height());
+        let mut out = self.new_like(h, w, self.atoms()[0]);
+        for y in 0..h { for x in 0..w { *out.get_mut(h - 1 - y, x).unwrap() = *self.get(x, y).unwrap(); } }
+        out
+    }
+
+    /// Rotated copy, upside down
+    fn rotate180(&self) -> Self where Self: Sized {
+        let (w, h) = (self.width(), self.height());
+        let mut out = self.new_like(w, h, self.atoms()[0]);
+        for y in 0..h { for x in 0..w { *out.get_mut(w - 1 - x, h - 1 - y).unwrap() = *self.get(x, y).unwrap(); } }
+        out
+    }
+
+    /// Rotated copy, 90 degrees clockwise
+    fn rotate270(&self) -> Self where Self: Sized {
+        let (w, h) = (self.width(), self.height());
+        let mut out = self.new_like(h, w, self.atoms()[0]);
+        for y in 0..h { for x in 0..w { *out.get_mut(y, w - 1 - x).unwrap() = *self.get(x, y).unwrap(); } }
+        out
+    }
+
+    /// Mirrored copy, left becomes right
+    fn flip_horizontal(&self) -> Self where Self: Sized {
+        let (w, h) = (self.width(), self.height());
+        let mut out = self.new_like(w, h, self.atoms()[0]);
+        for y in 0..h { for x in 0..w { *out.get_mut(w - 1 - x, y).unwrap() = *self.get(x, y).unwrap(); } }
+        out
+    }
+
+    /// Mirrored copy, top becomes bottom
+    fn flip_vertical(&self) -> Self where Self: Sized {
+        let (w, h) = (self.width(), self.height());
+        let mut out = self.new_like(w, h, self.atoms()[0]);
+        for y in 0..h { for x in 0..w { *out.get_mut(x, h - 1 - y).unwrap() = *self.get(x, y).unwrap(); } }
+        out
+    }
+
+    /// Copy rotated by `theta` radians counterclockwise around the center, with bilinear
+    /// sampling. The size stays the same: corners that rotate out are lost and the uncovered
+    /// areas get `bg`
+    fn rotate(&self, theta: f64, bg: Self::Atom) -> Self where Self: Sized, Self::Atom: Blend {
+        let (w, h) = (self.width(), self.height());
+        let (cx, cy) = ((w as f64 - 1.0)/2.0, (h as f64 - 1.0)/2.0);
+        let (sin, cos) = theta.sin_cos();
+        let at = |x: f64, y: f64| {
+            if x < 0.0 || y < 0.0 { return bg; }
+            self.get(x as usize, y as usize).copied().unwrap_or(bg)
+        };
+
+        let mut out = self.new_like(w, h, bg);
+        for y in 0..h {
+        for x in 0..w {
+            // Rotate the destination pixel backwards to find where it comes from
+            let (dx, dy) = (x as f64 - cx, y as f64 - cy);
+            let (sx, sy) = (cx + dx*cos + dy*sin, cy - dx*sin + dy*cos);
+            if sx <= -1.0 || sy <= -1.0 || sx >= w as f64 || sy >= h as f64 { continue; }
+
+            let (x0, y0) = (sx.floor(), sy.floor());
+            let (tx, ty) = (sx - x0, sy - y0);
+            let bottom = at(x0, y0).blend(at(x0 + 1.0, y0), tx);
+            let top = at(x0, y0 + 1.0).blend(at(x0 + 1.0, y0 + 1.0), tx);
+            *out.get_mut(x, y).unwrap() = bottom.blend(top, ty);
+        }
+        }
+        out
+    }
+
+    /// Copy of the `w`x`h` region whose bottom left corner is `origin`. Unlike `sub_image`, the
+    /// region must fit entirely, panics otherwise (see `try_crop`)
+    fn crop(&self, origin: Coord, w: usize, h: usize) -> Self where Self: Sized {
+        self.try_crop(origin, w, h).unwrap_or_else(|e| panic!("crop of {w}x{h} at {origin:?} failed: {e}"))
+    }
+
+    /// Same as `crop`, but returns an error instead of panicking: `InvalidDimensions` for an
+    /// empty region and `OutOfBounds` (with the far corner) if it doesn't fit
+    fn try_crop(&self, origin: Coord, w: usize, h: usize) -> Result<Self, PpmError> where Self: Sized {
+        if w == 0 || h == 0 { return Err(PpmError::InvalidDimensions(format!("can't crop an empty {w}x{h} region"))); }
+        let far = Coord::new(origin.x.saturating_add(w - 1), origin.y.saturating_add(h - 1));
+        if far.x >= self.width() || far.y >= self.height() {
+            return Err(PpmError::OutOfBounds { coord: far, width: self.width(), height: self.height() });
+        }
+        Ok(self.sub_image(origin, w, h).unwrap())
+    }
+
+    /// Copy with extra borders of `fill` around it, e.g. for framing or letterboxing
+    fn pad(&self, top: usize, bottom: usize, left: usize, right: usize, fill: Self::Atom) -> Self where Self: Sized {
+        let mut out = self.new_like(self.width() + left + right, self.height() + top + bottom, fill);
+        out.blit(self, Coord::new(left, bottom));
+        out
     }
 
+    /// Write the image in the corresponding (plain) format to anything writable: stdout, a socket,
+    /// a `Vec<u8>`... Output is buffered internally, so there's no need to wrap `w` yourself
+    fn write_to(&self, w: impl Write) -> Result<(), PpmError>;
+
+    /// Same as `write_to`, but using the binary (raw) variant of the format. Way smaller and
+    /// faster to write, at the cost of not being human readable
+    fn write_binary_to(&self, w: impl Write) -> Result<(), PpmError>;
+
     /// Save created image at `./$filepath` if possible in the corresponding format (the format
-    /// suffix is not automatically added).
-    fn save_to_file(&self, filepath: impl Into<PathBuf>) -> Result<(), std::io::Error>;
+    /// suffix is not automatically added). Only borrows the image, so it can be saved, drawn on
+    /// some more and saved again.
+    fn save_to_file(&self, filepath: impl Into<PathBuf>) -> Result<(), PpmError> {
+        self.write_to(File::create(filepath.into())?)
+    }
+
+    /// Same as `save_to_file`, but using the binary variant of the format (see `write_binary_to`)
+    fn save_to_file_binary(&self, filepath: impl Into<PathBuf>) -> Result<(), PpmError> {
+        self.write_binary_to(File::create(filepath.into())?)
+    }
 }
 
-/// Basic image file type
-#[derive(Clone, Debug)]
-pub struct ImagePPM {
-    atoms: Vec<Pixel>,
-    width: usize,
-    height: usize,
+/// What an `Image` can be made of. Each pixel type picks its Netpbm format and knows how to write
+/// an image of itself in it
+pub trait PixelLike: Copy {
+    /// Maxval of new images (formats without one, like PBM, ignore it)
+    const MAXVAL: u16;
+
+    /// File extension for the format, without the dot
+    const EXTENSION: &'static str;
+
+    /// What new pixels are when nothing else is said: black, or fully transparent
+    const BLANK: Self;
+
+    /// Write the header and the raster in the plain variant of the format
+    fn write_plain(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError>;
+
+    /// Write the header and the raster in the raw (binary) variant of the format
+    fn write_raw(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError>;
 }
 
+/// Image generic over its pixel type, so every format shares the same storage and code. Pixels
+/// are stored row by row starting from the top one, but all the public API uses (0, 0) as the
+/// bottom left corner. Usually used through one of the aliases below
 #[derive(Clone, Debug)]
-pub struct ImagePBM {
-    /// False for background (black), true for foreground (white)
-    atoms: Vec<bool>,
+pub struct Image<P> {
+    atoms: Vec<P>,
     width: usize,
     height: usize,
+    maxval: u16,
 }
 
-impl PpmFormat for ImagePPM {
-    type Atom = Pixel;
+/// Basic image file type
+pub type ImagePPM = Image<Pixel>;
 
-    fn new(width: usize, height: usize, bg_color: Pixel) -> Self { Self { width, height, atoms: vec![bg_color; width*height], } }
-    fn width(&self) -> usize { self.width }
-    fn height(&self) -> usize { self.height }
-    fn atoms(&self) -> &Vec<Pixel> { &self.atoms }
-    fn atoms_mut(&mut self) -> &mut Vec<Pixel> { &mut self.atoms }
+/// RGB image with up to 16 bits per channel and a configurable maxval (65535 by default)
+pub type ImagePPM16 = Image<Pixel16>;
+
+/// Grayscale image, 0 is black and 255 is white
+pub type ImagePGM = Image<u8>;
 
-    /// This uses write! instead of println!, which panics instead of erroring on failure. However,
-    /// I'm planning to unwrap the error anyway, so literally whatever lmao
-    fn save_to_file(&self, filepath: impl Into<PathBuf>) -> Result<(), std::io::Error> {
-        let file = File::create(filepath.into())?;
-        let mut writer = BufWriter::new(file);
+/// False for background (black), true for foreground (white)
+pub type ImagePBM = Image<bool>;
 
-        write!(writer, "P3\n{} {}\n255\n", self.width, self.height).unwrap();
+/// RGB image with transparency, meant to be used as a layer and composited onto an `ImagePPM`
+/// with `ImagePPM::blit_with_alpha` (or flattened). Netpbm's only container for it is PAM, so
+/// that's what it gets saved as
+pub type ImageRGBA = Image<PixelRgba>;
 
-        for &Pixel {r, g, b} in &self.atoms {
-            write!(writer, "{:3} {:3} {:3}\n", r, g, b).unwrap();
+/// Grayscale image with transparency, saved as PAM too
+pub type ImageGrayAlpha = Image<PixelGrayAlpha>;
+
+impl<P: PixelLike> Image<P> {
+    /// Wrap already existing pixels, in storage order (top row first). Panics if there aren't
+    /// exactly `width*height` of them
+    pub(crate) fn from_atoms(width: usize, height: usize, atoms: Vec<P>) -> Self {
+        assert_eq!(atoms.len(), width*height, "expected {width}x{height} pixels");
+        Self { atoms, width, height, maxval: P::MAXVAL }
+    }
+
+    /// Same as `new`, but fails instead of panicking or aborting when `width*height` overflows
+    pub fn try_new(width: usize, height: usize, bg_color: P) -> Result<Self, PpmError> {
+        width.checked_mul(height)
+            .filter(|n| n.checked_mul(std::mem::size_of::<P>()).is_some_and(|bytes| bytes <= isize::MAX as usize))
+            .ok_or_else(|| PpmError::InvalidDimensions(format!("{width}x{height} is too big")))?;
+        Ok(Self::new(width, height, bg_color))
+    }
+
+    /// Builds an image by calling `f` for every coordinate (same coordinates as `get`). See
+    /// `par_from_fn` for a multi-threaded version
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(Coord) -> P) -> Self {
+        let atoms = (0..height).rev().flat_map(|y| (0..width).map(move |x| Coord::new(x, y))).map(&mut f).collect();
+        Self::from_atoms(width, height, atoms)
+    }
+
+    /// A new image with `f` applied to every pixel, which may change the pixel type
+    pub fn map_pixels<Q: PixelLike>(&self, f: impl FnMut(P) -> Q) -> Image<Q> {
+        let out = Image::from_atoms(self.width, self.height, self.atoms.iter().copied().map(f).collect());
+        Image { maxval: self.maxval_for::<Q>(), ..out }
+    }
+
+    /// Same as `map_pixels`, but `f` also gets each pixel's coordinates (same as `get`)
+    pub fn map_pixels_with_coord<Q: PixelLike>(&self, mut f: impl FnMut(Coord, P) -> Q) -> Image<Q> {
+        let out = Image::from_fn(self.width, self.height, |c| f(c, self.atoms[c.x + (self.height - c.y - 1)*self.width]));
+        Image { maxval: self.maxval_for::<Q>(), ..out }
+    }
+
+    /// A new image made by combining each pixel with the one at the same spot in `other`.
+    /// Panics if their sizes differ
+    pub fn zip_map<Q: PixelLike, R: PixelLike>(&self, other: &Image<Q>, mut f: impl FnMut(P, Q) -> R) -> Image<R> {
+        assert!(self.width == other.width && self.height == other.height,
+            "can't zip a {}x{} image with a {}x{} one", self.width, self.height, other.width, other.height);
+        let out = Image::from_atoms(self.width, self.height, self.atoms.iter().zip(&other.atoms).map(|(&a, &b)| f(a, b)).collect());
+        Image { maxval: self.maxval_for::<R>(), ..out }
+    }
+
+    /// Maxval for an image of `Q` made from this one: kept when the samples have the same range
+    /// (the same pixel type, say), the default otherwise
+    fn maxval_for<Q: PixelLike>(&self) -> u16 { if Q::MAXVAL == P::MAXVAL { self.maxval } else { Q::MAXVAL } }
+
+    /// Largest sample value, what the header says is full intensity
+    pub fn maxval(&self) -> u16 { self.maxval }
+
+    /// Writes the raster a row at a time (top first): `encode` turns each row into bytes in a
+    /// buffer that's reused for every row, so memory use stays at one row whatever the image size
+    pub(crate) fn write_rows(&self, w: &mut impl Write, mut encode: impl FnMut(&[P], &mut Vec<u8>) -> Result<(), PpmError>) -> Result<(), PpmError> {
+        let mut buf = Vec::new();
+        for row in self.atoms.chunks(self.width.max(1)) {
+            buf.clear();
+            encode(row, &mut buf)?;
+            w.write_all(&buf)?;
         }
         Ok(())
     }
-
 }
 
-impl PpmFormat for ImagePBM {
-    type Atom = bool;
+impl<P: PixelLike> Draw for Image<P> {
+    type Atom = P;
 
-    fn new(width: usize, height: usize, val: bool) -> Self { Self { width, height, atoms: vec![val; width*height], } }
     fn width(&self) -> usize { self.width }
     fn height(&self) -> usize { self.height }
-    fn atoms(&self) -> &Vec<bool> { &self.atoms }
-    fn atoms_mut(&mut self) -> &mut Vec<Self::Atom> { &mut self.atoms }
 
-    /// This uses write! instead of println!, which panics instead of erroring on failure. However,
-    /// I'm planning to unwrap the error anyway, so literally whatever lmao
-    fn save_to_file(&self, filepath: impl Into<PathBuf>) -> Result<(), std::io::Error> {
-        let file = File::create(filepath.into())?;
-        let mut writer = BufWriter::new(file);
+    fn get(&self, x: usize, y: usize) -> Option<&P> {
+        if x >= self.width || y >= self.height { return None; }
+        Some(&self.atoms[x + (self.height - y - 1)*self.width])
+    }
 
-        write!(writer, "P1\n{} {}\n", self.width, self.height).unwrap();
+    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut P> {
+        if x >= self.width || y >= self.height { return None; }
+        Some(&mut self.atoms[x + (self.height - y - 1)*self.width])
+    }
+}
 
-        for &b in &self.atoms { writer.write(&[if b { b'0' } else { b'1' }])?; }
+impl<P: PixelLike> PpmFormat for Image<P> {
+    fn new(width: usize, height: usize, bg_color: P) -> Self { Self::from_atoms(width, height, vec![bg_color; width*height]) }
+    fn new_like(&self, width: usize, height: usize, bg_color: P) -> Self { Self { maxval: self.maxval, ..Self::new(width, height, bg_color) } }
+    fn blank(&self) -> Option<P> { Some(P::BLANK) }
+    fn atoms(&self) -> &Vec<P> { &self.atoms }
+    fn atoms_mut(&mut self) -> &mut Vec<P> { &mut self.atoms }
 
-        writer.flush().unwrap();
-        Ok(())
+    fn write_to(&self, w: impl Write) -> Result<(), PpmError> {
+        let mut writer = BufWriter::new(w);
+        P::write_plain(self, &mut writer)?;
+        Ok(writer.flush()?)
+    }
+
+    fn write_binary_to(&self, w: impl Write) -> Result<(), PpmError> {
+        let mut writer = BufWriter::new(w);
+        P::write_raw(self, &mut writer)?;
+        Ok(writer.flush()?)
+    }
+}
+
+/// `img[Coord::new(x, y)]`, same coordinates as `get`. Panics if out of bounds
+impl<P: PixelLike> ops::Index<Coord> for Image<P> {
+    type Output = P;
+    fn index(&self, c: Coord) -> &P {
+        self.get(c.x, c.y).unwrap_or_else(|| panic!("{c:?} is out of bounds ({}x{})", self.width, self.height))
+    }
+}
+impl<P: PixelLike> ops::IndexMut<Coord> for Image<P> {
+    fn index_mut(&mut self, c: Coord) -> &mut P {
+        let (w, h) = (self.width, self.height);
+        self.get_mut(c.x, c.y).unwrap_or_else(|| panic!("{c:?} is out of bounds ({w}x{h})"))
+    }
+}
+/// `img[(x, y)]`, same as indexing by `Coord`
+impl<P: PixelLike> ops::Index<(usize, usize)> for Image<P> {
+    type Output = P;
+    fn index(&self, (x, y): (usize, usize)) -> &P { &self[Coord::new(x, y)] }
+}
+impl<P: PixelLike> ops::IndexMut<(usize, usize)> for Image<P> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut P { &mut self[Coord::new(x, y)] }
+}
+
+/// PPM, P3 and P6
+impl PixelLike for Pixel {
+    const MAXVAL: u16 = 255;
+    const EXTENSION: &'static str = "ppm";
+    const BLANK: Self = Pixel::BLACK;
+
+    fn write_plain(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError> {
+        write!(w, "P3\n{} {}\n255\n", img.width, img.height)?;
+
+        img.write_rows(w, |row, buf| {
+            for &Pixel {r, g, b} in row {
+                push_number(buf, r as u16, 3);
+                buf.push(b' ');
+                push_number(buf, g as u16, 3);
+                buf.push(b' ');
+                push_number(buf, b as u16, 3);
+                buf.push(b'\n');
+            }
+            Ok(())
+        })
+    }
+
+    /// Same header as P3, but each pixel is written as three raw bytes
+    fn write_raw(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError> {
+        write!(w, "P6\n{} {}\n255\n", img.width, img.height)?;
+        img.write_rows(w, |row, buf| {
+            buf.extend(row.iter().flat_map(|p| [p.r, p.g, p.b]));
+            Ok(())
+        })
+    }
+}
+
+/// PPM, P3 and P6, with the image's maxval
+impl PixelLike for Pixel16 {
+    const MAXVAL: u16 = u16::MAX;
+    const EXTENSION: &'static str = "ppm";
+    const BLANK: Self = Pixel16::new(0, 0, 0);
+
+    fn write_plain(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError> {
+        let m = img.maxval;
+        write!(w, "P3\n{} {}\n{}\n", img.width, img.height, m)?;
+        img.write_rows(w, |row, buf| {
+            for &Pixel16 { r, g, b } in row {
+                push_number(buf, r.min(m), 0);
+                buf.push(b' ');
+                push_number(buf, g.min(m), 0);
+                buf.push(b' ');
+                push_number(buf, b.min(m), 0);
+                buf.push(b'\n');
+            }
+            Ok(())
+        })
+    }
+
+    /// Two bytes (big endian) per sample if maxval is over 255, as the spec says
+    fn write_raw(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError> {
+        let m = img.maxval;
+        write!(w, "P6\n{} {}\n{}\n", img.width, img.height, m)?;
+        img.write_rows(w, |row, buf| {
+            for s in row.iter().flat_map(|p| [p.r.min(m), p.g.min(m), p.b.min(m)]) {
+                if m > 255 { buf.extend(s.to_be_bytes()); } else { buf.push(s as u8); }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// PGM, P2 and P5
+impl PixelLike for u8 {
+    const MAXVAL: u16 = 255;
+    const EXTENSION: &'static str = "pgm";
+    const BLANK: Self = 0;
+
+    fn write_plain(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError> {
+        write!(w, "P2\n{} {}\n255\n", img.width, img.height)?;
+        img.write_rows(w, |row, buf| {
+            for &v in row {
+                push_number(buf, v as u16, 3);
+                buf.push(b'\n');
+            }
+            Ok(())
+        })
+    }
+
+    /// One raw byte per pixel
+    fn write_raw(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError> {
+        write!(w, "P5\n{} {}\n255\n", img.width, img.height)?;
+        Ok(w.write_all(&img.atoms)?)
+    }
+}
+
+/// PBM, P1 and P4. Careful: PBM uses 1 for black, while here true is white
+impl PixelLike for bool {
+    const MAXVAL: u16 = 1;
+    const EXTENSION: &'static str = "pbm";
+    const BLANK: Self = false;
+
+    fn write_plain(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError> {
+        write!(w, "P1\n{} {}\n", img.width, img.height)?;
+        img.write_rows(w, |row, buf| {
+            buf.extend(row.iter().map(|&b| if b { b'0' } else { b'1' }));
+            Ok(())
+        })
+    }
+
+    /// Each row is packed into bits (MSB first, 1 meaning black) and padded to a whole byte
+    fn write_raw(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError> {
+        write!(w, "P4\n{} {}\n", img.width, img.height)?;
+        img.write_rows(w, |row, buf| {
+            buf.extend(row.chunks(8).map(|byte| byte.iter().enumerate()
+                .fold(0u8, |acc, (i, &b)| if b { acc } else { acc | (0x80 >> i) })));
+            Ok(())
+        })
+    }
+}
+
+/// PAM (P7) with the `RGB_ALPHA` tuple type. PAM has no plain variant, so both are the same
+impl PixelLike for PixelRgba {
+    const MAXVAL: u16 = 255;
+    const EXTENSION: &'static str = "pam";
+    const BLANK: Self = PixelRgba::TRANSPARENT;
+
+    fn write_plain(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError> { Self::write_raw(img, w) }
+
+    fn write_raw(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError> {
+        write_pam_header(w, img, 4, "RGB_ALPHA")?;
+        img.write_rows(w, |row, buf| {
+            buf.extend(row.iter().flat_map(|p| [p.r, p.g, p.b, p.a]));
+            Ok(())
+        })
+    }
+}
+
+/// PAM (P7) with the `GRAYSCALE_ALPHA` tuple type, same deal as `PixelRgba`
+impl PixelLike for PixelGrayAlpha {
+    const MAXVAL: u16 = 255;
+    const EXTENSION: &'static str = "pam";
+    const BLANK: Self = PixelGrayAlpha::TRANSPARENT;
+
+    fn write_plain(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError> { Self::write_raw(img, w) }
+
+    fn write_raw(img: &Image<Self>, w: &mut impl Write) -> Result<(), PpmError> {
+        write_pam_header(w, img, 2, "GRAYSCALE_ALPHA")?;
+        img.write_rows(w, |row, buf| {
+            buf.extend(row.iter().flat_map(|p| [p.v, p.a]));
+            Ok(())
+        })
+    }
+}
+
+/// Same as `write!(buf, "{v:width$}")` (right aligned, padded with spaces), but without the
+/// formatting machinery, which is the bulk of the time spent writing plain images
+fn push_number(buf: &mut Vec<u8>, v: u16, width: usize) {
+    let mut digits = [0u8; 5];
+    let (mut n, mut start) = (v, digits.len());
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 { break; }
+    }
+    let len = digits.len() - start;
+    buf.extend(std::iter::repeat_n(b' ', width.saturating_sub(len)));
+    buf.extend_from_slice(&digits[start..]);
+}
+
+/// PAM's header, which unlike the other formats says how many samples each pixel has and what
+/// they mean
+fn write_pam_header<P>(w: &mut impl Write, img: &Image<P>, depth: usize, tuple_type: &str) -> Result<(), PpmError> {
+    Ok(write!(w, "P7\nWIDTH {}\nHEIGHT {}\nDEPTH {depth}\nMAXVAL {}\nTUPLTYPE {tuple_type}\nENDHDR\n", img.width, img.height, img.maxval)?)
+}
+
+impl ImagePPM {
+    /// Load an image from a P3 or P6 file. Comments and any maxval are accepted, samples get
+    /// rescaled to 0..=255
+    pub fn load_from_file(filepath: impl Into<PathBuf>) -> Result<Self, PpmError> {
+        Self::from_reader(BufReader::new(File::open(filepath.into())?))
+    }
+
+    /// Same as `load_from_file`, but from anything readable
+    pub fn from_reader(reader: impl Read) -> Result<Self, PpmError> {
+        let (width, height, _, atoms) = parse::read_ppm(reader, |[r, g, b], maxval| {
+            Pixel::new(parse::to_u8(r, maxval), parse::to_u8(g, maxval), parse::to_u8(b, maxval))
+        })?;
+
+        Ok(Self::from_atoms(width, height, atoms))
+    }
+
+    /// Pixels as packed RGB bytes, top row first: the layout most other libraries take raw buffers in
+    pub fn to_rgb_bytes(&self) -> Vec<u8> { self.atoms.iter().flat_map(|p| [p.r, p.g, p.b]).collect() }
+
+    /// The inverse of `to_rgb_bytes`. `None` if there aren't exactly `width*height*3` bytes
+    pub fn from_rgb_bytes(width: usize, height: usize, bytes: &[u8]) -> Option<Self> {
+        if Some(bytes.len()) != width.checked_mul(height).and_then(|n| n.checked_mul(3)) { return None; }
+        Some(Self::from_atoms(width, height, bytes.chunks_exact(3).map(|c| Pixel::new(c[0], c[1], c[2])).collect()))
+    }
+
+    /// Color at `p`, in the same coordinates as `get` but between pixels too. Anything past the
+    /// edges gets the closest edge pixel. Panics if the image is empty
+    pub fn sample(&self, p: impl Into<CoordF>, filter: ResizeFilter) -> Pixel {
+        let p = p.into();
+        let at = |x: f64, y: f64| *self.get(x.clamp(0.0, (self.width() - 1) as f64) as usize, y.clamp(0.0, (self.height() - 1) as f64) as usize).unwrap();
+        match filter {
+            ResizeFilter::Nearest => at(p.x.round(), p.y.round()),
+            ResizeFilter::Bilinear => {
+                let (x0, y0) = (p.x.floor(), p.y.floor());
+                let (tx, ty) = (p.x - x0, p.y - y0);
+                let bottom = at(x0, y0).blend(at(x0 + 1.0, y0), tx);
+                let top = at(x0, y0 + 1.0).blend(at(x0 + 1.0, y0 + 1.0), tx);
+                bottom.blend(top, ty)
+            },
+        }
+    }
+
+    /// Same as `sample`, but with texture coordinates: (0, 0) is the bottom left corner of the
+    /// image and (1, 1) the top right one
+    pub fn sample_uv(&self, uv: impl Into<CoordF>, filter: ResizeFilter) -> Pixel {
+        let uv = uv.into();
+        self.sample((uv.x*self.width() as f64 - 0.5, uv.y*self.height() as f64 - 0.5), filter)
+    }
+
+    /// Composite `src` on top of this image (source-over), with its bottom left corner at `dest`.
+    /// Whatever falls outside of this image is ignored
+    pub fn blit_with_alpha(&mut self, src: &ImageRGBA, dest: Coord) {
+        for y in 0..src.height.min(self.height.saturating_sub(dest.y)) {
+        for x in 0..src.width.min(self.width.saturating_sub(dest.x)) {
+            let under = self.get_mut(dest.x + x, dest.y + y).unwrap();
+            *under = src.get(x, y).unwrap().over(*under);
+        }
+        }
+    }
+}
+
+impl ImagePPM16 {
+    /// Load an image from a P3 or P6 file, keeping its samples and maxval as they are
+    pub fn load_from_file(filepath: impl Into<PathBuf>) -> Result<Self, PpmError> {
+        Self::from_reader(BufReader::new(File::open(filepath.into())?))
+    }
+
+    /// Same as `load_from_file`, but from anything readable
+    pub fn from_reader(reader: impl Read) -> Result<Self, PpmError> {
+        let (width, height, maxval, atoms) = parse::read_ppm(reader, |[r, g, b], _| Pixel16::new(r as u16, g as u16, b as u16))?;
+
+        Ok(Self::from_atoms(width, height, atoms).with_maxval(maxval as u16))
+    }
+
+    /// Change the maxval without touching the samples (so it changes what they mean). Samples
+    /// bigger than it get clamped when writing. Panics if 0
+    pub fn set_maxval(&mut self, maxval: u16) {
+        assert!(maxval > 0, "maxval must be at least 1");
+        self.maxval = maxval;
+    }
+
+    /// Builder-style `set_maxval`
+    pub fn with_maxval(mut self, maxval: u16) -> Self { self.set_maxval(maxval); self }
+
+    /// Rescale down to an 8 bit image
+    pub fn to_8bit(&self) -> ImagePPM {
+        let s = |v: u16| parse::to_u8(v.min(self.maxval) as usize, self.maxval as usize);
+        ImagePPM::from_atoms(self.width, self.height, self.atoms.iter().map(|p| Pixel::new(s(p.r), s(p.g), s(p.b))).collect())
+    }
+}
+
+impl From<&ImagePPM> for ImagePPM16 {
+    /// Scales 0..=255 up to the full 0..=65535 range
+    fn from(img: &ImagePPM) -> Self {
+        let atoms = img.atoms.iter().map(|p| Pixel16::new(p.r as u16 * 257, p.g as u16 * 257, p.b as u16 * 257)).collect();
+        Self::from_atoms(img.width, img.height, atoms)
+    }
+}
+
+impl ImageRGBA {
+    /// Composite the whole layer on top of a solid background
+    pub fn flatten(&self, bg: Pixel) -> ImagePPM {
+        ImagePPM::from_atoms(self.width, self.height, self.atoms.iter().map(|p| p.over(bg)).collect())
+    }
+}
+
+impl ImageGrayAlpha {
+    /// Composite the whole layer on top of a solid gray background
+    pub fn flatten(&self, bg: u8) -> ImagePGM {
+        ImagePGM::from_atoms(self.width, self.height, self.atoms.iter().map(|p| bg.blend(p.v, p.a as f64 / 255.0)).collect())
+    }
+}
+
+impl From<&ImagePPM> for ImagePGM {
+    /// Converts by luminance, not by plain averaging
+    fn from(img: &ImagePPM) -> Self {
+        Self::from_atoms(img.width, img.height, img.atoms.iter().map(Pixel::luminance).collect())
+    }
+}
+
+impl ImagePBM {
+    /// Binarizes by luminance: pixels at least as bright as `threshold` become foreground (white)
+    pub fn from_threshold(img: &ImagePPM, threshold: u8) -> Self {
+        Self::from_atoms(img.width, img.height, img.atoms.iter().map(|p| p.luminance() >= threshold).collect())
     }
 }
@@ -0,0 +1,98 @@
+//! Seeded gradient noise (Perlin and simplex) for procedural textures. Same seed, same noise, on
+//! every platform
+use crate::{color::Gradient, random::Rng, CoordF, ImagePPM};
+
+fn fade(t: f64) -> f64 { t*t*t*(t*(t*6.0 - 15.0) + 10.0) }
+
+const GRADIENTS: [(f64, f64); 8] = [(1.0, 1.0), (-1.0, 1.0), (1.0, -1.0), (-1.0, -1.0), (1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0)];
+
+/// 2D noise generator. All of its functions give smooth values in about -1..=1 that repeat every
+/// 256 units, changing noticeably over distances of about 1 (so scale the inputs down to zoom in)
+#[derive(Clone, Debug)]
+pub struct Noise {
+    /// Shuffled 0..=255, twice so lookups can skip the wrapping
+    perm: [u8; 512],
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut table: [u8; 256] = std::array::from_fn(|i| i as u8);
+        for i in (1..256).rev() {
+            table.swap(i, (rng.next_u64() % (i as u64 + 1)) as usize);
+        }
+        Self { perm: std::array::from_fn(|i| table[i % 256]) }
+    }
+
+    fn hash(&self, x: isize, y: isize) -> usize {
+        self.perm[self.perm[(x & 255) as usize] as usize + (y & 255) as usize] as usize
+    }
+
+    fn grad(&self, x: isize, y: isize, dx: f64, dy: f64) -> f64 {
+        let (gx, gy) = GRADIENTS[self.hash(x, y) % GRADIENTS.len()];
+        gx*dx + gy*dy
+    }
+
+    /// Classic (improved) Perlin noise
+    pub fn perlin(&self, p: impl Into<CoordF>) -> f64 {
+        let p = p.into();
+        let (x0, y0) = (p.x.floor(), p.y.floor());
+        let (dx, dy) = (p.x - x0, p.y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+        let (u, v) = (fade(dx), fade(dy));
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a)*t;
+        let bottom = lerp(self.grad(x0, y0, dx, dy), self.grad(x0 + 1, y0, dx - 1.0, dy), u);
+        let top = lerp(self.grad(x0, y0 + 1, dx, dy - 1.0), self.grad(x0 + 1, y0 + 1, dx - 1.0, dy - 1.0), u);
+        lerp(bottom, top, v)
+    }
+
+    /// Simplex noise (Gustavson's take on Perlin's): fewer directional artifacts than `perlin`
+    pub fn simplex(&self, p: impl Into<CoordF>) -> f64 {
+        let p = p.into();
+        let f2 = 0.5*(3f64.sqrt() - 1.0);
+        let g2 = (3.0 - 3f64.sqrt())/6.0;
+        // Skew to find which simplex (triangle) we're in
+        let s = (p.x + p.y)*f2;
+        let (i, j) = ((p.x + s).floor(), (p.y + s).floor());
+        let t = (i + j)*g2;
+        let (x0, y0) = (p.x - (i - t), p.y - (j - t));
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let corners = [
+            (0, 0, x0, y0),
+            (i1, j1, x0 - i1 as f64 + g2, y0 - j1 as f64 + g2),
+            (1, 1, x0 - 1.0 + 2.0*g2, y0 - 1.0 + 2.0*g2),
+        ];
+        let (i, j) = (i as isize, j as isize);
+        70.0 * corners.iter().map(|&(di, dj, x, y)| {
+            let t = 0.5 - x*x - y*y;
+            if t < 0.0 { 0.0 } else { t.powi(4) * self.grad(i + di, j + dj, x, y) }
+        }).sum::<f64>()
+    }
+
+    /// Fractal Brownian motion: `octaves` layers of `perlin`, each at twice the frequency and half
+    /// the amplitude of the last, for a more natural, detailed look. Still in about -1..=1
+    pub fn fbm(&self, p: impl Into<CoordF>, octaves: usize) -> f64 {
+        let p = p.into();
+        let (mut sum, mut amp, mut total, mut freq) = (0.0, 1.0, 0.0, 1.0);
+        for _ in 0..octaves.max(1) {
+            sum += amp*self.perlin(p*freq);
+            total += amp;
+            amp *= 0.5;
+            freq *= 2.0;
+        }
+        sum / total
+    }
+}
+
+impl ImagePPM {
+    /// Perlin noise colored by `gradient`, one noise unit every `scale` pixels (so bigger scales
+    /// make bigger blobs). For other kinds of noise, use `Noise` with `from_fn`
+    pub fn from_noise(width: usize, height: usize, scale: f64, seed: u64, gradient: &Gradient) -> Self {
+        let noise = Noise::new(seed);
+        let scale = if scale > 0.0 { scale } else { 1.0 };
+        Self::from_fn(width, height, |c| {
+            let n = noise.perlin(CoordF::from(c)*(1.0 / scale));
+            gradient.sample((n + 1.0) / 2.0)
+        })
+    }
+}
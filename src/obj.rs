@@ -0,0 +1,42 @@
+//! Wavefront OBJ (https://en.wikipedia.org/wiki/Wavefront_.obj_file), just enough for meshes:
+//! `v` vertices and `f` faces. Texture coordinates, normals, groups, materials... are skipped
+use std::{fs::File, io::{BufRead, BufReader, Read}, path::PathBuf};
+
+use crate::{parse::invalid, render3d::{Mesh, Vec3}, PpmError};
+
+impl Mesh {
+    /// Read an OBJ mesh. Faces with more than three corners are split into a fan of triangles,
+    /// and negative indices count back from the last vertex, like the format says
+    pub fn from_obj_reader(reader: impl Read) -> Result<Self, PpmError> {
+        let mut mesh = Mesh::default();
+        for (line_no, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let err = |msg: &str| invalid(format!("line {}: {msg}", line_no + 1));
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    let coords: Vec<f64> = tokens.take(3).map(str::parse).collect::<Result<_, _>>().map_err(|_| err("bad vertex coordinate"))?;
+                    let &[x, y, z] = &coords[..] else { return Err(err("vertices need three coordinates")) };
+                    mesh.vertices.push(Vec3::new(x, y, z));
+                },
+                Some("f") => {
+                    // Each corner is `v`, `v/vt`, `v/vt/vn` or `v//vn`, only `v` matters here
+                    let corners = tokens.map(|t| {
+                        let i: isize = t.split('/').next().unwrap().parse().map_err(|_| err("bad face index"))?;
+                        let n = mesh.vertices.len() as isize;
+                        let i = if i < 0 { n + i } else { i - 1 };
+                        if (0..n).contains(&i) { Ok(i as usize) } else { Err(err("face index out of range")) }
+                    }).collect::<Result<Vec<usize>, _>>()?;
+                    if corners.len() < 3 { return Err(err("faces need at least three corners")); }
+                    mesh.faces.extend(corners[1..].windows(2).map(|w| [corners[0], w[0], w[1]]));
+                },
+                _ => {},
+            }
+        }
+        Ok(mesh)
+    }
+
+    pub fn load_obj(filepath: impl Into<PathBuf>) -> Result<Self, PpmError> {
+        Self::from_obj_reader(File::open(filepath.into())?)
+    }
+}
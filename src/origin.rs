@@ -0,0 +1,76 @@
+//! Choosing which corner (0, 0) is
+use std::{io::Write, ops};
+
+use crate::{tile::WrapMode, Coord, Draw, PpmError, PpmFormat};
+
+/// Where (0, 0) is. Only coordinates change, storage (and so every file written) stays the same
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Origin {
+    /// y grows upwards, like in maths. What every image uses unless told otherwise
+    #[default]
+    BottomLeft,
+    /// y grows downwards, like in most other graphics libraries
+    TopLeft,
+}
+
+/// Wraps an image so (0, 0) is its top left corner. Everything in `PpmFormat` works as usual, just
+/// mirrored vertically: "bottom left corner" arguments become the top left one, and since y grows
+/// downwards rotations go the other way around
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopLeft<I>(pub I);
+
+impl<I> TopLeft<I> {
+    pub fn into_inner(self) -> I { self.0 }
+}
+
+impl<I> From<I> for TopLeft<I> {
+    fn from(img: I) -> Self { TopLeft(img) }
+}
+
+impl<I: PpmFormat> Draw for TopLeft<I> {
+    type Atom = I::Atom;
+
+    fn width(&self) -> usize { self.0.width() }
+    fn height(&self) -> usize { self.0.height() }
+    fn origin(&self) -> Origin { Origin::TopLeft }
+    fn wrap_mode(&self) -> WrapMode { self.0.wrap_mode() }
+
+    // Storage is already top row first, so it's just the plain index
+    fn get(&self, x: usize, y: usize) -> Option<&I::Atom> {
+        if x >= self.width() || y >= self.height() { return None; }
+        Some(&self.atoms()[x + y*self.width()])
+    }
+
+    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut I::Atom> {
+        if x >= self.width() || y >= self.height() { return None; }
+        let i = x + y*self.width();
+        Some(&mut self.atoms_mut()[i])
+    }
+}
+
+impl<I: PpmFormat> PpmFormat for TopLeft<I> {
+    fn new(width: usize, height: usize, bg_color: I::Atom) -> Self { TopLeft(I::new(width, height, bg_color)) }
+    fn new_like(&self, width: usize, height: usize, bg_color: I::Atom) -> Self { TopLeft(self.0.new_like(width, height, bg_color)) }
+    fn blank(&self) -> Option<I::Atom> { self.0.blank() }
+    fn atoms(&self) -> &Vec<I::Atom> { self.0.atoms() }
+    fn atoms_mut(&mut self) -> &mut Vec<I::Atom> { self.0.atoms_mut() }
+
+    fn pixels(&self) -> impl DoubleEndedIterator<Item = &I::Atom> { self.atoms().iter() }
+    fn pixels_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut I::Atom> { self.atoms_mut().iter_mut() }
+
+    fn write_to(&self, w: impl Write) -> Result<(), PpmError> { self.0.write_to(w) }
+    fn write_binary_to(&self, w: impl Write) -> Result<(), PpmError> { self.0.write_binary_to(w) }
+}
+
+impl<I: PpmFormat> ops::Index<Coord> for TopLeft<I> {
+    type Output = I::Atom;
+    fn index(&self, c: Coord) -> &I::Atom {
+        self.get(c.x, c.y).unwrap_or_else(|| panic!("{c:?} is out of bounds ({}x{})", self.width(), self.height()))
+    }
+}
+impl<I: PpmFormat> ops::IndexMut<Coord> for TopLeft<I> {
+    fn index_mut(&mut self, c: Coord) -> &mut I::Atom {
+        let (w, h) = (self.width(), self.height());
+        self.get_mut(c.x, c.y).unwrap_or_else(|| panic!("{c:?} is out of bounds ({w}x{h})"))
+    }
+}
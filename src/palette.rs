@@ -0,0 +1,145 @@
+//! Limited palettes: reducing an image to a fixed set of colors
+use crate::{ImagePBM, ImagePPM, Pixel};
+
+/// How to spread the error when snapping colors to a palette
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DitherMethod {
+    /// Plain nearest color, no dithering at all
+    None,
+    /// Error diffusion: each pixel's error is pushed onto its unvisited neighbours
+    FloydSteinberg,
+    /// Ordered dithering with a 4x4 Bayer matrix: the regular crosshatch look
+    Bayer,
+}
+
+const BAYER_4X4: [[u8; 4]; 4] = [
+    [ 0,  8,  2, 10],
+    [12,  4, 14,  6],
+    [ 3, 11,  1,  9],
+    [15,  7, 13,  5],
+];
+
+fn dist2(a: [f64; 3], b: Pixel) -> f64 {
+    let (dr, dg, db) = (a[0] - b.r as f64, a[1] - b.g as f64, a[2] - b.b as f64);
+    dr*dr + dg*dg + db*db
+}
+
+/// Closest palette color, by plain euclidean distance in RGB
+fn nearest(palette: &[Pixel], c: [f64; 3]) -> Pixel {
+    *palette.iter().min_by(|a, b| dist2(c, **a).total_cmp(&dist2(c, **b))).unwrap()
+}
+
+/// Roughly how far apart the palette colors are, per channel. Used as the amplitude of the
+/// Bayer pattern: 255 for black and white, 85 for four evenly spaced grays, etc.
+fn spread(palette: &[Pixel]) -> f64 {
+    let cheb = |a: Pixel, b: Pixel| a.r.abs_diff(b.r).max(a.g.abs_diff(b.g)).max(a.b.abs_diff(b.b)) as f64;
+    let gaps: Vec<f64> = palette.iter().enumerate().filter_map(|(i, &a)| {
+        palette.iter().enumerate().filter(|&(j, _)| j != i).map(|(_, &b)| cheb(a, b)).min_by(f64::total_cmp)
+    }).collect();
+    if gaps.is_empty() { 0.0 } else { gaps.iter().sum::<f64>() / gaps.len() as f64 }
+}
+
+fn channels(p: Pixel) -> [f64; 3] { [p.r as f64, p.g as f64, p.b as f64] }
+
+impl ImagePPM {
+    /// Redraws the image using only colors from `palette`. Panics if `palette` is empty
+    pub fn dither(&self, palette: &[Pixel], method: DitherMethod) -> ImagePPM {
+        assert!(!palette.is_empty(), "can't dither to an empty palette");
+        let (w, h) = (self.width, self.height);
+        let atoms = match method {
+            DitherMethod::None => self.atoms.iter().map(|&p| nearest(palette, channels(p))).collect(),
+            DitherMethod::FloydSteinberg => {
+                // Storage is top row first, so "below" is just the next row here
+                let mut work: Vec<[f64; 3]> = self.atoms.iter().map(|&p| channels(p)).collect();
+                let mut out = Vec::with_capacity(w*h);
+                for y in 0..h {
+                for x in 0..w {
+                    let old = work[x + y*w];
+                    let new = nearest(palette, old);
+                    let err = [old[0] - new.r as f64, old[1] - new.g as f64, old[2] - new.b as f64];
+                    let mut push = |dx: isize, dy: usize, weight: f64| {
+                        let nx = x as isize + dx;
+                        if nx < 0 || nx >= w as isize || y + dy >= h { return; }
+                        let c = &mut work[nx as usize + (y + dy)*w];
+                        for i in 0..3 { c[i] += err[i] * weight; }
+                    };
+                    push(1, 0, 7.0/16.0);
+                    push(-1, 1, 3.0/16.0);
+                    push(0, 1, 5.0/16.0);
+                    push(1, 1, 1.0/16.0);
+                    out.push(new);
+                }
+                }
+                out
+            },
+            DitherMethod::Bayer => {
+                let amp = spread(palette);
+                self.atoms.iter().enumerate().map(|(i, &p)| {
+                    let (x, y) = (i % w, i / w);
+                    let offset = amp * ((BAYER_4X4[y % 4][x % 4] as f64 + 0.5) / 16.0 - 0.5);
+                    nearest(palette, channels(p).map(|c| c + offset))
+                }).collect()
+            },
+        };
+        ImagePPM::from_atoms(w, h, atoms)
+    }
+}
+
+impl ImagePBM {
+    /// Like `from_threshold`, but dithers to black and white first so gradients survive
+    pub fn from_dithered(img: &ImagePPM, method: DitherMethod) -> Self {
+        let bw = img.dither(&[Pixel::BLACK, Pixel::WHITE], method);
+        Self::from_atoms(bw.width, bw.height, bw.atoms.iter().map(|p| p.r == 255).collect())
+    }
+}
+
+/// Distinct colors with how many pixels have them
+type ColorBox = Vec<([u8; 3], usize)>;
+
+/// Channel with the widest range in the box, and that range
+fn widest_channel(b: &ColorBox) -> (usize, u8) {
+    (0..3).map(|ch| {
+        let (lo, hi) = b.iter().fold((255, 0), |(lo, hi), (c, _)| (c[ch].min(lo), c[ch].max(hi)));
+        (ch, hi.saturating_sub(lo))
+    }).max_by_key(|&(_, range)| range).unwrap()
+}
+
+fn box_average(b: &ColorBox) -> Pixel {
+    let total: usize = b.iter().map(|(_, n)| n).sum();
+    let avg = |ch: usize| ((b.iter().map(|(c, n)| c[ch] as usize * n).sum::<usize>() + total/2) / total) as u8;
+    Pixel::new(avg(0), avg(1), avg(2))
+}
+
+impl ImagePPM {
+    /// Picks (up to) `n` representative colors with median cut and redraws the image with them.
+    /// Returns the new image and the palette. Panics if `n` is zero
+    pub fn quantize(&self, n: usize) -> (ImagePPM, Vec<Pixel>) {
+        assert!(n > 0, "can't quantize to zero colors");
+        let mut counts = std::collections::HashMap::new();
+        for p in &self.atoms { *counts.entry([p.r, p.g, p.b]).or_insert(0) += 1; }
+        if counts.is_empty() { return (self.clone(), vec![]); }
+
+        let mut boxes: Vec<ColorBox> = vec![counts.into_iter().collect()];
+        while boxes.len() < n {
+            // Split the box that's most spread out, between the two values of that channel
+            // closest to the (pixel count weighted) median
+            let Some((i, (ch, _))) = boxes.iter().map(widest_channel).enumerate()
+                .filter(|(_, (_, range))| *range > 0).max_by_key(|(_, (_, range))| *range)
+                else { break };
+            let mut b = boxes.swap_remove(i);
+            b.sort_unstable_by_key(|&(c, _)| (c[ch], c));
+            let total: usize = b.iter().map(|(_, n)| n).sum();
+            let mut acc = 0;
+            let cut = (1..b.len()).filter_map(|k| {
+                acc += b[k - 1].1;
+                (b[k - 1].0[ch] != b[k].0[ch]).then_some((k, (acc*2).abs_diff(total)))
+            }).min_by_key(|&(_, off)| off).unwrap().0;
+            let rest = b.split_off(cut);
+            boxes.push(b);
+            boxes.push(rest);
+        }
+
+        let palette: Vec<Pixel> = boxes.iter().map(box_average).collect();
+        (self.dither(&palette, DitherMethod::None), palette)
+    }
+}
@@ -0,0 +1,42 @@
+//! Multi-threaded constructors and maps, for embarrassingly parallel work like fractals or ray
+//! tracing. Plain `std::thread::scope` threads, one contiguous band of rows each
+use std::{num::NonZeroUsize, thread};
+
+use crate::{Coord, Image, PixelLike};
+
+/// How many rows each thread gets
+fn rows_per_thread(height: usize) -> usize {
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    height.div_ceil(threads).max(1)
+}
+
+impl<P: PixelLike + Send + Sync> Image<P> {
+    /// Builds an image by calling `f` for every coordinate, spread over all cores. Same
+    /// coordinates as `get`
+    pub fn par_from_fn(width: usize, height: usize, f: impl Fn(Coord) -> P + Sync) -> Self {
+        let band = rows_per_thread(height);
+        let f = &f;
+        let atoms = thread::scope(|s| {
+            // Storage row r is y = height - 1 - r
+            let handles: Vec<_> = (0..height).step_by(band).map(|r0| s.spawn(move || {
+                (r0..(r0 + band).min(height))
+                    .flat_map(|r| (0..width).map(move |x| f(Coord::new(x, height - 1 - r))))
+                    .collect::<Vec<P>>()
+            })).collect();
+            handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+        });
+        Self::from_atoms(width, height, atoms)
+    }
+
+    /// Replaces every pixel with `f` of itself in place, spread over all cores (unlike
+    /// `map_pixels`, which makes a new image)
+    pub fn par_apply_pixels(&mut self, f: impl Fn(P) -> P + Sync) {
+        let chunk = (rows_per_thread(self.height) * self.width).max(1);
+        let f = &f;
+        thread::scope(|s| {
+            for part in self.atoms.chunks_mut(chunk) {
+                s.spawn(move || part.iter_mut().for_each(|p| *p = f(*p)));
+            }
+        });
+    }
+}
@@ -0,0 +1,113 @@
+//! Tiny hand-rolled parser for the Netpbm headers and rasters. Everything gets read into memory
+//! first, which is fine for the sizes this crate deals with
+use std::io::Read;
+
+use crate::PpmError;
+
+pub(crate) fn invalid(msg: impl Into<String>) -> PpmError {
+    PpmError::Parse(msg.into())
+}
+
+pub(crate) struct Parser {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl Parser {
+    pub(crate) fn new(mut reader: impl Read) -> Result<Self, PpmError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok(Self { data, pos: 0 })
+    }
+
+    /// Skips whitespace and `#` comments (which run until the end of the line)
+    fn skip_filler(&mut self) {
+        while let Some(&c) = self.data.get(self.pos) {
+            if c == b'#' {
+                while self.data.get(self.pos).is_some_and(|&c| c != b'\n' && c != b'\r') { self.pos += 1; }
+            } else if c.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Reads the two byte magic number, like `P3`
+    pub(crate) fn magic(&mut self) -> Result<[u8; 2], PpmError> {
+        match self.data.get(self.pos..self.pos + 2) {
+            Some(&[a, b]) => { self.pos += 2; Ok([a, b]) }
+            _ => Err(invalid("missing magic number")),
+        }
+    }
+
+    /// Reads an ASCII decimal number, skipping any whitespace or comments before it
+    pub(crate) fn number(&mut self) -> Result<usize, PpmError> {
+        self.skip_filler();
+        let start = self.pos;
+        while self.data.get(self.pos).is_some_and(u8::is_ascii_digit) { self.pos += 1; }
+        if start == self.pos { return Err(invalid(format!("expected a number at byte {start}"))); }
+
+        std::str::from_utf8(&self.data[start..self.pos]).unwrap()
+            .parse()
+            .map_err(|_| invalid(format!("number too big at byte {start}")))
+    }
+
+    /// Reads an ASCII sample, checking it against `maxval`
+    pub(crate) fn ascii_sample(&mut self, maxval: usize) -> Result<usize, PpmError> {
+        let v = self.number()?;
+        if v > maxval { return Err(invalid(format!("sample {v} is bigger than maxval {maxval}"))); }
+        Ok(v)
+    }
+
+    /// Reads the maxval, which must be in 1..=65535
+    pub(crate) fn maxval(&mut self) -> Result<usize, PpmError> {
+        let maxval = self.number()?;
+        if !(1..=65535).contains(&maxval) { return Err(invalid(format!("invalid maxval {maxval}"))); }
+        Ok(maxval)
+    }
+
+    /// Binary rasters start after exactly one whitespace character following the header
+    pub(crate) fn raster_start(&mut self) -> Result<(), PpmError> {
+        match self.data.get(self.pos) {
+            Some(c) if c.is_ascii_whitespace() => { self.pos += 1; Ok(()) }
+            _ => Err(invalid("expected whitespace before the raster")),
+        }
+    }
+
+    /// Reads a raw sample: one byte if `maxval` < 256, two (big endian) otherwise
+    pub(crate) fn binary_sample(&mut self, maxval: usize) -> Result<usize, PpmError> {
+        let width = if maxval < 256 { 1 } else { 2 };
+        let bytes = self.data.get(self.pos..self.pos + width).ok_or_else(|| invalid("raster ended early"))?;
+        self.pos += width;
+        let v = bytes.iter().fold(0, |acc, &b| (acc << 8) | b as usize);
+        if v > maxval { return Err(invalid(format!("sample {v} is bigger than maxval {maxval}"))); }
+        Ok(v)
+    }
+}
+
+/// Rescales a sample in `0..=maxval` to `0..=255`, rounding to the nearest
+pub(crate) fn to_u8(v: usize, maxval: usize) -> u8 {
+    ((v * 255 + maxval / 2) / maxval) as u8
+}
+
+/// Reads a whole P3 or P6 file, returning `(width, height, maxval, pixels)`. Each pixel's three
+/// samples (untouched, in file order) go through `pixel` as they're read, so the raster is never
+/// held as loose samples
+pub(crate) fn read_ppm<T>(reader: impl Read, mut pixel: impl FnMut([usize; 3], usize) -> T) -> Result<(usize, usize, usize, Vec<T>), PpmError> {
+    let mut p = Parser::new(reader)?;
+    let binary = match &p.magic()? {
+        b"P3" => false,
+        b"P6" => true,
+        m => return Err(invalid(format!("expected P3 or P6, got {:?}", String::from_utf8_lossy(m)))),
+    };
+    let (width, height, maxval) = (p.number()?, p.number()?, p.maxval()?);
+    if binary { p.raster_start()?; }
+
+    let count = width.checked_mul(height).filter(|n| n.checked_mul(3).is_some()).ok_or_else(|| invalid("image is too big"))?;
+    let mut sample = || if binary { p.binary_sample(maxval) } else { p.ascii_sample(maxval) };
+    let pixels = (0..count)
+        .map(|_| Ok(pixel([sample()?, sample()?, sample()?], maxval)))
+        .collect::<Result<_, PpmError>>()?;
+    Ok((width, height, maxval, pixels))
+}
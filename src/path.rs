@@ -0,0 +1,258 @@
+//! Retained paths made of lines and Bézier curves, in the spirit of the HTML canvas: build one up
+//! with `move_to`, `line_to`, `curve_to`... and then stroke or fill it with `Draw::stroke_path`
+//! and `Draw::fill_path`
+use crate::{draw_span, transform::Transform, CoordF, Draw};
+
+/// One connected run of points
+#[derive(Clone, Debug, PartialEq)]
+struct Contour {
+    points: Vec<CoordF>,
+    closed: bool,
+}
+
+/// Any number of contours, each one a polyline that may be closed. Curves are turned into short
+/// segments as they're added
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    contours: Vec<Contour>,
+}
+
+/// Segments to split a curve into: about one every two pixels of its control polygon
+fn curve_steps(control: &[CoordF]) -> usize {
+    let len: f64 = control.windows(2).map(|w| w[0].distance(w[1])).sum();
+    ((len / 2.0).ceil() as usize).clamp(1, 1024)
+}
+
+impl Path {
+    pub fn new() -> Self { Self::default() }
+
+    /// Start a new contour at `p`
+    pub fn move_to(mut self, p: impl Into<CoordF>) -> Self {
+        self.contours.push(Contour { points: vec![p.into()], closed: false });
+        self
+    }
+
+    /// Where the last contour ends, if there's any
+    fn current(&self) -> Option<CoordF> {
+        self.contours.last().filter(|c| !c.closed).and_then(|c| c.points.last().copied())
+    }
+
+    /// Adds `points` to the current contour, starting a new one if there's none (or the last one
+    /// was closed). Like in HTML, a new one starts at its first point
+    fn extend(mut self, points: impl IntoIterator<Item = CoordF>) -> Self {
+        let mut points = points.into_iter();
+        if self.current().is_none() {
+            let Some(first) = points.next() else { return self };
+            self = self.move_to(first);
+        }
+        self.contours.last_mut().unwrap().points.extend(points);
+        self
+    }
+
+    /// Straight line from the current point to `p`
+    pub fn line_to(self, p: impl Into<CoordF>) -> Self { self.extend([p.into()]) }
+
+    /// Quadratic Bézier curve from the current point to `to`, pulled towards `ctrl`
+    pub fn quad_to(self, ctrl: impl Into<CoordF>, to: impl Into<CoordF>) -> Self {
+        let (ctrl, to) = (ctrl.into(), to.into());
+        let from = self.current().unwrap_or(ctrl);
+        let n = curve_steps(&[from, ctrl, to]);
+        self.extend((1..=n).map(|i| {
+            let t = i as f64 / n as f64;
+            from.lerp(ctrl, t).lerp(ctrl.lerp(to, t), t)
+        }))
+    }
+
+    /// Cubic Bézier curve from the current point to `to`, with control points `c1` and `c2`
+    /// (HTML's `bezierCurveTo`)
+    pub fn curve_to(self, c1: impl Into<CoordF>, c2: impl Into<CoordF>, to: impl Into<CoordF>) -> Self {
+        let (c1, c2, to) = (c1.into(), c2.into(), to.into());
+        let from = self.current().unwrap_or(c1);
+        let n = curve_steps(&[from, c1, c2, to]);
+        self.extend((1..=n).map(|i| {
+            let t = i as f64 / n as f64;
+            let (a, b, c) = (from.lerp(c1, t), c1.lerp(c2, t), c2.lerp(to, t));
+            a.lerp(b, t).lerp(b.lerp(c, t), t)
+        }))
+    }
+
+    /// Circular arc around `center`, from `start` to `end` (radians, counterclockwise from the
+    /// positive x axis; `end` below `start` goes clockwise). Like in HTML, if there's a current
+    /// point a straight line joins it to where the arc starts
+    pub fn arc(self, center: impl Into<CoordF>, radius: f64, start: f64, end: f64) -> Self {
+        let center = center.into();
+        // Segments about a pixel long
+        let n = ((end - start).abs() * radius).ceil().clamp(1.0, 4096.0) as usize;
+        self.extend((0..=n).map(|i| {
+            let t = start + (end - start) * i as f64 / n as f64;
+            (center + CoordF::new(t.cos(), t.sin())*radius).snapped()
+        }))
+    }
+
+    /// Closed rectangle from `corner` to `corner + (w, h)` with its corners rounded off by
+    /// `radius` (at most half of the shorter side), as a new contour
+    pub fn rounded_rect(self, corner: impl Into<CoordF>, w: f64, h: f64, radius: f64) -> Self {
+        use std::f64::consts::{FRAC_PI_2, PI};
+        let (a, r) = (corner.into(), radius.clamp(0.0, w.min(h) / 2.0));
+        let b = a + CoordF::new(w, h);
+        self.move_to((a.x + r, a.y))
+            .arc((b.x - r, a.y + r), r, -FRAC_PI_2, 0.0)
+            .arc((b.x - r, b.y - r), r, 0.0, FRAC_PI_2)
+            .arc((a.x + r, b.y - r), r, FRAC_PI_2, PI)
+            .arc((a.x + r, a.y + r), r, PI, 3.0*FRAC_PI_2)
+            .close()
+    }
+
+    /// Join the current contour back to its start. Whatever comes next starts a new contour
+    pub fn close(mut self) -> Self {
+        if let Some(c) = self.contours.last_mut() { c.closed = true; }
+        self
+    }
+
+    /// Every contour's points, and whether it's closed
+    pub fn contours(&self) -> impl Iterator<Item = (&[CoordF], bool)> {
+        self.contours.iter().map(|c| (&c.points[..], c.closed))
+    }
+
+    /// The path as the `d` attribute of an SVG `<path>`, in the same coordinates (SVG's y axis
+    /// points down, so flip it with `transformed` first if that matters)
+    pub fn to_svg_data(&self) -> String {
+        let mut d = String::new();
+        for (points, closed) in self.contours() {
+            for (i, p) in points.iter().enumerate() {
+                if !d.is_empty() { d.push(' '); }
+                d += &format!("{}{} {}", if i == 0 { 'M' } else { 'L' }, svg_number(p.x), svg_number(p.y));
+            }
+            if closed { d += " Z"; }
+        }
+        d
+    }
+
+    /// Copy with every point moved by `t`
+    pub fn transformed(&self, t: Transform) -> Self {
+        let contours = self.contours.iter().map(|c| Contour { points: c.points.iter().map(|&p| t.apply(p)).collect(), closed: c.closed }).collect();
+        Self { contours }
+    }
+}
+
+/// Numbers for SVG attributes: three decimals at most, and no trailing zeros
+pub(crate) fn svg_number(v: f64) -> String {
+    let s = format!("{v:.3}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".into() } else { s.into() }
+}
+
+/// Fill everything inside `contours` with the even-odd rule: a pixel is filled if a ray from its
+/// center crosses the outlines an odd number of times. Contours are closed automatically, so holes
+/// just need to be another contour inside
+pub(crate) fn fill_even_odd<I: Draw + ?Sized>(img: &mut I, contours: &[&[CoordF]], col: I::Atom) {
+    let all = contours.iter().flat_map(|c| c.iter());
+    let y_max = all.clone().map(|p| p.y).fold(f64::NEG_INFINITY, f64::max);
+    let y_min = all.map(|p| p.y).fold(f64::INFINITY, f64::min);
+
+    let mode = img.wrap_mode();
+    let mut crossings = Vec::new();
+    for y in draw_span(y_min - 0.5, y_max - 0.5, img.height(), mode) {
+        let yc = y as f64 + 0.5;
+        crossings.clear();
+        for points in contours {
+            for (i, p) in points.iter().enumerate() {
+                let q = points[(i + 1) % points.len()];
+                if (p.y <= yc) != (q.y <= yc) {
+                    crossings.push(p.x + (yc - p.y)/(q.y - p.y)*(q.x - p.x));
+                }
+            }
+        }
+        crossings.sort_by(f64::total_cmp);
+
+        for span in crossings.chunks_exact(2) {
+            // Pixels whose center lies in [span[0], span[1])
+            for x in draw_span(span[0] - 0.5, (span[1] - 0.5).ceil() - 1.0, img.width(), mode) { img.put(x, y, col); }
+        }
+    }
+}
+
+/// Every pixel within `half` of the segment `ab`: a capsule, so consecutive segments get round
+/// joins for free
+fn stroke_segment<I: Draw + ?Sized>(img: &mut I, a: CoordF, b: CoordF, half: f64, col: I::Atom) {
+    let (ab, mode) = (b - a, img.wrap_mode());
+    let len2 = ab.dot(ab);
+    for y in draw_span(a.y.min(b.y) - half, a.y.max(b.y) + half, img.height(), mode) {
+    for x in draw_span(a.x.min(b.x) - half, a.x.max(b.x) + half, img.width(), mode) {
+        let p = CoordF::new(x as f64, y as f64);
+        let t = if len2 == 0.0 { 0.0 } else { ((p - a).dot(ab) / len2).clamp(0.0, 1.0) };
+        if p.distance(a.lerp(b, t)) <= half { img.put(x, y, col); }
+    }
+    }
+}
+
+/// How strokes look along their length
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StrokeStyle {
+    #[default]
+    Solid,
+    /// `dash` pixels drawn, `gap` pixels skipped, over and over
+    Dashed { dash: f64, gap: f64 },
+    /// Single dots (as wide as the stroke) every `spacing` pixels
+    Dotted { spacing: f64 },
+}
+
+impl StrokeStyle {
+    pub fn dashed(dash: f64, gap: f64) -> Self { StrokeStyle::Dashed { dash, gap } }
+    pub fn dotted(spacing: f64) -> Self { StrokeStyle::Dotted { spacing } }
+
+    /// Lengths drawn and skipped, `None` for solid (or a pattern that wouldn't go anywhere)
+    fn pattern(self) -> Option<[f64; 2]> {
+        let pattern = match self {
+            StrokeStyle::Solid => return None,
+            StrokeStyle::Dashed { dash, gap } => [dash.max(0.0), gap.max(0.0)],
+            StrokeStyle::Dotted { spacing } => [0.0, spacing.max(0.0)],
+        };
+        (pattern[0] + pattern[1] > 0.0).then_some(pattern)
+    }
+}
+
+/// Chops a polyline into the pieces that `style` draws. The pattern carries on from one segment to
+/// the next, so corners don't restart it
+fn dash(points: &[CoordF], closed: bool, style: StrokeStyle) -> Vec<Vec<CoordF>> {
+    let mut points = points.to_vec();
+    if closed && points.len() > 2 { points.push(points[0]); }
+    let Some(pattern) = style.pattern() else { return vec![points] };
+
+    let mut pieces = vec![];
+    let (mut on, mut left) = (true, pattern[0]);
+    let mut current: Vec<CoordF> = points.first().copied().into_iter().collect();
+    for w in points.windows(2) {
+        let (mut a, b) = (w[0], w[1]);
+        let mut seg = a.distance(b);
+        while seg > left {
+            let p = a.lerp(b, left / seg);
+            if on {
+                current.push(p);
+                pieces.push(std::mem::take(&mut current));
+            } else {
+                current = vec![p];
+            }
+            (a, seg, on) = (p, seg - left, !on);
+            left = pattern[if on { 0 } else { 1 }];
+        }
+        left -= seg;
+        if on { current.push(b); }
+    }
+    if on && !current.is_empty() { pieces.push(current); }
+    pieces
+}
+
+/// Strokes one open polyline. A single point becomes a dot
+fn stroke_polyline<I: Draw + ?Sized>(img: &mut I, points: &[CoordF], width: f64, col: I::Atom) {
+    if let [p] = points { return stroke_polyline(img, &[*p, *p], width, col); }
+    for w in points.windows(2) {
+        if width <= 1.0 { img.draw_line(w[0], w[1], col); } else { stroke_segment(img, w[0], w[1], width / 2.0, col); }
+    }
+}
+
+pub(crate) fn stroke<I: Draw + ?Sized>(img: &mut I, path: &Path, width: f64, style: StrokeStyle, col: I::Atom) {
+    for (points, closed) in path.contours() {
+        for piece in dash(points, closed, style) { stroke_polyline(img, &piece, width, col); }
+    }
+}
@@ -0,0 +1,35 @@
+//! Test patterns, for checking viewers and pipelines get colors and orientation right
+use crate::{Image, ImagePPM, Pixel, PixelLike};
+
+impl<P: PixelLike> Image<P> {
+    /// `cell`x`cell` squares alternating between `a` and `b`, with `a` in the bottom left corner
+    pub fn checkerboard(width: usize, height: usize, cell: usize, a: P, b: P) -> Self {
+        let cell = cell.max(1);
+        Self::from_fn(width, height, |c| if (c.x / cell + c.y / cell).is_multiple_of(2) { a } else { b })
+    }
+}
+
+impl ImagePPM {
+    /// The classic vertical bars, left to right: white, yellow, cyan, green, magenta, red and blue
+    pub fn color_bars(width: usize, height: usize) -> Self {
+        const BARS: [Pixel; 7] = [
+            Pixel::WHITE, Pixel::new(255, 255, 0), Pixel::new(0, 255, 255), Pixel::GREEN,
+            Pixel::new(255, 0, 255), Pixel::RED, Pixel::BLUE,
+        ];
+        Self::from_fn(width, height, |c| BARS[c.x * BARS.len() / width])
+    }
+
+    /// Four horizontal bands, top to bottom: gray, red, green and blue, each going from black on
+    /// the left to full intensity on the right
+    pub fn gradient_test(width: usize, height: usize) -> Self {
+        Self::from_fn(width, height, |c| {
+            let v = if width > 1 { (c.x * 255 / (width - 1)) as u8 } else { 255 };
+            match (height - 1 - c.y) * 4 / height {
+                0 => Pixel::new(v, v, v),
+                1 => Pixel::new(v, 0, 0),
+                2 => Pixel::new(0, v, 0),
+                _ => Pixel::new(0, 0, v),
+            }
+        })
+    }
+}
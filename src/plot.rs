@@ -0,0 +1,119 @@
+//! Quick function plots: a wrapper that maps data coordinates onto an image, plus axes and grids
+use std::ops::Range;
+
+use crate::{path::{Path, StrokeStyle}, CoordF, Draw, Origin};
+
+/// What marks each data point in `scatter` and `line_chart`
+pub use crate::MarkerShape;
+
+/// Borrows an image and draws on it in data coordinates: `x` spans the image's width left to
+/// right, `y` its height bottom to top (whatever the image's origin)
+pub struct Plot<'a, I: Draw> {
+    img: &'a mut I,
+    x: Range<f64>,
+    y: Range<f64>,
+}
+
+impl<'a, I: Draw> Plot<'a, I> {
+    pub fn new(img: &'a mut I, x: Range<f64>, y: Range<f64>) -> Self { Self { img, x, y } }
+
+    /// `y` range picked to fit `f` over `x` (plus a bit of room above and below)
+    pub fn for_fn(img: &'a mut I, x: Range<f64>, f: impl Fn(f64) -> f64) -> Self {
+        let n = img.width().max(2);
+        let values = (0..n).map(|i| f(x.start + (x.end - x.start) * i as f64 / (n - 1) as f64)).filter(|v| v.is_finite());
+        let (lo, hi) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+        let y = if lo > hi { -1.0..1.0 } else if lo == hi { lo - 1.0..hi + 1.0 } else {
+            let pad = (hi - lo) * 0.05;
+            lo - pad..hi + pad
+        };
+        Self { img, x, y }
+    }
+
+    pub fn x_range(&self) -> Range<f64> { self.x.clone() }
+    pub fn y_range(&self) -> Range<f64> { self.y.clone() }
+
+    /// The underlying image, to draw on it in pixels
+    pub fn image(&mut self) -> &mut I { self.img }
+
+    /// Where a point in data coordinates lands on the image
+    pub fn to_pixel(&self, p: impl Into<CoordF>) -> CoordF {
+        let p = p.into();
+        let (w, h) = ((self.img.width().max(1) - 1) as f64, (self.img.height().max(1) - 1) as f64);
+        let x = (p.x - self.x.start) / (self.x.end - self.x.start) * w;
+        let y = (p.y - self.y.start) / (self.y.end - self.y.start) * h;
+        CoordF::new(x, if self.img.origin() == Origin::TopLeft { h - y } else { y }).snapped()
+    }
+
+    /// Line between two points in data coordinates
+    pub fn line(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, style: StrokeStyle, col: I::Atom) {
+        let (a, b) = (self.to_pixel(a), self.to_pixel(b));
+        self.img.draw_line_styled(a, b, style, col);
+    }
+
+    /// The x and y axes, where they cross zero. An axis whose zero is out of view goes along the
+    /// nearest edge instead
+    pub fn draw_axes(&mut self, col: I::Atom) {
+        let x0 = 0f64.clamp(self.x.start.min(self.x.end), self.x.start.max(self.x.end));
+        let y0 = 0f64.clamp(self.y.start.min(self.y.end), self.y.start.max(self.y.end));
+        self.line((self.x.start, y0), (self.x.end, y0), StrokeStyle::Solid, col);
+        self.line((x0, self.y.start), (x0, self.y.end), StrokeStyle::Solid, col);
+    }
+
+    /// Vertical lines at every multiple of `step_x` and horizontal ones at every multiple of
+    /// `step_y` in view. Non positive steps skip that direction
+    pub fn draw_grid(&mut self, step_x: f64, step_y: f64, style: StrokeStyle, col: I::Atom) {
+        let multiples = |r: &Range<f64>, step: f64| -> Vec<f64> {
+            if step <= 0.0 || !step.is_finite() { return vec![]; }
+            let (lo, hi) = (r.start.min(r.end), r.start.max(r.end));
+            ((lo / step).ceil() as i64..=(hi / step).floor() as i64).map(|k| k as f64 * step).collect()
+        };
+        for x in multiples(&self.x, step_x) { self.line((x, self.y.start), (x, self.y.end), style, col); }
+        for y in multiples(&self.y, step_y) { self.line((self.x.start, y), (self.x.end, y), style, col); }
+    }
+
+    /// Graph of `f` over `range`, sampled once per pixel column. Where `f` isn't finite (poles,
+    /// square roots of negatives...) the line just breaks
+    pub fn plot_fn(&mut self, range: Range<f64>, f: impl Fn(f64) -> f64, style: StrokeStyle, col: I::Atom) {
+        let span = (self.to_pixel((range.end, 0.0)).x - self.to_pixel((range.start, 0.0)).x).abs();
+        let n = (span.ceil() as usize).max(1);
+        let mut path = Path::new();
+        let mut drawing = false;
+        for i in 0..=n {
+            let x = range.start + (range.end - range.start) * i as f64 / n as f64;
+            let y = f(x);
+            if !y.is_finite() {
+                drawing = false;
+                continue;
+            }
+            let p = self.to_pixel((x, y));
+            path = if drawing { path.line_to(p) } else { path.move_to(p) };
+            drawing = true;
+        }
+        self.img.stroke_path_styled(&path, 1.0, style, col);
+    }
+
+    /// A marker at every point, `size` pixels wide. Points that aren't finite are skipped
+    pub fn scatter(&mut self, points: &[(f64, f64)], shape: MarkerShape, size: f64, col: I::Atom) {
+        for &(x, y) in points {
+            if x.is_finite() && y.is_finite() { self.img.draw_marker(self.to_pixel((x, y)), shape, size, col); }
+        }
+    }
+
+    /// Points joined in order by lines, and optionally marked with `marker` (shape and size).
+    /// Points that aren't finite break the line
+    pub fn line_chart(&mut self, points: &[(f64, f64)], style: StrokeStyle, marker: Option<(MarkerShape, f64)>, col: I::Atom) {
+        let mut path = Path::new();
+        let mut drawing = false;
+        for &(x, y) in points {
+            if !(x.is_finite() && y.is_finite()) {
+                drawing = false;
+                continue;
+            }
+            let p = self.to_pixel((x, y));
+            path = if drawing { path.line_to(p) } else { path.move_to(p) };
+            drawing = true;
+        }
+        self.img.stroke_path_styled(&path, 1.0, style, col);
+        if let Some((shape, size)) = marker { self.scatter(points, shape, size, col); }
+    }
+}
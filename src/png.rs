@@ -0,0 +1,240 @@
+//! PNG export, with a small built-in encoder (fixed Huffman deflate, no dependencies). Only
+//! compiled with the `png` feature
+use std::{fs::File, io::{BufWriter, Write}, path::PathBuf};
+
+use crate::{Image, Pixel, Pixel16, PixelGrayAlpha, PixelLike, PixelRgba, PpmError};
+
+/// Pixels that have a matching PNG color type
+pub trait PngPixel: PixelLike {
+    /// PNG's color type: 0 gray, 2 RGB, 4 gray with alpha, 6 RGBA
+    const COLOR_TYPE: u8;
+    const BIT_DEPTH: u8;
+
+    /// Append the pixel's samples, big endian. `maxval` is the image's
+    fn push_samples(self, maxval: u16, out: &mut Vec<u8>);
+}
+
+impl PngPixel for Pixel {
+    const COLOR_TYPE: u8 = 2;
+    const BIT_DEPTH: u8 = 8;
+    fn push_samples(self, _: u16, out: &mut Vec<u8>) { out.extend([self.r, self.g, self.b]); }
+}
+
+/// Always 16 bits, with samples rescaled from the image's maxval to 65535
+impl PngPixel for Pixel16 {
+    const COLOR_TYPE: u8 = 2;
+    const BIT_DEPTH: u8 = 16;
+    fn push_samples(self, maxval: u16, out: &mut Vec<u8>) {
+        for s in [self.r, self.g, self.b] {
+            let s = (s.min(maxval) as u32 * 65535 + maxval as u32/2) / maxval as u32;
+            out.extend((s as u16).to_be_bytes());
+        }
+    }
+}
+
+impl PngPixel for u8 {
+    const COLOR_TYPE: u8 = 0;
+    const BIT_DEPTH: u8 = 8;
+    fn push_samples(self, _: u16, out: &mut Vec<u8>) { out.push(self); }
+}
+
+/// Written as 8 bit grayscale, black and white
+impl PngPixel for bool {
+    const COLOR_TYPE: u8 = 0;
+    const BIT_DEPTH: u8 = 8;
+    fn push_samples(self, _: u16, out: &mut Vec<u8>) { out.push(if self { 255 } else { 0 }); }
+}
+
+impl PngPixel for PixelGrayAlpha {
+    const COLOR_TYPE: u8 = 4;
+    const BIT_DEPTH: u8 = 8;
+    fn push_samples(self, _: u16, out: &mut Vec<u8>) { out.extend([self.v, self.a]); }
+}
+
+impl PngPixel for PixelRgba {
+    const COLOR_TYPE: u8 = 6;
+    const BIT_DEPTH: u8 = 8;
+    fn push_samples(self, _: u16, out: &mut Vec<u8>) { out.extend([self.r, self.g, self.b, self.a]); }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| {
+        (0..8).fold(crc ^ b as u32, |c, _| if c & 1 == 1 { (c >> 1) ^ 0xEDB88320 } else { c >> 1 })
+    })
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &x| {
+        let a = (a + x as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+/// Deflate's bit order: values go in least significant bit first
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u64,
+    nbits: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, n: u32) {
+        self.acc |= (value as u64) << self.nbits;
+        self.nbits += n;
+        while self.nbits >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.nbits -= 8;
+        }
+    }
+
+    /// Huffman codes are the exception, they go most significant bit first
+    fn code(&mut self, code: u32, n: u32) { self.bits(code.reverse_bits() >> (32 - n), n); }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 { self.out.push(self.acc as u8); }
+        self.out
+    }
+}
+
+const LEN_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LEN_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Literal/length symbol with deflate's fixed Huffman table
+fn fixed_literal(w: &mut BitWriter, sym: u16) {
+    match sym {
+        0..=143 => w.code(0x30 + sym as u32, 8),
+        144..=255 => w.code(0x190 + (sym - 144) as u32, 9),
+        256..=279 => w.code((sym - 256) as u32, 7),
+        _ => w.code(0xC0 + (sym - 280) as u32, 8),
+    }
+}
+
+/// Index of the last table entry not over `v`
+fn bucket(table: &[u16], v: u16) -> usize { table.partition_point(|&b| b <= v) - 1 }
+
+const WINDOW: usize = 32768;
+const MAX_CHAIN: usize = 64;
+
+/// Zlib stream with a single fixed Huffman block. Matches are found with hash chains over the
+/// last 32K
+fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    let mut w = BitWriter { out: vec![0x78, 0x01], acc: 0, nbits: 0 };
+    w.bits(1, 1); // last block
+    w.bits(1, 2); // fixed Huffman
+
+    let hash = |i: usize| ((data[i] as usize) << 10 ^ (data[i + 1] as usize) << 5 ^ data[i + 2] as usize) & 0x7FFF;
+    let mut head = vec![usize::MAX; 1 << 15];
+    let mut prev = vec![usize::MAX; data.len()];
+    let insert = |i: usize, head: &mut Vec<usize>, prev: &mut Vec<usize>| {
+        if i + 2 < data.len() {
+            let h = hash(i);
+            prev[i] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let (mut best_len, mut best_dist) = (0, 0);
+        if i + 2 < data.len() {
+            let mut cand = head[hash(i)];
+            let mut steps = 0;
+            while cand != usize::MAX && i - cand <= WINDOW && steps < MAX_CHAIN {
+                let len = data[cand..].iter().zip(&data[i..]).take(258).take_while(|(a, b)| a == b).count();
+                if len > best_len { (best_len, best_dist) = (len, i - cand); }
+                if len == 258 { break; }
+                cand = prev[cand];
+                steps += 1;
+            }
+        }
+
+        if best_len >= 3 {
+            let l = bucket(&LEN_BASE, best_len as u16);
+            fixed_literal(&mut w, 257 + l as u16);
+            w.bits((best_len as u16 - LEN_BASE[l]) as u32, LEN_EXTRA[l] as u32);
+            let d = bucket(&DIST_BASE, best_dist as u16);
+            w.code(d as u32, 5);
+            w.bits((best_dist as u16 - DIST_BASE[d]) as u32, DIST_EXTRA[d] as u32);
+            for j in i..i + best_len { insert(j, &mut head, &mut prev); }
+            i += best_len;
+        } else {
+            fixed_literal(&mut w, data[i] as u16);
+            insert(i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+    fixed_literal(&mut w, 256);
+
+    let mut out = w.finish();
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+}
+
+/// Filters every row with whichever of the five PNG filters leaves the smallest values (the
+/// usual heuristic), prefixing each with its filter type
+fn filter_rows(raw: &[u8], stride: usize, bpp: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len() + raw.len() / stride.max(1));
+    let zeros = vec![0; stride];
+    for (y, row) in raw.chunks(stride.max(1)).enumerate() {
+        let up = if y == 0 { &zeros[..] } else { &raw[(y - 1)*stride..y*stride] };
+        let left = |i: usize| if i >= bpp { row[i - bpp] } else { 0 };
+        let up_left = |i: usize| if i >= bpp { up[i - bpp] } else { 0 };
+        let candidates: [Vec<u8>; 5] = [
+            row.to_vec(),
+            (0..row.len()).map(|i| row[i].wrapping_sub(left(i))).collect(),
+            (0..row.len()).map(|i| row[i].wrapping_sub(up[i])).collect(),
+            (0..row.len()).map(|i| row[i].wrapping_sub(((left(i) as u16 + up[i] as u16)/2) as u8)).collect(),
+            (0..row.len()).map(|i| row[i].wrapping_sub(paeth(left(i), up[i], up_left(i)))).collect(),
+        ];
+        let cost = |f: &Vec<u8>| f.iter().map(|&b| (b as i8).unsigned_abs() as usize).sum::<usize>();
+        let (kind, best) = candidates.iter().enumerate().min_by_key(|(_, f)| cost(f)).unwrap();
+        out.push(kind as u8);
+        out.extend(best);
+    }
+    out
+}
+
+fn write_chunk(w: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> Result<(), PpmError> {
+    let len = u32::try_from(data.len()).map_err(|_| PpmError::InvalidDimensions("PNG chunk too big".into()))?;
+    w.write_all(&len.to_be_bytes())?;
+    let mut crc_data = kind.to_vec();
+    crc_data.extend(data);
+    w.write_all(&crc_data)?;
+    Ok(w.write_all(&crc32(&crc_data).to_be_bytes())?)
+}
+
+impl<P: PngPixel> Image<P> {
+    /// Write the image as a PNG (top row first, like every other format here)
+    pub fn write_png_to(&self, mut w: impl Write) -> Result<(), PpmError> {
+        let mut raw = Vec::new();
+        for &p in &self.atoms { p.push_samples(self.maxval, &mut raw); }
+        let bpp = raw.len() / self.atoms.len().max(1);
+        let too_big = || PpmError::InvalidDimensions("image too big for a PNG".into());
+        let (width, height) = (u32::try_from(self.width).map_err(|_| too_big())?, u32::try_from(self.height).map_err(|_| too_big())?);
+
+        w.write_all(b"\x89PNG\r\n\x1a\n")?;
+        let mut ihdr = vec![];
+        ihdr.extend(width.to_be_bytes());
+        ihdr.extend(height.to_be_bytes());
+        ihdr.extend([P::BIT_DEPTH, P::COLOR_TYPE, 0, 0, 0]);
+        write_chunk(&mut w, b"IHDR", &ihdr)?;
+        write_chunk(&mut w, b"IDAT", &zlib_compress(&filter_rows(&raw, self.width*bpp, bpp)))?;
+        write_chunk(&mut w, b"IEND", &[])?;
+        Ok(w.flush()?)
+    }
+
+    /// Save the image as a PNG at `filepath` (the suffix is not automatically added)
+    pub fn save_as_png(&self, filepath: impl Into<PathBuf>) -> Result<(), PpmError> {
+        self.write_png_to(BufWriter::new(File::create(filepath.into())?))
+    }
+}
@@ -0,0 +1,165 @@
+//! QOI ("Quite OK Image") encoding and decoding, following the spec at https://qoiformat.org.
+//! Only compiled with the `qoi` feature
+use std::{fs::File, io::{BufReader, BufWriter, Read, Write}, path::PathBuf};
+
+use crate::{parse::invalid, Image, ImagePPM, ImageRGBA, Pixel, PixelRgba, PpmError};
+
+const OP_INDEX: u8 = 0b0000_0000;
+const OP_DIFF: u8 = 0b0100_0000;
+const OP_LUMA: u8 = 0b1000_0000;
+const OP_RUN: u8 = 0b1100_0000;
+const OP_RGB: u8 = 0xFE;
+const OP_RGBA: u8 = 0xFF;
+const END: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+type Rgba = [u8; 4];
+
+fn hash([r, g, b, a]: Rgba) -> usize {
+    (r as usize*3 + g as usize*5 + b as usize*7 + a as usize*11) % 64
+}
+
+/// The whole encoder. `channels` only goes in the header, the pixels always carry alpha
+fn encode(w: &mut impl Write, width: usize, height: usize, channels: u8, pixels: impl Iterator<Item = Rgba>) -> Result<(), PpmError> {
+    let too_big = || PpmError::InvalidDimensions("QOI sides can't go over u32::MAX".into());
+    w.write_all(b"qoif")?;
+    w.write_all(&u32::try_from(width).map_err(|_| too_big())?.to_be_bytes())?;
+    w.write_all(&u32::try_from(height).map_err(|_| too_big())?.to_be_bytes())?;
+    w.write_all(&[channels, 0])?;
+
+    let mut index = [[0u8; 4]; 64];
+    let mut prev = [0, 0, 0, 255];
+    let mut run = 0u8;
+    let mut out = Vec::new();
+    for px in pixels {
+        if px == prev {
+            run += 1;
+            if run == 62 {
+                out.push(OP_RUN | (run - 1));
+                run = 0;
+            }
+            continue;
+        }
+        if run > 0 {
+            out.push(OP_RUN | (run - 1));
+            run = 0;
+        }
+
+        let h = hash(px);
+        if index[h] == px {
+            out.push(OP_INDEX | h as u8);
+        } else {
+            index[h] = px;
+            if px[3] != prev[3] {
+                out.extend([OP_RGBA, px[0], px[1], px[2], px[3]]);
+            } else {
+                let d = |i: usize| px[i].wrapping_sub(prev[i]) as i8;
+                let (dr, dg, db) = (d(0), d(1), d(2));
+                let (dr_dg, db_dg) = (dr.wrapping_sub(dg), db.wrapping_sub(dg));
+                if [dr, dg, db].iter().all(|v| (-2..=1).contains(v)) {
+                    out.push(OP_DIFF | ((dr + 2) as u8) << 4 | ((dg + 2) as u8) << 2 | (db + 2) as u8);
+                } else if (-32..=31).contains(&dg) && (-8..=7).contains(&dr_dg) && (-8..=7).contains(&db_dg) {
+                    out.extend([OP_LUMA | (dg + 32) as u8, ((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8]);
+                } else {
+                    out.extend([OP_RGB, px[0], px[1], px[2]]);
+                }
+            }
+        }
+        prev = px;
+    }
+    if run > 0 { out.push(OP_RUN | (run - 1)); }
+    w.write_all(&out)?;
+    w.write_all(&END)?;
+    Ok(w.flush()?)
+}
+
+/// The whole decoder, giving back width, height, the header's channels and the pixels in storage
+/// order
+fn decode(mut r: impl Read) -> Result<(usize, usize, u8, Vec<Rgba>), PpmError> {
+    let mut data = Vec::new();
+    r.read_to_end(&mut data)?;
+    if data.len() < 14 || &data[..4] != b"qoif" { return Err(invalid("not a QOI file")); }
+    let width = u32::from_be_bytes(data[4..8].try_into().unwrap()) as usize;
+    let height = u32::from_be_bytes(data[8..12].try_into().unwrap()) as usize;
+    let channels = data[12];
+    if !(3..=4).contains(&channels) { return Err(invalid(format!("QOI can't have {channels} channels"))); }
+
+    let total = width.checked_mul(height).ok_or_else(|| invalid("QOI image is too big"))?;
+    let mut pixels = Vec::with_capacity(total.min(data.len()*62));
+    let mut index = [[0u8; 4]; 64];
+    let mut px = [0, 0, 0, 255];
+    let mut pos = 14;
+    let mut byte = || -> Result<u8, PpmError> {
+        let b = *data.get(pos).ok_or_else(|| invalid("QOI data ends too early"))?;
+        pos += 1;
+        Ok(b)
+    };
+    while pixels.len() < total {
+        let op = byte()?;
+        let mut run = 1;
+        match op {
+            OP_RGB => px = [byte()?, byte()?, byte()?, px[3]],
+            OP_RGBA => px = [byte()?, byte()?, byte()?, byte()?],
+            _ => match op & 0b1100_0000 {
+                OP_INDEX => px = index[op as usize],
+                OP_DIFF => for (i, shift) in [4, 2, 0].into_iter().enumerate() {
+                    px[i] = px[i].wrapping_add((op >> shift) & 3).wrapping_sub(2);
+                },
+                OP_LUMA => {
+                    let dg = (op & 0x3F).wrapping_sub(32);
+                    let next = byte()?;
+                    px[0] = px[0].wrapping_add(dg).wrapping_add(next >> 4).wrapping_sub(8);
+                    px[1] = px[1].wrapping_add(dg);
+                    px[2] = px[2].wrapping_add(dg).wrapping_add(next & 0xF).wrapping_sub(8);
+                },
+                _ => run = (op & 0x3F) as usize + 1,
+            },
+        }
+        index[hash(px)] = px;
+        for _ in 0..run.min(total - pixels.len()) { pixels.push(px); }
+    }
+    Ok((width, height, channels, pixels))
+}
+
+impl ImagePPM {
+    /// Write the image as a 3 channel QOI
+    pub fn write_qoi_to(&self, mut w: impl Write) -> Result<(), PpmError> {
+        encode(&mut w, self.width, self.height, 3, self.atoms.iter().map(|p| [p.r, p.g, p.b, 255]))
+    }
+
+    /// Save the image as a QOI at `filepath` (the suffix is not automatically added)
+    pub fn save_as_qoi(&self, filepath: impl Into<PathBuf>) -> Result<(), PpmError> {
+        self.write_qoi_to(BufWriter::new(File::create(filepath.into())?))
+    }
+
+    /// Read a QOI image. Any alpha is dropped
+    pub fn from_qoi_reader(reader: impl Read) -> Result<Self, PpmError> {
+        let (width, height, _, pixels) = decode(reader)?;
+        Ok(Image::from_atoms(width, height, pixels.into_iter().map(|[r, g, b, _]| Pixel::new(r, g, b)).collect()))
+    }
+
+    pub fn load_qoi(filepath: impl Into<PathBuf>) -> Result<Self, PpmError> {
+        Self::from_qoi_reader(BufReader::new(File::open(filepath.into())?))
+    }
+}
+
+impl ImageRGBA {
+    /// Write the image as a 4 channel QOI
+    pub fn write_qoi_to(&self, mut w: impl Write) -> Result<(), PpmError> {
+        encode(&mut w, self.width, self.height, 4, self.atoms.iter().map(|p| [p.r, p.g, p.b, p.a]))
+    }
+
+    /// Save the image as a QOI at `filepath` (the suffix is not automatically added)
+    pub fn save_as_qoi(&self, filepath: impl Into<PathBuf>) -> Result<(), PpmError> {
+        self.write_qoi_to(BufWriter::new(File::create(filepath.into())?))
+    }
+
+    /// Read a QOI image. 3 channel ones come out fully opaque
+    pub fn from_qoi_reader(reader: impl Read) -> Result<Self, PpmError> {
+        let (width, height, _, pixels) = decode(reader)?;
+        Ok(Image::from_atoms(width, height, pixels.into_iter().map(|[r, g, b, a]| PixelRgba::new(r, g, b, a)).collect()))
+    }
+
+    pub fn load_qoi(filepath: impl Into<PathBuf>) -> Result<Self, PpmError> {
+        Self::from_qoi_reader(BufReader::new(File::open(filepath.into())?))
+    }
+}
@@ -0,0 +1,58 @@
+//! Small seeded random numbers for generative sketches: same seed, same picture, on every platform
+use std::ops::Range;
+
+use crate::{Coord, Pixel};
+
+/// splitmix64 pseudo random generator. Fast and good enough for pictures, but not for anything
+/// that needs to be unpredictable
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self { Self { state: seed } }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in 0..1
+    pub fn next_f64(&mut self) -> f64 { (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 }
+
+    /// Uniform in 0..n. Panics if `n` is 0
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "can't pick a number below 0");
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+
+    /// Uniform in `range`
+    pub fn range(&mut self, range: Range<f64>) -> f64 { range.start + self.next_f64()*(range.end - range.start) }
+
+    /// True with probability `p`
+    pub fn chance(&mut self, p: f64) -> bool { self.next_f64() < p }
+
+    /// Any coordinate inside a `width`x`height` image. Panics if it has no pixels
+    pub fn random_coord(&mut self, width: usize, height: usize) -> Coord { Coord::new(self.below(width), self.below(height)) }
+
+    /// Any color at all
+    pub fn random_color(&mut self) -> Pixel {
+        let [r, g, b, ..] = self.next_u64().to_le_bytes();
+        Pixel::new(r, g, b)
+    }
+
+    /// Picks one of `items`, `None` if there are none
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        (!items.is_empty()).then(|| &items[self.below(items.len())])
+    }
+}
+
+/// `n` coordinates spread uniformly over a `width`x`height` image, the same ones for the same seed
+pub fn scatter_points(n: usize, width: usize, height: usize, seed: u64) -> Vec<Coord> {
+    let mut rng = Rng::new(seed);
+    (0..n).map(|_| rng.random_coord(width, height)).collect()
+}
@@ -0,0 +1,342 @@
+//! Toy 3D: meshes seen through a perspective camera, projected onto images
+use std::ops::{self, Add, Sub};
+
+use crate::{span, CoordF, Draw, ImagePPM, Pixel, ResizeFilter};
+
+/// Point or direction in 3D space. Right handed, with y up
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vec3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Vec3 {
+    pub const fn new(x: f64, y: f64, z: f64) -> Self { Self { x, y, z } }
+    pub fn dot(&self, rhs: Self) -> f64 { self.x*rhs.x + self.y*rhs.y + self.z*rhs.z }
+    pub fn cross(&self, rhs: Self) -> Self {
+        Self::new(self.y*rhs.z - self.z*rhs.y, self.z*rhs.x - self.x*rhs.z, self.x*rhs.y - self.y*rhs.x)
+    }
+    pub fn length(&self) -> f64 { self.dot(*self).sqrt() }
+
+    /// Same direction, length 1. Zero stays zero
+    pub fn normalized(&self) -> Self {
+        let len = self.length();
+        if len == 0.0 { *self } else { *self * (1.0 / len) }
+    }
+
+    /// Linear interpolation: `self` at t = 0, `rhs` at t = 1
+    pub fn lerp(&self, rhs: Self, t: f64) -> Self { *self + (rhs - *self)*t }
+}
+
+impl From<(f64, f64, f64)> for Vec3 {
+    fn from((x, y, z): (f64, f64, f64)) -> Self { Self::new(x, y, z) }
+}
+
+impl Add for Vec3 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output { Self { x: self.x + rhs.x, y: self.y + rhs.y, z: self.z + rhs.z } }
+}
+impl Sub for Vec3 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output { Self { x: self.x - rhs.x, y: self.y - rhs.y, z: self.z - rhs.z } }
+}
+impl ops::Mul<f64> for Vec3 {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self::Output { Self { x: self.x * rhs, y: self.y * rhs, z: self.z * rhs } }
+}
+impl ops::Neg for Vec3 {
+    type Output = Self;
+    fn neg(self) -> Self::Output { Self { x: -self.x, y: -self.y, z: -self.z } }
+}
+
+/// Triangle mesh: faces are indices into `vertices`, counterclockwise when seen from outside
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<Vec3>,
+    pub faces: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Vec3>, faces: Vec<[usize; 3]>) -> Self { Self { vertices, faces } }
+
+    /// Cube with sides of length 2 centered on the origin, two triangles per side
+    pub fn cube() -> Self {
+        let vertices = (0..8).map(|i| Vec3::new(
+            if i & 1 == 0 { -1.0 } else { 1.0 },
+            if i & 2 == 0 { -1.0 } else { 1.0 },
+            if i & 4 == 0 { -1.0 } else { 1.0 },
+        )).collect();
+        let quads = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+        let faces = quads.iter().flat_map(|&[a, b, c, d]| [[a, b, c], [a, c, d]]).collect();
+        Self { vertices, faces }
+    }
+
+    /// Every edge once, as pairs of indices into `vertices` (smaller first)
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        let mut edges: Vec<(usize, usize)> = self.faces.iter()
+            .flat_map(|f| [(f[0], f[1]), (f[1], f[2]), (f[2], f[0])])
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+        edges
+    }
+
+    /// Copy with every vertex moved by `f`, for scaling, rotating, placing...
+    pub fn transformed(&self, f: impl Fn(Vec3) -> Vec3) -> Self {
+        Self { vertices: self.vertices.iter().map(|&v| f(v)).collect(), faces: self.faces.clone() }
+    }
+}
+
+/// Pinhole camera at `position` looking at `target`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub position: Vec3,
+    pub target: Vec3,
+    /// Roughly which way is up, it doesn't need to be perpendicular to the view
+    pub up: Vec3,
+    /// Vertical field of view, in radians
+    pub fov: f64,
+    /// Anything closer than this is cut off
+    pub near: f64,
+}
+
+impl Camera {
+    /// Camera with y up, a 60 degree field of view and the near plane at 0.1
+    pub fn new(position: impl Into<Vec3>, target: impl Into<Vec3>) -> Self {
+        Self { position: position.into(), target: target.into(), up: Vec3::new(0.0, 1.0, 0.0), fov: 60f64.to_radians(), near: 0.1 }
+    }
+
+    pub fn with_fov(self, fov: f64) -> Self { Self { fov, ..self } }
+
+    /// `p` relative to the camera: x right, y up and z how far in front it is
+    pub fn to_view(&self, p: Vec3) -> Vec3 {
+        let forward = (self.target - self.position).normalized();
+        let right = forward.cross(self.up).normalized();
+        let up = right.cross(forward);
+        let d = p - self.position;
+        Vec3::new(d.dot(right), d.dot(up), d.dot(forward))
+    }
+
+    /// Where a point in view space (see `to_view`) lands on a `width`x`height` image. Only
+    /// meaningful for points past the near plane
+    pub fn view_to_image(&self, v: Vec3, width: usize, height: usize) -> CoordF {
+        let f = height as f64 / 2.0 / (self.fov / 2.0).tan();
+        CoordF::new(width as f64 / 2.0 + f * v.x / v.z, height as f64 / 2.0 + f * v.y / v.z)
+    }
+
+    /// Where `p` lands on a `width`x`height` image, along with its depth. `None` if it's behind
+    /// the near plane
+    pub fn project(&self, p: Vec3, width: usize, height: usize) -> Option<(CoordF, f64)> {
+        let v = self.to_view(p);
+        (v.z >= self.near).then(|| (self.view_to_image(v, width, height), v.z))
+    }
+}
+
+/// How far away the closest thing drawn so far is, for every pixel of an image. Lets triangles be
+/// drawn in any order and still hide each other properly
+#[derive(Clone, Debug, PartialEq)]
+pub struct DepthBuffer {
+    width: usize,
+    height: usize,
+    /// Same layout as `get`: x + y*width
+    depths: Vec<f64>,
+}
+
+impl DepthBuffer {
+    /// Buffer for a `width`x`height` image, with nothing drawn yet (everything infinitely far)
+    pub fn new(width: usize, height: usize) -> Self { Self { width, height, depths: vec![f64::INFINITY; width*height] } }
+
+    pub fn width(&self) -> usize { self.width }
+    pub fn height(&self) -> usize { self.height }
+
+    /// Forget everything drawn, to reuse the buffer for another frame
+    pub fn clear(&mut self) { self.depths.fill(f64::INFINITY); }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<f64> {
+        (x < self.width && y < self.height).then(|| self.depths[x + y*self.width])
+    }
+
+    /// If `depth` is closer than what's at `(x, y)`, records it and returns true
+    pub fn test_and_set(&mut self, x: usize, y: usize, depth: f64) -> bool {
+        if x >= self.width || y >= self.height { return false; }
+        let d = &mut self.depths[x + y*self.width];
+        if depth < *d { *d = depth; true } else { false }
+    }
+}
+
+/// Calls `f` for every pixel of a `width`x`height` image whose center is inside the projected
+/// triangle `pts` (either winding), with the depth there and the perspective-correct barycentric
+/// weights of the three corners. `zs` are the corners' depths
+fn rasterize(width: usize, height: usize, pts: [CoordF; 3], zs: [f64; 3], mut f: impl FnMut(usize, usize, f64, [f64; 3])) {
+    let edge = |p: CoordF, q: CoordF, r: CoordF| (q - p).cross(r - p);
+    let area = edge(pts[0], pts[1], pts[2]);
+    if area == 0.0 { return; }
+    let [a, b, c] = pts;
+    let xs = span(a.x.min(b.x).min(c.x) - 0.5, a.x.max(b.x).max(c.x) - 0.5, width);
+    let ys = span(a.y.min(b.y).min(c.y) - 0.5, a.y.max(b.y).max(c.y) - 0.5, height);
+    for y in ys {
+    for x in xs.clone() {
+        let p = CoordF::new(x as f64 + 0.5, y as f64 + 0.5);
+        let ws = [edge(b, c, p) / area, edge(c, a, p) / area, edge(a, b, p) / area];
+        if ws.iter().any(|&w| w < 0.0) { continue; }
+        // 1/z is what's linear on screen, so weights go through it to stay right in 3D
+        let inv = [ws[0] / zs[0], ws[1] / zs[1], ws[2] / zs[2]];
+        let depth = 1.0 / (inv[0] + inv[1] + inv[2]);
+        f(x, y, depth, inv.map(|w| w * depth));
+    }
+    }
+}
+
+/// Weighted mix of three colors, weights adding up to 1
+fn mix(colors: [Pixel; 3], w: [f64; 3]) -> Pixel {
+    let channel = |f: fn(&Pixel) -> u8| (f(&colors[0]) as f64*w[0] + f(&colors[1]) as f64*w[1] + f(&colors[2]) as f64*w[2]).round().clamp(0.0, 255.0) as u8;
+    Pixel::new(channel(|p| p.r), channel(|p| p.g), channel(|p| p.b))
+}
+
+/// Light coming from far away (like the sun) from `direction`, plus some `ambient` light that
+/// reaches everywhere so faces turned away aren't pitch black
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    /// Towards the light, from whatever it's lighting
+    pub direction: Vec3,
+    /// How lit surfaces facing away from the light are, from 0 to 1
+    pub ambient: f64,
+}
+
+impl Light {
+    /// Light from `direction`, with an ambient level of 0.2
+    pub fn new(direction: impl Into<Vec3>) -> Self { Self { direction: direction.into(), ambient: 0.2 } }
+
+    pub fn with_ambient(self, ambient: f64) -> Self { Self { ambient, ..self } }
+
+    /// Lambertian brightness of a surface facing `normal`, from `ambient` to 1
+    pub fn intensity(&self, normal: Vec3) -> f64 {
+        let diffuse = normal.normalized().dot(self.direction.normalized()).max(0.0);
+        self.ambient + (1.0 - self.ambient)*diffuse
+    }
+
+    /// `col` as lit on a surface facing `normal`. Flat shading is this once per face
+    pub fn shade(&self, normal: Vec3, col: Pixel) -> Pixel { col * self.intensity(normal) as f32 }
+}
+
+/// How `render_mesh` lights faces
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shading {
+    /// One color per face, from its normal: faceted look
+    Flat,
+    /// Colors computed at the corners (from normals averaged over the faces around each vertex)
+    /// and blended across: smooth look
+    Gouraud,
+}
+
+impl Mesh {
+    /// Normal of face `i`, length 1, pointing out (for counterclockwise faces)
+    pub fn face_normal(&self, i: usize) -> Vec3 {
+        let [a, b, c] = self.faces[i].map(|v| self.vertices[v]);
+        (b - a).cross(c - a).normalized()
+    }
+
+    /// One normal per vertex, averaging the faces around it (bigger faces count for more)
+    pub fn vertex_normals(&self) -> Vec<Vec3> {
+        let mut normals = vec![Vec3::new(0.0, 0.0, 0.0); self.vertices.len()];
+        for f in &self.faces {
+            let [a, b, c] = f.map(|v| self.vertices[v]);
+            let n = (b - a).cross(c - a);
+            for &v in f { normals[v] = normals[v] + n; }
+        }
+        normals.iter().map(Vec3::normalized).collect()
+    }
+}
+
+impl ImagePPM {
+    /// Fills the projected triangle whose corners are at `pts` on the image and `zs` away from the
+    /// camera, only where it's closer than what `depth` says is already there
+    pub fn fill_triangle_depth(&mut self, depth: &mut DepthBuffer, pts: [CoordF; 3], zs: [f64; 3], col: Pixel) {
+        self.shade_triangle(Some(depth), pts, zs, |_| col);
+    }
+
+    /// Fills the triangle `pts`, blending the corners' `colors` smoothly across it (Gouraud
+    /// shading)
+    pub fn fill_triangle_gouraud(&mut self, pts: [CoordF; 3], colors: [Pixel; 3]) {
+        self.shade_triangle(None, pts, [1.0; 3], |w| mix(colors, w));
+    }
+
+    /// Same as `fill_triangle_gouraud`, but z-tested like `fill_triangle_depth`. The blend is
+    /// perspective-correct
+    pub fn fill_triangle_gouraud_depth(&mut self, depth: &mut DepthBuffer, pts: [CoordF; 3], zs: [f64; 3], colors: [Pixel; 3]) {
+        self.shade_triangle(Some(depth), pts, zs, |w| mix(colors, w));
+    }
+
+    /// Fills the triangle `pts` with `texture`, stretched so that corner `i` shows the texture at
+    /// `uvs[i]` (see `sample_uv`)
+    pub fn fill_triangle_textured(&mut self, pts: [CoordF; 3], uvs: [CoordF; 3], texture: &ImagePPM, filter: ResizeFilter) {
+        self.shade_triangle(None, pts, [1.0; 3], |w| texture.sample_uv(uvs[0]*w[0] + uvs[1]*w[1] + uvs[2]*w[2], filter));
+    }
+
+    /// Same as `fill_triangle_textured`, but z-tested like `fill_triangle_depth`. The texture is
+    /// perspective-correct, so it doesn't swim on triangles seen at an angle
+    pub fn fill_triangle_textured_depth(&mut self, depth: &mut DepthBuffer, pts: [CoordF; 3], zs: [f64; 3], uvs: [CoordF; 3], texture: &ImagePPM, filter: ResizeFilter) {
+        self.shade_triangle(Some(depth), pts, zs, |w| texture.sample_uv(uvs[0]*w[0] + uvs[1]*w[1] + uvs[2]*w[2], filter));
+    }
+
+    /// Fills a projected triangle with whatever `shade` gives for each pixel's barycentric
+    /// weights, z-tested if there's a depth buffer
+    fn shade_triangle(&mut self, mut depth: Option<&mut DepthBuffer>, pts: [CoordF; 3], zs: [f64; 3], mut shade: impl FnMut([f64; 3]) -> Pixel) {
+        if let Some(depth) = &depth {
+            assert!(depth.width == self.width() && depth.height == self.height(), "depth buffer must be the same size as the image");
+        }
+        rasterize(self.width(), self.height(), pts, zs, |x, y, z, w| {
+            if depth.as_mut().is_none_or(|d| d.test_and_set(x, y, z)) { *self.get_mut(x, y).unwrap() = shade(w); }
+        });
+    }
+
+    /// What `fill_mesh` does, but `shade` gives each pixel's color from its face's index and its
+    /// barycentric weights
+    fn draw_faces(&mut self, mesh: &Mesh, camera: &Camera, mut depth: Option<&mut DepthBuffer>, mut shade: impl FnMut(usize, [f64; 3]) -> Pixel) {
+        let (w, h) = (self.width(), self.height());
+        let mut visible: Vec<(usize, [CoordF; 3], [f64; 3])> = mesh.faces.iter().enumerate().filter_map(|(i, f)| {
+            let view = f.map(|v| camera.to_view(mesh.vertices[v]));
+            if view.iter().any(|v| v.z < camera.near) { return None; }
+            let pts = view.map(|v| camera.view_to_image(v, w, h));
+            // Counterclockwise on screen means it faces the camera
+            ((pts[1] - pts[0]).cross(pts[2] - pts[0]) > 0.0).then(|| (i, pts, view.map(|v| v.z)))
+        }).collect();
+        if depth.is_none() { visible.sort_by(|a, b| b.2.iter().sum::<f64>().total_cmp(&a.2.iter().sum::<f64>())); }
+        for (i, pts, zs) in visible { self.shade_triangle(depth.as_deref_mut(), pts, zs, |w| shade(i, w)); }
+    }
+
+    /// Fills the faces of `mesh` that face `camera`, each with the color `color` gives for its
+    /// index. With a depth buffer hidden parts are z-tested away; without one, faces are sorted
+    /// back to front (the painter's algorithm), which goes wrong where faces cross. Faces reaching
+    /// behind the near plane are skipped
+    pub fn fill_mesh(&mut self, mesh: &Mesh, camera: &Camera, depth: Option<&mut DepthBuffer>, color: impl FnMut(usize) -> Pixel) {
+        let colors: Vec<Pixel> = (0..mesh.faces.len()).map(color).collect();
+        self.draw_faces(mesh, camera, depth, |i, _| colors[i]);
+    }
+
+    /// Same as `fill_mesh`, but every face is `col` lit by `light`
+    pub fn render_mesh(&mut self, mesh: &Mesh, camera: &Camera, depth: Option<&mut DepthBuffer>, light: &Light, col: Pixel, shading: Shading) {
+        match shading {
+            Shading::Flat => self.fill_mesh(mesh, camera, depth, |i| light.shade(mesh.face_normal(i), col)),
+            Shading::Gouraud => {
+                let lit: Vec<Pixel> = mesh.vertex_normals().iter().map(|&n| light.shade(n, col)).collect();
+                self.draw_faces(mesh, camera, depth, |i, w| mix(mesh.faces[i].map(|v| lit[v]), w));
+            },
+        }
+    }
+
+    /// Draws every edge of `mesh` as seen by `camera`, hidden ones too. Edges crossing the near
+    /// plane are cut where they cross it
+    pub fn draw_wireframe(&mut self, mesh: &Mesh, camera: &Camera, col: Pixel) {
+        let (w, h) = (self.width(), self.height());
+        for (a, b) in mesh.edges() {
+            let (mut a, mut b) = (camera.to_view(mesh.vertices[a]), camera.to_view(mesh.vertices[b]));
+            if a.z < camera.near && b.z < camera.near { continue; }
+            if a.z < camera.near { a = b.lerp(a, (b.z - camera.near) / (b.z - a.z)); }
+            if b.z < camera.near { b = a.lerp(b, (a.z - camera.near) / (a.z - b.z)); }
+            self.draw_line(camera.view_to_image(a, w, h), camera.view_to_image(b, w, h), col);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Retained mode drawing: a scene remembers its shapes and can be rasterized again whenever,
+//! at any resolution, or exported as SVG
+use std::{fs::File, io::{BufWriter, Write}, path::PathBuf};
+
+use crate::{path::{svg_number as num, Path}, transform::{Canvas, Transform}, CoordF, ImagePPM, Pixel, PpmError, PpmFormat};
+
+/// Something a `Scene` can hold, in scene units
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    /// Filled axis-aligned rectangle with corners `a` and `b`
+    Rect { a: CoordF, b: CoordF },
+    /// Filled circle
+    Circle { center: CoordF, radius: f64 },
+    /// Filled polygon, even-odd like `Draw::fill_polygon`
+    Polygon(Vec<CoordF>),
+    /// Filled path, even-odd like `Draw::fill_path`
+    Path(Path),
+    /// Text in the built-in font, each font pixel `scale` units wide. `origin` is its bottom left
+    /// corner, like `Draw::draw_text`
+    Text { origin: CoordF, text: String, scale: f64 },
+}
+
+/// One shape in a scene, along with how and where it's drawn
+#[derive(Clone, Debug)]
+pub struct SceneItem {
+    pub shape: Shape,
+    pub color: Pixel,
+    /// Higher layers are drawn over lower ones. Within a layer, later items go on top
+    pub layer: i32,
+}
+
+/// An ordered list of shapes on a `width`x`height` unit area. Rendering at that size gives one
+/// unit per pixel, other sizes stretch everything to fit
+#[derive(Clone, Debug)]
+pub struct Scene {
+    width: f64,
+    height: f64,
+    background: Pixel,
+    items: Vec<SceneItem>,
+}
+
+impl Scene {
+    pub fn new(width: f64, height: f64, background: Pixel) -> Self { Self { width, height, background, items: Vec::new() } }
+
+    /// Adds `shape` on top of everything else already in `layer`
+    pub fn add(&mut self, layer: i32, shape: Shape, color: Pixel) -> &mut Self {
+        self.items.push(SceneItem { shape, color, layer });
+        self
+    }
+
+    pub fn items(&self) -> &[SceneItem] { &self.items }
+
+    /// For moving things between layers, recoloring them, etc.
+    pub fn items_mut(&mut self) -> &mut Vec<SceneItem> { &mut self.items }
+
+    /// Rasterizes the scene onto a new `width`x`height` image, lowest layer first
+    pub fn render(&self, width: usize, height: usize) -> ImagePPM {
+        let mut img = ImagePPM::new(width, height, self.background);
+        let mut canvas = Canvas::new(&mut img);
+        canvas.push_transform(Transform::scale(width as f64 / self.width, height as f64 / self.height));
+        for item in self.ordered() {
+            match &item.shape {
+                Shape::Rect { a, b } => canvas.fill_rect(*a, *b, item.color),
+                Shape::Circle { center, radius } => canvas.fill_circle(*center, *radius, item.color),
+                Shape::Polygon(points) => canvas.fill_polygon(points, item.color),
+                Shape::Path(path) => canvas.fill_path(path, item.color),
+                Shape::Text { origin, text, scale } => canvas.draw_text(*origin, text, *scale, item.color),
+            }
+        }
+        img
+    }
+
+    /// Items in drawing order, lowest layer first
+    fn ordered(&self) -> Vec<&SceneItem> {
+        let mut order: Vec<&SceneItem> = self.items.iter().collect();
+        order.sort_by_key(|item| item.layer);
+        order
+    }
+
+    /// The scene as a standalone SVG document, one element per item. The y axis gets flipped, so
+    /// it looks the same as `render`. Text uses a generic monospace font, so it won't match the
+    /// built-in one exactly
+    pub fn to_svg(&self) -> String {
+        let (w, h) = (num(self.width), num(self.height));
+        let flip = |p: CoordF| (num(p.x), num(self.height - p.y));
+        let hex = |p: Pixel| format!("#{:02x}{:02x}{:02x}", p.r, p.g, p.b);
+        let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n");
+        svg += &format!("<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n", hex(self.background));
+        for item in self.ordered() {
+            let fill = hex(item.color);
+            svg += &match &item.shape {
+                Shape::Rect { a, b } => {
+                    let (x, y) = flip(CoordF::new(a.x.min(b.x), a.y.max(b.y)));
+                    format!("<rect x=\"{x}\" y=\"{y}\" width=\"{}\" height=\"{}\" fill=\"{fill}\"/>", num((a.x - b.x).abs()), num((a.y - b.y).abs()))
+                },
+                Shape::Circle { center, radius } => {
+                    let (cx, cy) = flip(*center);
+                    format!("<circle cx=\"{cx}\" cy=\"{cy}\" r=\"{}\" fill=\"{fill}\"/>", num(*radius))
+                },
+                Shape::Polygon(points) => {
+                    let points: Vec<String> = points.iter().map(|&p| { let (x, y) = flip(p); format!("{x},{y}") }).collect();
+                    format!("<polygon points=\"{}\" fill=\"{fill}\" fill-rule=\"evenodd\"/>", points.join(" "))
+                },
+                Shape::Path(path) => {
+                    let d = path.transformed(Transform::scale(1.0, -1.0).then(Transform::translate(0.0, self.height))).to_svg_data();
+                    format!("<path d=\"{d}\" fill=\"{fill}\" fill-rule=\"evenodd\"/>")
+                },
+                // SVG text doesn't wrap, so every line is its own element
+                Shape::Text { origin, text, scale } => text.lines().enumerate().map(|(i, line)| {
+                    let (x, y) = flip(*origin - CoordF::new(0.0, i as f64 * 8.0 * scale));
+                    let line = line.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+                    format!("<text x=\"{x}\" y=\"{y}\" font-family=\"monospace\" font-size=\"{}\" fill=\"{fill}\">{line}</text>", num(8.0 * scale))
+                }).collect::<Vec<_>>().join("\n"),
+            };
+            svg.push('\n');
+        }
+        svg + "</svg>\n"
+    }
+
+    /// Saves `to_svg` to a file
+    pub fn save_svg(&self, filepath: impl Into<PathBuf>) -> Result<(), PpmError> {
+        let mut w = BufWriter::new(File::create(filepath.into())?);
+        w.write_all(self.to_svg().as_bytes())?;
+        Ok(w.flush()?)
+    }
+}
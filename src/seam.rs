@@ -0,0 +1,59 @@
+//! Content-aware resizing, removing the seams that cross the least detail
+use crate::{ImagePPM, Pixel};
+
+impl ImagePPM {
+    /// Narrows the image to `new_width` by repeatedly removing the lowest-energy vertical seam: a
+    /// path of one pixel per row, each touching the one above, crossing as few edges as possible.
+    /// Flat areas shrink while detailed ones are kept intact. Panics if `new_width` is 0 or wider
+    /// than the image
+    pub fn seam_carve(&self, new_width: usize) -> ImagePPM {
+        assert!(new_width > 0 && new_width <= self.width, "can't carve a {}-wide image down to {new_width}", self.width);
+        let (mut w, h) = (self.width, self.height);
+        let mut atoms = self.atoms.clone();
+        while w > new_width {
+            let seam = lowest_seam(&energy(&atoms, w, h), w, h);
+            atoms = atoms.chunks(w).zip(&seam)
+                .flat_map(|(row, &cut)| row.iter().enumerate().filter(move |&(x, _)| x != cut).map(|(_, p)| *p))
+                .collect();
+            w -= 1;
+        }
+        ImagePPM::from_atoms(new_width, h, atoms)
+    }
+}
+
+/// Sum of the horizontal and vertical color differences around each pixel (edges are clamped),
+/// in storage order
+fn energy(atoms: &[Pixel], w: usize, h: usize) -> Vec<u32> {
+    let diff = |a: Pixel, b: Pixel| a.r.abs_diff(b.r) as u32 + a.g.abs_diff(b.g) as u32 + a.b.abs_diff(b.b) as u32;
+    let at = |x: usize, y: usize| atoms[x + y*w];
+    let mut energy = Vec::with_capacity(w*h);
+    for y in 0..h { for x in 0..w {
+        let horizontal = diff(at(x.saturating_sub(1), y), at((x + 1).min(w - 1), y));
+        let vertical = diff(at(x, y.saturating_sub(1)), at(x, (y + 1).min(h - 1)));
+        energy.push(horizontal + vertical);
+    }}
+    energy
+}
+
+/// Column to remove on each row (in storage order) so that the total energy removed is minimal
+fn lowest_seam(energy: &[u32], w: usize, h: usize) -> Vec<usize> {
+    if h == 0 { return Vec::new(); }
+    // cost[x + y*w]: cheapest seam from the first row down to (x, y)
+    let mut cost = energy[..w].to_vec();
+    for y in 1..h {
+        for x in 0..w {
+            let above = &cost[(y - 1)*w..y*w];
+            let best = above[x.saturating_sub(1)..=(x + 1).min(w - 1)].iter().min().unwrap();
+            cost.push(energy[x + y*w] + best);
+        }
+    }
+    // Walk back up from the cheapest end, always stepping to the cheapest neighbour above
+    let cheapest = |y: usize, lo: usize, hi: usize| (lo..=hi).min_by_key(|&x| cost[x + y*w]).unwrap();
+    let mut seam = vec![cheapest(h - 1, 0, w - 1)];
+    for y in (0..h - 1).rev() {
+        let x = *seam.last().unwrap();
+        seam.push(cheapest(y, x.saturating_sub(1), (x + 1).min(w - 1)));
+    }
+    seam.reverse();
+    seam
+}
@@ -0,0 +1,45 @@
+//! Small images stamped onto bigger ones, with one color standing in for transparency. The usual
+//! way to do tiles and sprites without going all the way to alpha
+use crate::{Coord, Draw, ICoord, ImagePPM, Pixel, PpmFormat};
+
+/// An image plus, optionally, a key color whose pixels are skipped when stamping
+#[derive(Clone, Debug)]
+pub struct Sprite {
+    image: ImagePPM,
+    key: Option<Pixel>,
+}
+
+impl Sprite {
+    /// Fully opaque sprite
+    pub fn new(image: ImagePPM) -> Self { Self { image, key: None } }
+
+    /// Pixels of exactly this color won't be stamped
+    pub fn with_key(self, key: Pixel) -> Self { Self { key: Some(key), ..self } }
+
+    pub fn image(&self) -> &ImagePPM { &self.image }
+    pub fn key(&self) -> Option<Pixel> { self.key }
+    pub fn width(&self) -> usize { self.image.width() }
+    pub fn height(&self) -> usize { self.image.height() }
+
+    /// Cuts a sprite sheet into `tile_w`x`tile_h` sprites, all with the same key. They come in
+    /// reading order: left to right, top row first. Leftovers at the right and bottom edges that
+    /// don't make a whole tile are ignored
+    pub fn sheet(sheet: &ImagePPM, tile_w: usize, tile_h: usize, key: Option<Pixel>) -> Vec<Sprite> {
+        if tile_w == 0 || tile_h == 0 { return vec![]; }
+        let (cols, rows) = (sheet.width() / tile_w, sheet.height() / tile_h);
+        (0..rows).flat_map(|row| (0..cols).map(move |col| (row, col))).map(|(row, col)| {
+            let corner = Coord::new(col*tile_w, sheet.height() - (row + 1)*tile_h);
+            Sprite { image: sheet.crop(corner, tile_w, tile_h), key }
+        }).collect()
+    }
+
+    /// Draw the sprite onto `target` with its bottom left corner at `position`, which can be
+    /// partly (or entirely) off the target
+    pub fn stamp<I: Draw<Atom = Pixel> + ?Sized>(&self, target: &mut I, position: impl Into<ICoord>) {
+        let position = position.into();
+        let is_key = |p: &Pixel| self.key.is_some_and(|k| (k.r, k.g, k.b) == (p.r, p.g, p.b));
+        for (c, p) in self.image.enumerate_pixels() {
+            if !is_key(p) { target.put(position.x + c.x as isize, position.y + c.y as isize, *p); }
+        }
+    }
+}
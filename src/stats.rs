@@ -0,0 +1,72 @@
+//! Histograms and other numbers that summarize an image
+use crate::{ImagePPM, Pixel};
+
+/// How many pixels have each value, per channel
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Histogram {
+    pub r: [usize; 256],
+    pub g: [usize; 256],
+    pub b: [usize; 256],
+}
+
+impl Histogram {
+    /// Number of pixels counted
+    pub fn total(&self) -> usize { self.r.iter().sum() }
+
+    pub fn channels(&self) -> [&[usize; 256]; 3] { [&self.r, &self.g, &self.b] }
+}
+
+/// Remaps 0..=255 so the cumulative count of `bins` becomes (roughly) a straight line
+fn equalization_table(bins: &[usize; 256]) -> [u8; 256] {
+    let total: usize = bins.iter().sum();
+    let cdf_min = bins.iter().copied().find(|&n| n > 0).unwrap_or(0);
+    let mut table = [0; 256];
+    let mut acc = 0;
+    for (v, n) in bins.iter().enumerate() {
+        acc += n;
+        // A single value can't be spread out, so it's left alone
+        table[v] = if total == cdf_min { v as u8 } else { ((acc - cdf_min.min(acc)) as f64 * 255.0 / (total - cdf_min) as f64).round() as u8 };
+    }
+    table
+}
+
+impl ImagePPM {
+    pub fn histogram(&self) -> Histogram {
+        let mut h = Histogram { r: [0; 256], g: [0; 256], b: [0; 256] };
+        for p in &self.atoms {
+            h.r[p.r as usize] += 1;
+            h.g[p.g as usize] += 1;
+            h.b[p.b as usize] += 1;
+        }
+        h
+    }
+
+    /// Smallest value of each channel (they may come from different pixels). `None` if the image
+    /// has no pixels
+    pub fn min(&self) -> Option<Pixel> {
+        self.atoms.iter().copied().reduce(|a, p| Pixel::new(a.r.min(p.r), a.g.min(p.g), a.b.min(p.b)))
+    }
+
+    /// Largest value of each channel (they may come from different pixels). `None` if the image
+    /// has no pixels
+    pub fn max(&self) -> Option<Pixel> {
+        self.atoms.iter().copied().reduce(|a, p| Pixel::new(a.r.max(p.r), a.g.max(p.g), a.b.max(p.b)))
+    }
+
+    /// Average of each channel, as `[r, g, b]`. `None` if the image has no pixels
+    pub fn mean(&self) -> Option<[f64; 3]> {
+        if self.atoms.is_empty() { return None; }
+        let sum = self.atoms.iter().fold([0.0; 3], |s, p| [s[0] + p.r as f64, s[1] + p.g as f64, s[2] + p.b as f64]);
+        Some(sum.map(|s| s / self.atoms.len() as f64))
+    }
+
+    /// Stretches every channel so its values use the whole 0..=255 range evenly. Great for
+    /// washed out images, but since channels are done separately it can shift hues a bit
+    pub fn equalize_histogram(&mut self) {
+        let h = self.histogram();
+        let [tr, tg, tb] = h.channels().map(equalization_table);
+        for p in &mut self.atoms {
+            *p = Pixel::new(tr[p.r as usize], tg[p.g as usize], tb[p.b as usize]);
+        }
+    }
+}
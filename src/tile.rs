@@ -0,0 +1,69 @@
+//! Drawing that wraps around the edges, for seamless textures
+use std::{io::Write, ops};
+
+use crate::{Coord, Draw, Origin, PpmError, PpmFormat};
+
+/// What happens to whatever gets drawn past an edge of the image
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WrapMode {
+    /// It's cut off. What every image does unless told otherwise
+    #[default]
+    Clip,
+    /// It comes back in from the opposite edge, as if the image was repeated forever in every
+    /// direction (drawing on a torus), so the result always tiles seamlessly
+    Wrap,
+}
+
+/// Wraps an image so every drawing primitive wraps around its edges (see `WrapMode::Wrap`).
+/// Coordinates and storage don't change, it's only what falls outside that does: reading pixels,
+/// transforming or saving work just like on the inner image. Shapes are meant to be smaller than
+/// the image: along an axis where one is bigger, only one image-sized stretch of it gets drawn
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tiled<I>(pub I);
+
+impl<I> Tiled<I> {
+    pub fn into_inner(self) -> I { self.0 }
+}
+
+impl<I> From<I> for Tiled<I> {
+    fn from(img: I) -> Self { Tiled(img) }
+}
+
+impl<I: PpmFormat> Draw for Tiled<I> {
+    type Atom = I::Atom;
+
+    fn width(&self) -> usize { self.0.width() }
+    fn height(&self) -> usize { self.0.height() }
+    fn origin(&self) -> Origin { self.0.origin() }
+    fn wrap_mode(&self) -> WrapMode { WrapMode::Wrap }
+
+    fn get(&self, x: usize, y: usize) -> Option<&I::Atom> { self.0.get(x, y) }
+    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut I::Atom> { self.0.get_mut(x, y) }
+}
+
+impl<I: PpmFormat> PpmFormat for Tiled<I> {
+    fn new(width: usize, height: usize, bg_color: I::Atom) -> Self { Tiled(I::new(width, height, bg_color)) }
+    fn new_like(&self, width: usize, height: usize, bg_color: I::Atom) -> Self { Tiled(self.0.new_like(width, height, bg_color)) }
+    fn blank(&self) -> Option<I::Atom> { self.0.blank() }
+    fn atoms(&self) -> &Vec<I::Atom> { self.0.atoms() }
+    fn atoms_mut(&mut self) -> &mut Vec<I::Atom> { self.0.atoms_mut() }
+
+    fn pixels(&self) -> impl DoubleEndedIterator<Item = &I::Atom> { self.0.pixels() }
+    fn pixels_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut I::Atom> { self.0.pixels_mut() }
+
+    fn write_to(&self, w: impl Write) -> Result<(), PpmError> { self.0.write_to(w) }
+    fn write_binary_to(&self, w: impl Write) -> Result<(), PpmError> { self.0.write_binary_to(w) }
+}
+
+impl<I: PpmFormat> ops::Index<Coord> for Tiled<I> {
+    type Output = I::Atom;
+    fn index(&self, c: Coord) -> &I::Atom {
+        self.get(c.x, c.y).unwrap_or_else(|| panic!("{c:?} is out of bounds ({}x{})", self.width(), self.height()))
+    }
+}
+impl<I: PpmFormat> ops::IndexMut<Coord> for Tiled<I> {
+    fn index_mut(&mut self, c: Coord) -> &mut I::Atom {
+        let (w, h) = (self.width(), self.height());
+        self.get_mut(c.x, c.y).unwrap_or_else(|| panic!("{c:?} is out of bounds ({w}x{h})"))
+    }
+}
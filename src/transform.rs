@@ -0,0 +1,194 @@
+//! Affine transforms and a canvas-like wrapper that applies them to every drawing call
+use crate::{font, path::{Path, StrokeStyle}, CoordF, Draw, Origin};
+
+/// 2D affine transform, stored as the top two rows of a 3x3 matrix:
+/// `x' = a*x + b*y + c`, `y' = d*x + e*y + f`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub a: f64, pub b: f64, pub c: f64,
+    pub d: f64, pub e: f64, pub f: f64,
+}
+
+impl Default for Transform {
+    fn default() -> Self { Self::IDENTITY }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self { a: 1.0, b: 0.0, c: 0.0, d: 0.0, e: 1.0, f: 0.0 };
+
+    pub fn translate(dx: f64, dy: f64) -> Self { Self { c: dx, f: dy, ..Self::IDENTITY } }
+    pub fn scale(sx: f64, sy: f64) -> Self { Self { a: sx, e: sy, ..Self::IDENTITY } }
+
+    /// Counterclockwise rotation of `theta` radians around the origin
+    pub fn rotate(theta: f64) -> Self {
+        let (sin, cos) = theta.sin_cos();
+        Self { a: cos, b: -sin, c: 0.0, d: sin, e: cos, f: 0.0 }
+    }
+
+    /// Counterclockwise rotation of `theta` radians around `center`
+    pub fn rotate_around(center: impl Into<CoordF>, theta: f64) -> Self {
+        let center = center.into();
+        Self::translate(-center.x, -center.y).then(Self::rotate(theta)).then(Self::translate(center.x, center.y))
+    }
+
+    /// Transform that applies `self` first and `next` afterwards
+    pub fn then(self, next: Self) -> Self {
+        Self {
+            a: next.a*self.a + next.b*self.d, b: next.a*self.b + next.b*self.e, c: next.a*self.c + next.b*self.f + next.c,
+            d: next.d*self.a + next.e*self.d, e: next.d*self.b + next.e*self.e, f: next.d*self.c + next.e*self.f + next.f,
+        }
+    }
+
+    pub fn apply(&self, p: impl Into<CoordF>) -> CoordF {
+        let p = p.into();
+        CoordF::new(self.a*p.x + self.b*p.y + self.c, self.d*p.x + self.e*p.y + self.f)
+    }
+
+    /// How much lengths get stretched, at most. Used to pick how finely to approximate curves
+    fn max_scale(&self) -> f64 { self.a.hypot(self.d).max(self.b.hypot(self.e)) }
+}
+
+/// Borrows an image and draws on it through a stack of transforms, in the spirit of the HTML
+/// canvas. Everything is turned into polygons and lines before being transformed, so rotated
+/// rectangles, sheared circles and such come out right
+pub struct Canvas<'a, I: Draw> {
+    img: &'a mut I,
+    current: Transform,
+    stack: Vec<Transform>,
+}
+
+impl<'a, I: Draw> Canvas<'a, I> {
+    pub fn new(img: &'a mut I) -> Self { Self { img, current: Transform::IDENTITY, stack: Vec::new() } }
+
+    /// The transform currently being applied
+    pub fn transform(&self) -> Transform { self.current }
+
+    /// The underlying image, to draw on it without any transform
+    pub fn image(&mut self) -> &mut I { self.img }
+
+    /// Save the current transform and compose `t` onto it: shapes get `t` applied first, then
+    /// whatever was already there
+    pub fn push_transform(&mut self, t: Transform) {
+        self.stack.push(self.current);
+        self.current = t.then(self.current);
+    }
+
+    /// Go back to the transform before the last `push_transform`. Does nothing if the stack is
+    /// empty
+    pub fn pop_transform(&mut self) {
+        if let Some(t) = self.stack.pop() { self.current = t; }
+    }
+
+    fn map<P: Into<CoordF> + Copy>(&self, points: &[P]) -> Vec<CoordF> {
+        points.iter().map(|&p| self.current.apply(p)).collect()
+    }
+
+    /// Points around an ellipse, enough for the segments to be about a pixel long once transformed
+    fn ellipse_points(&self, center: CoordF, rx: f64, ry: f64) -> Vec<CoordF> {
+        let n = ((std::f64::consts::TAU * rx.max(ry) * self.current.max_scale()).ceil() as usize).clamp(8, 4096);
+        (0..n).map(|i| {
+            let t = i as f64 * std::f64::consts::TAU / n as f64;
+            CoordF::new(center.x + rx*t.cos(), center.y + ry*t.sin())
+        }).collect()
+    }
+
+    pub fn draw_line(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, col: I::Atom) {
+        let (a, b) = (self.current.apply(a), self.current.apply(b));
+        self.img.draw_line(a, b, col);
+    }
+
+    pub fn draw_line_aa(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, col: I::Atom) where I::Atom: crate::Blend {
+        let (a, b) = (self.current.apply(a), self.current.apply(b));
+        self.img.draw_line_aa(a, b, col);
+    }
+
+    pub fn draw_polyline<P: Into<CoordF> + Copy>(&mut self, points: &[P], col: I::Atom) {
+        let points = self.map(points);
+        self.img.draw_polyline(&points, col);
+    }
+
+    pub fn draw_polygon_outline<P: Into<CoordF> + Copy>(&mut self, points: &[P], col: I::Atom) {
+        let points = self.map(points);
+        self.img.draw_polygon_outline(&points, col);
+    }
+
+    pub fn fill_polygon<P: Into<CoordF> + Copy>(&mut self, points: &[P], col: I::Atom) {
+        let points = self.map(points);
+        self.img.fill_polygon(&points, col);
+    }
+
+    /// The width gets scaled too (by the most the transform stretches anything)
+    pub fn stroke_path(&mut self, path: &Path, width: f64, col: I::Atom) {
+        self.img.stroke_path(&path.transformed(self.current), width*self.current.max_scale(), col);
+    }
+
+    /// Dash lengths are taken after the transform, in pixels
+    pub fn stroke_path_styled(&mut self, path: &Path, width: f64, style: StrokeStyle, col: I::Atom) {
+        self.img.stroke_path_styled(&path.transformed(self.current), width*self.current.max_scale(), style, col);
+    }
+
+    pub fn fill_path(&mut self, path: &Path, col: I::Atom) {
+        self.img.fill_path(&path.transformed(self.current), col);
+    }
+
+    pub fn fill_triangle(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, c: impl Into<CoordF>, col: I::Atom) {
+        let (a, b, c) = (self.current.apply(a), self.current.apply(b), self.current.apply(c));
+        self.img.fill_triangle(a, b, c, col);
+    }
+
+    pub fn draw_triangle(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, c: impl Into<CoordF>, col: I::Atom) {
+        let (a, b, c) = (self.current.apply(a), self.current.apply(b), self.current.apply(c));
+        self.img.draw_triangle(a, b, c, col);
+    }
+
+    /// Rectangle with corners `a` and `b`, as a polygon so it can end up rotated
+    pub fn fill_rect(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, col: I::Atom) {
+        let (a, b) = (a.into(), b.into());
+        self.fill_polygon(&[a, CoordF::new(b.x, a.y), b, CoordF::new(a.x, b.y)], col);
+    }
+
+    pub fn draw_rect(&mut self, a: impl Into<CoordF>, b: impl Into<CoordF>, col: I::Atom) {
+        let (a, b) = (a.into(), b.into());
+        self.draw_polygon_outline(&[a, CoordF::new(b.x, a.y), b, CoordF::new(a.x, b.y)], col);
+    }
+
+    pub fn fill_ellipse(&mut self, center: impl Into<CoordF>, rx: f64, ry: f64, col: I::Atom) {
+        let points = self.ellipse_points(center.into(), rx, ry);
+        self.fill_polygon(&points, col);
+    }
+
+    pub fn draw_ellipse_outline(&mut self, center: impl Into<CoordF>, rx: f64, ry: f64, col: I::Atom) {
+        let points = self.ellipse_points(center.into(), rx, ry);
+        self.draw_polygon_outline(&points, col);
+    }
+
+    pub fn fill_circle(&mut self, center: impl Into<CoordF>, radius: f64, col: I::Atom) {
+        self.fill_ellipse(center, radius, radius, col);
+    }
+
+    pub fn draw_circle_outline(&mut self, center: impl Into<CoordF>, radius: f64, col: I::Atom) {
+        self.draw_ellipse_outline(center, radius, radius, col);
+    }
+
+    /// Same as `Draw::draw_text`, but each font pixel is a transformed square, so text can
+    /// be rotated or sheared too
+    pub fn draw_text(&mut self, origin: impl Into<CoordF>, text: &str, scale: f64, col: I::Atom) {
+        let origin = origin.into();
+        let size = font::GLYPH_SIZE as f64 * scale;
+        let flipped = self.img.origin() == Origin::TopLeft;
+        let down = if flipped { size } else { -size };
+        for (line_no, line) in text.lines().enumerate() {
+            for (i, c) in line.chars().enumerate() {
+                let base = CoordF::new(origin.x + i as f64*size, origin.y + line_no as f64*down);
+                for (row, bits) in font::glyph(c).iter().enumerate() {
+                for bit in 0..font::GLYPH_SIZE {
+                    if bits >> bit & 1 == 0 { continue; }
+                    let row = if flipped { row } else { font::GLYPH_SIZE - 1 - row };
+                    let corner = base + CoordF::new(bit as f64, row as f64)*scale;
+                    self.fill_rect(corner - CoordF::new(0.5, 0.5), corner + CoordF::new(scale - 0.5, scale - 0.5), col);
+                }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Turtle graphics: a pen that walks around the image, turning and drawing as it goes. L-systems
+//! and most fractal curves come out of it in a few lines
+use crate::{CoordF, Draw};
+
+/// Where the turtle is, where it's facing and whether it's drawing
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct State<A> {
+    pos: CoordF,
+    /// In degrees, counterclockwise from the positive x axis
+    heading: f64,
+    pen_down: bool,
+    color: A,
+}
+
+/// Starts facing right (towards +x) with the pen down. Angles are in degrees and positive turns go
+/// counterclockwise (y grows upwards; with a top left origin they go clockwise instead)
+pub struct Turtle<'a, I: Draw> {
+    img: &'a mut I,
+    state: State<I::Atom>,
+    stack: Vec<State<I::Atom>>,
+}
+
+impl<'a, I: Draw> Turtle<'a, I> {
+    pub fn new(img: &'a mut I, start: impl Into<CoordF>, color: I::Atom) -> Self {
+        Self { img, state: State { pos: start.into(), heading: 0.0, pen_down: true, color }, stack: vec![] }
+    }
+
+    pub fn position(&self) -> CoordF { self.state.pos }
+    pub fn heading(&self) -> f64 { self.state.heading }
+    pub fn is_pen_down(&self) -> bool { self.state.pen_down }
+
+    /// The underlying image
+    pub fn image(&mut self) -> &mut I { self.img }
+
+    /// Walk `distance` pixels forward, drawing a line if the pen is down. Negative goes backwards
+    pub fn forward(&mut self, distance: f64) {
+        let (sin, cos) = self.state.heading.to_radians().sin_cos();
+        // Rounding noise would make a line at x = 1 come out at x = 0.99999, so one pixel off
+        let to = CoordF::new(self.state.pos.x + distance*cos, self.state.pos.y + distance*sin).snapped();
+        if self.state.pen_down { self.img.draw_line(self.state.pos, to, self.state.color); }
+        self.state.pos = to;
+    }
+
+    pub fn back(&mut self, distance: f64) { self.forward(-distance); }
+
+    /// Turn counterclockwise by `degrees` (clockwise if negative)
+    pub fn turn(&mut self, degrees: f64) { self.state.heading = (self.state.heading + degrees).rem_euclid(360.0); }
+
+    pub fn left(&mut self, degrees: f64) { self.turn(degrees); }
+    pub fn right(&mut self, degrees: f64) { self.turn(-degrees); }
+
+    /// Face `degrees` counterclockwise from the positive x axis, whatever the current heading
+    pub fn set_heading(&mut self, degrees: f64) { self.state.heading = degrees.rem_euclid(360.0); }
+
+    /// Move straight to `pos` without drawing, keeping the heading
+    pub fn jump_to(&mut self, pos: impl Into<CoordF>) { self.state.pos = pos.into(); }
+
+    pub fn pen_up(&mut self) { self.state.pen_down = false; }
+    pub fn pen_down(&mut self) { self.state.pen_down = true; }
+    pub fn set_color(&mut self, color: I::Atom) { self.state.color = color; }
+
+    /// Remember position, heading, pen and color, to come back with `pop` (L-systems' `[`)
+    pub fn push(&mut self) { self.stack.push(self.state); }
+
+    /// Go back to the last `push`ed state without drawing (L-systems' `]`). Does nothing if there
+    /// isn't one
+    pub fn pop(&mut self) {
+        if let Some(s) = self.stack.pop() { self.state = s; }
+    }
+}
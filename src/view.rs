@@ -0,0 +1,127 @@
+//! Borrowed views of a rectangular part of an image, to read or draw on just that part without
+//! copying it out. Made with `PpmFormat::view` and `PpmFormat::view_mut`
+use crate::{Coord, Draw, Image, Origin, PixelLike};
+
+/// Read-only view of a region of an image. Coordinates are relative to the region, with (0, 0) in
+/// the same corner the image has it
+#[derive(Clone, Copy, Debug)]
+pub struct ImageView<'a, A> {
+    /// From the region's first pixel in storage to its last, rows `stride` apart
+    atoms: &'a [A],
+    stride: usize,
+    width: usize,
+    height: usize,
+    origin: Origin,
+    corner: Coord,
+}
+
+/// Mutable view of a region of an image. It implements `Draw`, so every drawing primitive works on
+/// it, clipped to the region
+#[derive(Debug)]
+pub struct ImageViewMut<'a, A> {
+    atoms: &'a mut [A],
+    stride: usize,
+    width: usize,
+    height: usize,
+    origin: Origin,
+    corner: Coord,
+}
+
+/// Where a `w`x`h` region with its corner at `corner` starts and ends in the storage of a
+/// `stride`x`height` image. None if it's empty or doesn't fit
+pub(crate) fn region_bounds(stride: usize, height: usize, origin: Origin, corner: Coord, w: usize, h: usize) -> Option<(usize, usize)> {
+    let (right, top) = (corner.x.checked_add(w)?, corner.y.checked_add(h)?);
+    if w == 0 || h == 0 || right > stride || top > height { return None; }
+    let first_row = match origin {
+        Origin::BottomLeft => height - top,
+        Origin::TopLeft => corner.y,
+    };
+    Some((first_row*stride + corner.x, (first_row + h - 1)*stride + right))
+}
+
+impl<'a, A> ImageView<'a, A> {
+    pub(crate) fn new(atoms: &'a [A], stride: usize, width: usize, height: usize, origin: Origin, corner: Coord) -> Self {
+        Self { atoms, stride, width, height, origin, corner }
+    }
+
+    pub fn width(&self) -> usize { self.width }
+    pub fn height(&self) -> usize { self.height }
+    pub fn origin(&self) -> Origin { self.origin }
+
+    /// Where the region's (0, 0) is in the whole image
+    pub fn corner(&self) -> Coord { self.corner }
+
+    fn storage_row(&self, y: usize) -> usize {
+        match self.origin {
+            Origin::BottomLeft => self.height - y - 1,
+            Origin::TopLeft => y,
+        }
+    }
+
+    /// Pixel at (x, y) of the region. None if it's outside of it
+    pub fn get(&self, x: usize, y: usize) -> Option<&'a A> {
+        if x >= self.width || y >= self.height { return None; }
+        Some(&self.atoms[self.storage_row(y)*self.stride + x])
+    }
+
+    /// Row `y` of the region, left to right
+    pub fn row(&self, y: usize) -> Option<&'a [A]> {
+        if y >= self.height { return None; }
+        let start = self.storage_row(y)*self.stride;
+        Some(&self.atoms[start..start + self.width])
+    }
+}
+
+impl<A: PixelLike> ImageView<'_, A> {
+    /// Copy the region out into an image of its own
+    pub fn to_image(&self) -> Image<A> {
+        let atoms = (0..self.height).flat_map(|r| &self.atoms[r*self.stride..r*self.stride + self.width]).copied().collect();
+        Image::from_atoms(self.width, self.height, atoms)
+    }
+}
+
+impl<'a, A> ImageViewMut<'a, A> {
+    pub(crate) fn new(atoms: &'a mut [A], stride: usize, width: usize, height: usize, origin: Origin, corner: Coord) -> Self {
+        Self { atoms, stride, width, height, origin, corner }
+    }
+
+    /// Read-only version of this view, borrowing it
+    pub fn as_view(&self) -> ImageView<'_, A> { ImageView::new(self.atoms, self.stride, self.width, self.height, self.origin, self.corner) }
+
+    /// Where the region's (0, 0) is in the whole image
+    pub fn corner(&self) -> Coord { self.corner }
+
+    fn storage_row(&self, y: usize) -> usize {
+        match self.origin {
+            Origin::BottomLeft => self.height - y - 1,
+            Origin::TopLeft => y,
+        }
+    }
+
+    pub fn row(&self, y: usize) -> Option<&[A]> { self.as_view().row(y) }
+
+    pub fn row_mut(&mut self, y: usize) -> Option<&mut [A]> {
+        if y >= self.height { return None; }
+        let start = self.storage_row(y)*self.stride;
+        Some(&mut self.atoms[start..start + self.width])
+    }
+}
+
+impl<A: Copy> Draw for ImageViewMut<'_, A> {
+    type Atom = A;
+
+    fn width(&self) -> usize { self.width }
+    fn height(&self) -> usize { self.height }
+    fn origin(&self) -> Origin { self.origin }
+
+    fn get(&self, x: usize, y: usize) -> Option<&A> {
+        if x >= self.width || y >= self.height { return None; }
+        Some(&self.atoms[self.storage_row(y)*self.stride + x])
+    }
+
+    fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut A> {
+        if x >= self.width || y >= self.height { return None; }
+        let i = self.storage_row(y)*self.stride + x;
+        Some(&mut self.atoms[i])
+    }
+}
@@ -0,0 +1,26 @@
+//! Voronoi diagrams: every pixel takes the color of the site closest to it
+use crate::{Coord, ImagePPM, Pixel, PpmFormat};
+
+/// Index of the site closest to `p`, the first one on ties
+fn nearest(p: Coord, sites: &[Coord]) -> usize {
+    sites.iter().enumerate()
+        .min_by(|(_, a), (_, b)| p.distance(**a).total_cmp(&p.distance(**b)))
+        .map_or(0, |(i, _)| i)
+}
+
+/// A `width`x`height` Voronoi partition of `sites` (same coordinates as `get`). Site `i` gets
+/// `colors[i]`, wrapping around if there are fewer colors than sites. With `border` set, pixels
+/// where two cells meet are painted with it. Panics if there are sites but no colors
+pub fn voronoi(width: usize, height: usize, sites: &[Coord], colors: &[Pixel], border: Option<Pixel>) -> ImagePPM {
+    if sites.is_empty() { return ImagePPM::new(width, height, Pixel::BLACK); }
+    assert!(!colors.is_empty(), "voronoi needs at least one color");
+    ImagePPM::par_from_fn(width, height, |p| {
+        let cell = nearest(p, sites);
+        // Comparing against the right and upper neighbours gives one pixel wide borders
+        let edge = |q: Coord| q.x < width && q.y < height && nearest(q, sites) != cell;
+        match border {
+            Some(col) if edge(Coord::new(p.x + 1, p.y)) || edge(Coord::new(p.x, p.y + 1)) => col,
+            _ => colors[cell % colors.len()],
+        }
+    })
+}
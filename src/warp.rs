@@ -0,0 +1,11 @@
+//! Remapping where every pixel comes from, for lens and distortion effects
+use crate::{Coord, CoordF, ImagePPM, ResizeFilter};
+
+impl ImagePPM {
+    /// New image the same size, where pixel `c` shows this one at `source(c)` (any point, see
+    /// `sample`). Points past the edges get the closest edge pixel. An empty image gives an empty
+    /// one back, without calling `source`
+    pub fn warp(&self, mut source: impl FnMut(Coord) -> CoordF, filter: ResizeFilter) -> ImagePPM {
+        ImagePPM::from_fn(self.width, self.height, |c| self.sample(source(c), filter))
+    }
+}
@@ -1,4 +1,9 @@
-use ppmitzador::{utils::idx_to_coords, Coord, ImagePBM, ImagePPM, Pixel, PpmFormat};
+use ppmitzador::{composite::BlendMode, anim::{AnimatedGif, FrameSequence, Y4mWriter}, color::Gradient, fill::FillStyle, filter::{IntegralImage, Kernel}, noise::Noise, origin::TopLeft, palette::DitherMethod, path::{Path, StrokeStyle}, sprite::Sprite, tile::{Tiled, WrapMode}, transform::{Canvas, Transform}, turtle::Turtle, utils::idx_to_coords, view::ImageViewMut, Blend, Coord, CoordF, Draw, ICoord, ImageGrayAlpha, ImagePBM, ImagePGM, ImagePPM, ImagePPM16, ImageRGBA, LineCap, Origin, Pixel, Pixel16, PixelGrayAlpha, PixelRgba, PpmError, PpmFormat, ResizeFilter};
+
+fn out(name: &str) -> String {
+    std::fs::create_dir_all("test_outputs").unwrap();
+    format!("test_outputs/{name}")
+}
 
 #[test]
 fn color_square() {
@@ -11,7 +16,7 @@ fn color_square() {
         pixel.g = y as u8;
     }
 
-    sq.save_to_file("test_outputs/TEST_color_wheel.ppm").unwrap();
+    sq.save_to_file(out("TEST_color_wheel.ppm")).unwrap();
 
 }
 #[test]
@@ -19,6 +24,2477 @@ fn bw_square() {
     let mut sq = ImagePBM::new(255, 255, false);
     sq.draw_circle(Coord { x: 100, y: 100 }, 30, true);
 
-    sq.save_to_file("test_outputs/TEST_bw_square.pbm").unwrap();
+    sq.save_to_file(out("TEST_bw_square.pbm")).unwrap();
+
+}
+
+#[test]
+fn threshold_to_pbm() {
+    let mut color = ImagePPM::new(3, 1, Pixel::new(100, 100, 100));
+    *color.get_mut(2, 0).unwrap() = Pixel::WHITE;
+    let bw = ImagePBM::from_threshold(&color, 128);
+    assert_eq!(bw.atoms(), &vec![false, false, true]);
+}
+
+#[test]
+fn binary_saves() {
+    let mut img = ImagePPM::new(10, 3, Pixel::BLUE);
+    *img.get_mut(0, 0).unwrap() = Pixel::RED;
+    img.save_to_file_binary(out("TEST_binary.ppm")).unwrap();
+
+    let bytes = std::fs::read(out("TEST_binary.ppm")).unwrap();
+    let header = b"P6\n10 3\n255\n";
+    assert_eq!(&bytes[..header.len()], header);
+    assert_eq!(bytes.len(), header.len() + 10*3*3);
+    // (0, 0) is bottom left, so it's the first pixel of the last row
+    assert_eq!(&bytes[header.len() + 20*3..][..3], &[255, 0, 0]);
+
+    let mut bw = ImagePBM::new(10, 2, false);
+    *bw.get_mut(9, 1).unwrap() = true;
+    bw.save_to_file_binary(out("TEST_binary.pbm")).unwrap();
+    let bytes = std::fs::read(out("TEST_binary.pbm")).unwrap();
+    assert_eq!(&bytes[b"P4\n10 2\n".len()..], &[0xFF, 0x80, 0xFF, 0xC0]);
+}
+
+#[test]
+fn load_round_trip() {
+    let mut img = ImagePPM::new(7, 5, Pixel::PURPLE);
+    img.draw_line(Coord::new(0, 0), Coord::new(6, 4), Pixel::GREEN);
+    img.save_to_file(out("TEST_roundtrip.ppm")).unwrap();
+    img.save_to_file_binary(out("TEST_roundtrip_bin.ppm")).unwrap();
+
+    for path in ["TEST_roundtrip.ppm", "TEST_roundtrip_bin.ppm"] {
+        let loaded = ImagePPM::load_from_file(out(path)).unwrap();
+        assert_eq!((loaded.width(), loaded.height()), (7, 5));
+        for (a, b) in loaded.atoms().iter().zip(img.atoms()) { assert_eq!((a.r, a.g, a.b), (b.r, b.g, b.b)); }
+    }
+
+    let weird = b"P3\n# a comment\n2 1 # trailing\n15\n15 0 0   0 15 7\n";
+    let loaded = ImagePPM::from_reader(&weird[..]).unwrap();
+    assert_eq!(loaded.get(1, 0).map(|p| (p.r, p.g, p.b)), Some((0, 255, 119)));
+
+    assert!(ImagePPM::from_reader(&b"P3\n1 1\n255\n256 0 0\n"[..]).is_err());
+}
+
+#[test]
+fn grayscale_from_color() {
+    let mut img = ImagePPM::new(4, 4, Pixel::WHITE);
+    *img.get_mut(1, 2).unwrap() = Pixel::GREEN;
+    let gray = ImagePGM::from(&img);
+
+    assert_eq!(gray.get(0, 0), Some(&255));
+    assert_eq!(gray.get(1, 2), Some(&150));
+    gray.save_to_file(out("TEST_gray.pgm")).unwrap();
+    gray.save_to_file_binary(out("TEST_gray_bin.pgm")).unwrap();
+}
+
+#[test]
+fn write_to_memory() {
+    let img = ImagePGM::new(2, 2, 7);
+    let mut buf = Vec::new();
+    img.write_binary_to(&mut buf).unwrap();
+    assert_eq!(buf, b"P5\n2 2\n255\n\x07\x07\x07\x07");
+
+    let mut buf = Vec::new();
+    ImagePBM::new(2, 1, true).write_to(&mut buf).unwrap();
+    assert_eq!(buf, b"P1\n2 1\n00");
+}
+
+#[test]
+fn rectangles() {
+    let mut img = ImagePBM::new(6, 5, false);
+    img.fill_rect(Coord::new(4, 3), Coord::new(1, 1), true);
+    assert_eq!(img.atoms().iter().filter(|&&b| b).count(), 4*3);
+    assert_eq!(img.get(1, 1), Some(&true));
+    assert_eq!(img.get(0, 1), Some(&false));
+
+    let mut img = ImagePBM::new(6, 5, false);
+    img.draw_rect(Coord::new(1, 1), Coord::new(10, 3), true);
+    assert_eq!(img.get(5, 2), Some(&false)); // right edge is off the image
+    assert_eq!(img.get(2, 2), Some(&false));
+    assert_eq!(img.get(1, 2), Some(&true));
+    assert_eq!(img.get(5, 3), Some(&true));
+}
+
+#[test]
+fn triangles() {
+    let mut img = ImagePBM::new(10, 10, false);
+    img.fill_triangle(Coord::new(0, 0), Coord::new(0, 9), Coord::new(9, 0), true);
+    // Right triangle with legs of 10 pixels, hypotenuse included
+    assert_eq!(img.atoms().iter().filter(|&&b| b).count(), 55);
+    assert_eq!(img.get(9, 9), Some(&false));
+
+    let mut out_img = ImagePPM::new(100, 100, Pixel::BLACK);
+    out_img.fill_triangle(Coord::new(10, 10), Coord::new(90, 30), Coord::new(40, 95), Pixel::RED);
+    out_img.draw_triangle(Coord::new(10, 10), Coord::new(90, 30), Coord::new(40, 95), Pixel::WHITE);
+    out_img.save_to_file(out("TEST_triangle.ppm")).unwrap();
+}
+
+#[test]
+fn polygon_even_odd() {
+    // A pentagram: the inner pentagon crosses the outline twice, so it stays empty
+    let mut img = ImagePPM::new(200, 200, Pixel::BLACK);
+    let star: Vec<Coord> = (0..5).map(|i| {
+        let angle = std::f64::consts::FRAC_PI_2 + i as f64 * 4.0 * std::f64::consts::PI / 5.0;
+        Coord::new((100.0 + 90.0*angle.cos()) as usize, (100.0 + 90.0*angle.sin()) as usize)
+    }).collect();
+    img.fill_polygon(&star, Pixel::GREEN);
+    img.save_to_file(out("TEST_star.ppm")).unwrap();
+
+    assert_eq!(img.get(100, 100).unwrap().g, 0);
+    assert_eq!(img.get(100, 180).unwrap().g, 255);
+
+    let mut sq = ImagePBM::new(10, 10, false);
+    sq.fill_polygon(&[Coord::new(2, 2), Coord::new(6, 2), Coord::new(6, 6), Coord::new(2, 6)], true);
+    assert_eq!(sq.atoms().iter().filter(|&&b| b).count(), 16);
+}
+
+#[test]
+fn antialiased_line() {
+    let mut img = ImagePGM::new(11, 11, 0);
+    img.draw_line_aa(Coord::new(0, 0), Coord::new(10, 5), 200);
+    img.save_to_file(out("TEST_aa_line.pgm")).unwrap();
+
+    // Endpoints are fully covered, midway points split their coverage between two pixels
+    assert_eq!(img.get(0, 0), Some(&200));
+    assert_eq!(img.get(10, 5), Some(&200));
+    assert_eq!(img.get(1, 0), Some(&100));
+    assert_eq!(img.get(1, 1), Some(&100));
+}
+
+#[test]
+fn thick_lines() {
+    let count = |cap| {
+        let mut img = ImagePBM::new(30, 30, false);
+        img.draw_line_thick(Coord::new(10, 15), Coord::new(20, 15), 5, cap, true);
+        img.atoms().iter().filter(|&&b| b).count()
+    };
+    // 11 pixels long, 5 wide, plus the caps
+    assert_eq!(count(LineCap::Butt), 55);
+    assert_eq!(count(LineCap::Square), 75);
+    assert!((55..75).contains(&count(LineCap::Round)));
+
+    let mut img = ImagePPM::new(100, 100, Pixel::BLACK);
+    img.draw_line_thick(Coord::new(5, 5), Coord::new(90, 60), 9, LineCap::Round, Pixel::BLUE);
+    img.save_to_file(out("TEST_thick_line.ppm")).unwrap();
+}
+
+#[test]
+fn circle_outlines() {
+    let mut img = ImagePBM::new(21, 21, false);
+    img.draw_circle_outline(Coord::new(10, 10), 8, true);
+    for (x, y) in [(18, 10), (2, 10), (10, 18), (10, 2)] { assert_eq!(img.get(x, y), Some(&true)); }
+    assert_eq!(img.get(10, 10), Some(&false));
+
+    // Partially off the image is fine
+    img.draw_circle_outline(Coord::new(0, 0), 5, true);
+
+    let mut img = ImagePPM::new(100, 100, Pixel::BLACK);
+    img.draw_circle_outline_thick(Coord::new(50, 50), 40, 6, Pixel::RED);
+    assert_eq!(img.get(50, 50).unwrap().r, 0);
+    assert_eq!(img.get(88, 50).unwrap().r, 255);
+    img.save_to_file(out("TEST_ring.ppm")).unwrap();
+}
+
+#[test]
+fn ellipses() {
+    let mut img = ImagePBM::new(41, 21, false);
+    img.draw_ellipse_outline(Coord::new(20, 10), 15, 6, true);
+    for (x, y) in [(35, 10), (5, 10), (20, 16), (20, 4)] { assert_eq!(img.get(x, y), Some(&true)); }
+    assert_eq!(img.get(20, 10), Some(&false));
+
+    let mut img = ImagePBM::new(41, 21, false);
+    img.fill_ellipse(Coord::new(20, 10), 15, 6, true);
+    assert_eq!(img.get(20, 10), Some(&true));
+    assert_eq!(img.get(35, 10), Some(&true));
+    assert_eq!(img.get(20, 17), Some(&false));
+    assert_eq!(img.get(35, 16), Some(&false));
+}
+
+#[test]
+fn degenerate_ellipses() {
+    let set = |img: &ImagePBM| img.enumerate_pixels().filter(|(_, &b)| b).map(|(c, _)| (c.x, c.y)).collect::<Vec<_>>();
+    // Both radii zero: a single point (this used to never return)
+    let mut img = ImagePBM::new(11, 11, false);
+    img.draw_ellipse_outline(Coord::new(5, 5), 0, 0, true);
+    assert_eq!(set(&img), vec![(5, 5)]);
+    // No width: a vertical line
+    let mut img = ImagePBM::new(11, 11, false);
+    img.draw_ellipse_outline(Coord::new(5, 5), 0, 3, true);
+    let mut pixels = set(&img);
+    pixels.sort();
+    assert_eq!(pixels, (2..=8).map(|y| (5, y)).collect::<Vec<_>>());
+    // No height: a horizontal line
+    let mut img = ImagePBM::new(11, 11, false);
+    img.draw_ellipse_outline(Coord::new(5, 5), 3, 0, true);
+    assert_eq!(set(&img), (2..=8).map(|x| (x, 5)).collect::<Vec<_>>());
+}
+
+#[test]
+fn polylines() {
+    let pts = [Coord::new(1, 1), Coord::new(8, 1), Coord::new(8, 8)];
+    let mut open = ImagePBM::new(10, 10, false);
+    open.draw_polyline(&pts, true);
+    let mut closed = open.clone();
+    closed.draw_polygon_outline(&pts, true);
+
+    assert_eq!(open.atoms().iter().filter(|&&b| b).count(), 15);
+    assert_eq!(open.get(4, 4), Some(&false));
+    assert_eq!(closed.get(4, 4), Some(&true));
+}
+
+#[test]
+fn text() {
+    let mut img = ImagePBM::new(16, 8, false);
+    img.draw_text(Coord::new(0, 0), "I|", 1, true);
+    // Middle column of the I is set all the way down but its bottom row is empty
+    assert_eq!(img.get(2, 7), Some(&true));
+    assert_eq!(img.get(2, 0), Some(&false));
+    assert_eq!(img.get(3, 4), Some(&true));
+    // The bar has a hole in the middle
+    assert_eq!(img.get(11, 6), Some(&true));
+    assert_eq!(img.get(11, 4), Some(&false));
+
+    let mut img = ImagePPM::new(200, 60, Pixel::BLACK);
+    img.draw_text(Coord::new(4, 36), "frame 0042\n(x, y) = 3, 4", 2, Pixel::WHITE);
+    img.save_to_file(out("TEST_text.ppm")).unwrap();
+}
+
+#[test]
+fn alpha_compositing() {
+    let mut canvas = ImagePPM::new(20, 20, Pixel::WHITE);
+    let mut layer = ImageRGBA::new(10, 10, PixelRgba::TRANSPARENT);
+    layer.fill_rect(Coord::new(0, 0), Coord::new(9, 9), PixelRgba::new(255, 0, 0, 128));
+    layer.fill_rect(Coord::new(0, 0), Coord::new(4, 4), PixelRgba::new(0, 0, 255, 255));
+
+    canvas.blit_with_alpha(&layer, Coord::new(15, 0)); // sticks out on the right
+    let p = canvas.get(17, 7).unwrap();
+    assert_eq!((p.r, p.g, p.b), (255, 127, 127));
+    let p = canvas.get(15, 0).unwrap();
+    assert_eq!((p.r, p.g, p.b), (0, 0, 255));
+    let p = canvas.get(14, 0).unwrap();
+    assert_eq!((p.r, p.g, p.b), (255, 255, 255));
+
+    let two = PixelRgba::new(0, 0, 255, 128).over_rgba(PixelRgba::new(255, 0, 0, 128));
+    assert_eq!(two.a, 192);
+
+    let flat = layer.flatten(Pixel::BLACK);
+    assert_eq!(flat.get(9, 9).unwrap().r, 128);
+    layer.save_to_file(out("TEST_layer.pam")).unwrap();
+}
+
+#[test]
+fn blit_and_sub_image() {
+    let mut sprite = ImagePGM::new(4, 3, 10);
+    *sprite.get_mut(0, 0).unwrap() = 99;
+
+    let mut canvas = ImagePGM::new(10, 10, 0);
+    canvas.blit(&sprite, Coord::new(8, 2));
+    assert_eq!(canvas.get(8, 2), Some(&99));
+    assert_eq!(canvas.get(9, 4), Some(&10));
+    assert_eq!(canvas.get(7, 2), Some(&0));
+
+    let sub = canvas.sub_image(Coord::new(8, 2), 5, 5).unwrap();
+    assert_eq!((sub.width(), sub.height()), (2, 5));
+    assert_eq!(sub.get(0, 0), Some(&99));
+    assert_eq!(sub.get(0, 4), Some(&0));
+    assert!(canvas.sub_image(Coord::new(10, 0), 1, 1).is_none());
+}
+
+#[test]
+fn resizing() {
+    let mut img = ImagePGM::new(2, 1, 0);
+    *img.get_mut(1, 0).unwrap() = 200;
+
+    let near = img.resize(4, 2, ResizeFilter::Nearest);
+    assert_eq!(near.atoms(), &vec![0, 0, 200, 200, 0, 0, 200, 200]);
+
+    let bilinear = img.resize(4, 1, ResizeFilter::Bilinear);
+    assert_eq!(bilinear.atoms(), &vec![0, 50, 150, 200]);
+
+    let mut sq = ImagePPM::new(32, 32, Pixel::BLACK);
+    sq.fill_ellipse(Coord::new(16, 16), 10, 6, Pixel::RED);
+    sq.resize(256, 256, ResizeFilter::Bilinear).save_to_file(out("TEST_upscaled.ppm")).unwrap();
+}
+
+#[test]
+fn rotations_and_flips() {
+    // 3x2, values are x + 10*y
+    let mut img = ImagePGM::new(3, 2, 0);
+    for y in 0..2 { for x in 0..3 { *img.get_mut(x, y).unwrap() = (x + 10*y) as u8; } }
+
+    let r = img.rotate90();
+    assert_eq!((r.width(), r.height()), (2, 3));
+    assert_eq!((r.get(1, 0), r.get(0, 0), r.get(1, 2)), (Some(&0), Some(&10), Some(&2)));
+    assert_eq!(img.rotate90().rotate90().atoms(), img.rotate180().atoms());
+    assert_eq!(img.rotate90().rotate270().atoms(), img.atoms());
+    assert_eq!(img.flip_horizontal().get(0, 0), Some(&2));
+    assert_eq!(img.flip_vertical().get(0, 0), Some(&10));
+    assert_eq!(img.flip_vertical().flip_horizontal().atoms(), img.rotate180().atoms());
+
+    let mut sq = ImagePPM::new(101, 101, Pixel::BLACK);
+    sq.fill_rect(Coord::new(20, 45), Coord::new(80, 55), Pixel::GREEN);
+    let turned = sq.rotate(std::f64::consts::FRAC_PI_4, Pixel::BLUE);
+    assert_eq!(turned.get(50, 50).unwrap().g, 255);
+    assert_eq!(turned.get(0, 0).unwrap().b, 255);
+    assert_eq!(turned.get(70, 70).unwrap().g, 255);
+    turned.save_to_file(out("TEST_rotated.ppm")).unwrap();
+}
+
+#[test]
+fn crop_and_pad() {
+    let mut img = ImagePBM::new(4, 4, false);
+    *img.get_mut(1, 2).unwrap() = true;
+
+    let padded = img.pad(1, 2, 3, 0, true);
+    assert_eq!((padded.width(), padded.height()), (7, 7));
+    assert_eq!(padded.get(4, 4), Some(&true));
+    assert_eq!(padded.get(3, 2), Some(&false));
+    assert_eq!(padded.get(2, 2), Some(&true));
+
+    let cropped = padded.crop(Coord::new(3, 2), 4, 4);
+    assert_eq!(cropped.atoms(), img.atoms());
+    assert!(std::panic::catch_unwind(|| img.crop(Coord::new(2, 2), 3, 1)).is_err());
+}
+
+#[test]
+fn kernels() {
+    let mut img = ImagePPM::new(40, 40, Pixel::BLACK);
+    img.fill_rect(Coord::new(10, 10), Coord::new(29, 29), Pixel::WHITE);
+
+    let blurred = img.apply_kernel(&Kernel::box_blur(1));
+    assert_eq!(blurred.get(9, 20).unwrap().r, 85);
+    assert_eq!(blurred.get(20, 20).unwrap().r, 255);
+    img.apply_kernel(&Kernel::gaussian(2.0)).save_to_file(out("TEST_gaussian.ppm")).unwrap();
+    img.apply_kernel(&Kernel::emboss()).save_to_file(out("TEST_emboss.ppm")).unwrap();
+    assert_eq!(img.apply_kernel(&Kernel::emboss()).get(0, 0).unwrap().r, 128);
+
+    let edges = img.sobel_edges();
+    assert_eq!(edges.get(20, 20).unwrap().r, 0);
+    assert_eq!(edges.get(10, 20).unwrap().r, 255);
+
+    // Top row of the kernel looks upwards
+    let up = Kernel::new(1, 3, vec![1.0, 0.0, 0.0]);
+    let mut rgb = ImagePPM::new(1, 3, Pixel::BLACK);
+    *rgb.get_mut(0, 2).unwrap() = Pixel::WHITE;
+    assert_eq!(rgb.apply_kernel(&up).get(0, 1).unwrap().r, 255);
+}
+
+#[test]
+fn separable_gaussian() {
+    let mut img = ImagePPM::new(50, 30, Pixel::BLACK);
+    img.fill_ellipse(Coord::new(25, 15), 12, 8, Pixel::new(200, 100, 50));
+
+    let fast = img.gaussian_blur(1.5);
+    let slow = img.apply_kernel(&Kernel::gaussian(1.5));
+    for (a, b) in fast.atoms().iter().zip(slow.atoms()) {
+        assert!(a.r.abs_diff(b.r) <= 1 && a.g.abs_diff(b.g) <= 1 && a.b.abs_diff(b.b) <= 1);
+    }
+    fast.save_to_file(out("TEST_gaussian_separable.ppm")).unwrap();
+}
+
+#[test]
+fn pixel_iterators() {
+    let mut img = ImagePGM::new(3, 2, 0);
+    for (Coord { x, y }, p) in img.enumerate_pixels_mut() { *p = (x + 10*y) as u8; }
+
+    assert_eq!(img.get(2, 1), Some(&12));
+    assert_eq!(img.pixels().copied().collect::<Vec<_>>(), vec![0, 1, 2, 10, 11, 12]);
+    assert_eq!(img.enumerate_pixels().last().map(|(c, &p)| (c.x, c.y, p)), Some((2, 1, 12)));
+
+    for p in img.pixels_mut() { *p += 1; }
+    assert_eq!(img.get(0, 0), Some(&1));
+}
+
+#[test]
+fn indexing() {
+    let mut img = ImagePPM::new(5, 5, Pixel::BLACK);
+    img[Coord::new(3, 4)] = Pixel::RED;
+    img[(0, 1)].g = 7;
+
+    assert_eq!(img.get(3, 4).unwrap().r, 255);
+    assert_eq!(img[(3, 4)].r, 255);
+    assert_eq!(img[Coord::new(0, 1)].g, 7);
+    assert!(std::panic::catch_unwind(|| img[(5, 0)]).is_err());
+}
+
+#[test]
+fn drawing_off_canvas_clips() {
+    let mut img = ImagePPM::new(20, 20, Pixel::BLACK);
+    img.draw_line(Coord::new(10, 10), Coord::new(50, 30), Pixel::RED);
+    img.draw_line(Coord::new(3, 3), Coord::new(3, 3), Pixel::RED);
+    img.draw_circle(Coord::new(1, 1), 10, Pixel::GREEN);
+    img.draw_line_with_thickness(Coord::new(15, 15), Coord::new(25, 40), Pixel::BLUE, 4);
+    img.draw_triangle(Coord::new(0, 0), Coord::new(100, 5), Coord::new(5, 100), Pixel::WHITE);
+    img.draw_text(Coord::new(12, 16), "clipped", 2, Pixel::WHITE);
+
+    assert_eq!(img.get(19, 14).unwrap().r, 255);
+    assert_eq!(img.get(0, 0).unwrap().b, 255); // triangle corner
+    assert_eq!(img.get(3, 3).unwrap().g, 255);
+    assert_eq!(img.get(3, 3).unwrap().r, 0);
+}
+
+#[test]
+fn coord_arithmetic() {
+    let (a, b) = (Coord::new(2, 5), Coord::new(3, 1));
+    assert!(a.checked_sub(b).is_none());
+    assert_eq!(b.checked_sub(Coord::new(1, 1)).map(|c| (c.x, c.y)), Some((2, 0)));
+    let s = a.saturating_sub(b);
+    assert_eq!((s.x, s.y), (0, 4));
+
+    let d = ICoord::from(a) - ICoord::from(b);
+    assert_eq!(d, ICoord::new(-1, 4));
+    assert!(Coord::try_from(d).is_err());
+    assert_eq!(-d, ICoord::new(1, -4));
+    assert_eq!(Coord::try_from(d + ICoord::new(1, 0)).ok().map(|c| (c.x, c.y)), Some((0, 4)));
+}
+
+#[test]
+fn float_coords() {
+    let a = CoordF::new(1.0, 0.0);
+    let r = a.rotate(std::f64::consts::FRAC_PI_2);
+    assert!((r.x).abs() < 1e-12 && (r.y - 1.0).abs() < 1e-12);
+    assert_eq!(a.lerp(CoordF::new(3.0, 4.0), 0.5), CoordF::new(2.0, 2.0));
+    assert_eq!(a.dot(CoordF::new(3.0, 4.0)), 3.0);
+    assert_eq!(a.cross(CoordF::new(3.0, 4.0)), 4.0);
+    assert!(CoordF::new(-0.7, 3.2).to_coord().is_none());
+    assert_eq!(CoordF::new(2.6, 3.2).to_coord().map(|c| (c.x, c.y)), Some((3, 3)));
+
+    // Same pixels whether drawn with integer or float coordinates
+    let mut int = ImagePBM::new(20, 20, false);
+    int.fill_triangle(Coord::new(1, 1), Coord::new(18, 4), Coord::new(7, 17), true);
+    let mut float = ImagePBM::new(20, 20, false);
+    float.fill_triangle(CoordF::new(1.0, 1.0), (18.0, 4.0), CoordF::new(7.0, 17.0), true);
+    assert_eq!(int.atoms(), float.atoms());
+
+    // Shapes can now start off the left/bottom edges
+    let mut img = ImagePPM::new(50, 50, Pixel::BLACK);
+    let hexagon: Vec<CoordF> = (0..6).map(|i| CoordF::new(30.0, 0.0).rotate(i as f64 * std::f64::consts::PI / 3.0)).collect();
+    img.fill_polygon(&hexagon, Pixel::GREEN);
+    img.draw_line_aa(CoordF::new(-10.0, 40.5), CoordF::new(60.0, 10.25), Pixel::WHITE);
+    assert_eq!(img.get(0, 0).unwrap().g, 255);
+    img.save_to_file(out("TEST_float_coords.ppm")).unwrap();
+}
+
+#[test]
+fn transform_stack() {
+    let t = Transform::translate(10.0, 0.0).then(Transform::rotate(std::f64::consts::FRAC_PI_2));
+    let p = t.apply(Coord::new(1, 0));
+    assert!((p.x - 0.0).abs() < 1e-9 && (p.y - 11.0).abs() < 1e-9);
+
+    let mut img = ImagePBM::new(30, 30, false);
+    let mut canvas = Canvas::new(&mut img);
+    canvas.push_transform(Transform::translate(20.0, 20.0));
+    canvas.fill_rect(CoordF::new(0.0, 0.0), CoordF::new(4.0, 4.0), true);
+    canvas.push_transform(Transform::scale(2.0, 2.0));
+    canvas.fill_rect(CoordF::new(-8.0, -8.0), CoordF::new(-6.0, -6.0), true);
+    canvas.pop_transform();
+    canvas.pop_transform();
+    canvas.pop_transform(); // extra pops are harmless
+    assert_eq!(canvas.transform(), Transform::IDENTITY);
+
+    assert_eq!(img.get(22, 22), Some(&true));
+    assert_eq!(img.get(18, 18), Some(&false));
+    assert_eq!(img.get(5, 5), Some(&true));
+
+    // Stamp the same shape around a center
+    let mut img = ImagePPM::new(200, 200, Pixel::BLACK);
+    let mut canvas = Canvas::new(&mut img);
+    for i in 0..12 {
+        canvas.push_transform(Transform::rotate_around(CoordF::new(100.0, 100.0), i as f64 * std::f64::consts::TAU / 12.0));
+        canvas.fill_rect(CoordF::new(150.0, 95.0), CoordF::new(190.0, 105.0), Pixel::GREEN);
+        canvas.draw_text(CoordF::new(120.0, 96.0), "hi", 1.0, Pixel::WHITE);
+        canvas.pop_transform();
+    }
+    img.save_to_file(out("TEST_transforms.ppm")).unwrap();
+}
+
+#[test]
+fn sixteen_bit() {
+    let mut img = ImagePPM16::new(2, 1, Pixel16::new(1000, 0, 65535));
+    img[(1, 0)] = Pixel16::new(258, 1, 2);
+    let mut buf = Vec::new();
+    img.write_binary_to(&mut buf).unwrap();
+    assert_eq!(&buf[b"P6\n2 1\n65535\n".len()..], &[3, 232, 0, 0, 255, 255, 1, 2, 0, 1, 0, 2]);
+
+    let back = ImagePPM16::from_reader(&buf[..]).unwrap();
+    assert_eq!((back.maxval(), back[(0, 0)].r, back[(1, 0)].r), (65535, 1000, 258));
+
+    let small = ImagePPM16::new(1, 1, Pixel16::new(5, 9, 1000)).with_maxval(9);
+    let mut buf = Vec::new();
+    small.write_to(&mut buf).unwrap();
+    assert_eq!(buf, b"P3\n1 1\n9\n5 9 9\n");
+    assert_eq!(small.to_8bit()[(0, 0)].r, 142);
+
+    let eight = ImagePPM::new(1, 1, Pixel::new(255, 1, 0));
+    assert_eq!(ImagePPM16::from(&eight)[(0, 0)].r, 65535);
+}
+
+#[test]
+fn hsv_hsl() {
+    let rgb = |p: Pixel| (p.r, p.g, p.b);
+    assert_eq!(rgb(Pixel::from_hsv(0.0, 1.0, 1.0)), (255, 0, 0));
+    assert_eq!(rgb(Pixel::from_hsv(120.0, 1.0, 1.0)), (0, 255, 0));
+    assert_eq!(rgb(Pixel::from_hsv(-60.0, 1.0, 1.0)), (255, 0, 255));
+    assert_eq!(rgb(Pixel::from_hsl(240.0, 1.0, 0.5)), (0, 0, 255));
+    assert_eq!(rgb(Pixel::from_hsl(0.0, 0.0, 1.0)), (255, 255, 255));
+    assert_eq!(rgb(Pixel::RED.hue_rotate(120.0)), (0, 255, 0));
+
+    for p in [Pixel::new(12, 200, 99), Pixel::new(250, 3, 128), Pixel::new(40, 40, 40)] {
+        let (h, s, v) = p.to_hsv();
+        assert_eq!(rgb(Pixel::from_hsv(h, s, v)), rgb(p));
+        let (h, s, l) = p.to_hsl();
+        assert_eq!(rgb(Pixel::from_hsl(h, s, l)), rgb(p));
+    }
+
+    let mut img = ImagePPM::new(360, 20, Pixel::BLACK);
+    for (Coord { x, .. }, p) in img.enumerate_pixels_mut() { *p = Pixel::from_hsv(x as f64, 1.0, 1.0); }
+    img.save_to_file(out("TEST_rainbow.ppm")).unwrap();
+}
+
+#[test]
+fn gradients() {
+    let rgb = |p: Pixel| (p.r, p.g, p.b);
+    let g = Gradient::new([(1.0, Pixel::WHITE), (0.0, Pixel::BLACK), (0.5, Pixel::RED)]);
+    assert_eq!(rgb(g.sample(-3.0)), (0, 0, 0));
+    assert_eq!(rgb(g.sample(0.25)), (128, 0, 0));
+    assert_eq!(rgb(g.sample(0.5)), (255, 0, 0));
+    assert_eq!(rgb(g.sample(0.75)), (255, 128, 128));
+    assert_eq!(rgb(g.sample(7.0)), (255, 255, 255));
+    assert_eq!(rgb(Gradient::new([(0.3, Pixel::BLUE)]).sample(0.9)), (0, 0, 255));
+
+    let mut img = ImagePPM::new(256, 60, Pixel::BLACK);
+    let maps = [Gradient::viridis(), Gradient::heat(), Gradient::grayscale()];
+    for (Coord { x, y }, p) in img.enumerate_pixels_mut() { *p = maps[y / 20].sample(x as f64 / 255.0); }
+    img.save_to_file(out("TEST_gradients.ppm")).unwrap();
+}
+
+#[test]
+fn gradient_fills() {
+    let rgb = |p: &Pixel| (p.r, p.g, p.b);
+    let mut img = ImagePPM::new(11, 5, Pixel::BLACK);
+    img.fill_linear_gradient((0.0, 0.0), (10.0, 0.0), Pixel::BLACK, Pixel::new(200, 100, 0));
+    assert_eq!(rgb(img.get(0, 4).unwrap()), (0, 0, 0));
+    assert_eq!(rgb(img.get(5, 2).unwrap()), (100, 50, 0));
+    assert_eq!(rgb(img.get(10, 0).unwrap()), (200, 100, 0));
+
+    img.fill_linear_gradient(Coord::new(3, 3), Coord::new(3, 3), Pixel::RED, Pixel::BLUE);
+    assert!(img.pixels().all(|p| rgb(p) == (255, 0, 0)));
+
+    img.fill_radial_gradient((5.0, 2.0), 4.0, Pixel::WHITE, Pixel::BLACK);
+    assert_eq!(rgb(img.get(5, 2).unwrap()), (255, 255, 255));
+    assert_eq!(rgb(img.get(7, 2).unwrap()), (128, 128, 128));
+    assert_eq!(rgb(img.get(0, 0).unwrap()), (0, 0, 0));
+
+    let mut img = ImagePPM::new(200, 100, Pixel::BLACK);
+    img.fill_linear_gradient((0.0, 0.0), (200.0, 100.0), Pixel::PURPLE, Pixel::GREEN);
+    img.save_to_file(out("TEST_linear_gradient.ppm")).unwrap();
+    img.fill_radial_gradient((100.0, 50.0), 80.0, Pixel::WHITE, Pixel::BLUE);
+    img.save_to_file(out("TEST_radial_gradient.ppm")).unwrap();
+}
+
+#[test]
+fn pixel_blending() {
+    let rgb = |p: Pixel| (p.r, p.g, p.b);
+    let a = Pixel::new(200, 100, 0);
+    let b = Pixel::new(100, 200, 255);
+    assert_eq!(rgb(a.lerp(b, 0.0)), (200, 100, 0));
+    assert_eq!(rgb(a.lerp(b, 0.5)), (150, 150, 128));
+    assert_eq!(rgb(a.lerp(b, 4.0)), (100, 200, 255));
+    assert_eq!(rgb(a.blend(b, 0.25)), rgb(a.lerp(b, 0.25)));
+    assert_eq!(rgb(a.additive(b)), (255, 255, 255));
+    assert_eq!(rgb(a.multiply(Pixel::WHITE)), (200, 100, 0));
+    assert_eq!(rgb(a.multiply(b)), (78, 78, 0));
+    assert_eq!(rgb(a.screen(Pixel::BLACK)), (200, 100, 0));
+    assert_eq!(rgb(a.screen(b)), (222, 222, 255));
+}
+
+#[test]
+fn pixel_arithmetic() {
+    let rgb = |p: Pixel| (p.r, p.g, p.b);
+    let p = Pixel::new(100, 20, 0);
+    assert_eq!(rgb(p * 3), (255, 60, 0));
+    assert_eq!(rgb(p.saturating_mul(2)), (200, 40, 0));
+    assert_eq!(rgb(p * 0.5f32), (50, 10, 0));
+    assert_eq!(rgb(p * 4.0f32), (255, 80, 0));
+    assert_eq!(rgb(p * -1.0f32), (0, 0, 0));
+    assert_eq!(rgb(p + Pixel::new(200, 1, 2)), (255, 21, 2));
+    assert_eq!(rgb(p - Pixel::new(50, 30, 1)), (50, 0, 0));
+}
+
+#[test]
+fn dithering() {
+    let mut img = ImagePPM::new(64, 16, Pixel::BLACK);
+    img.fill_linear_gradient((0.0, 0.0), (63.0, 0.0), Pixel::BLACK, Pixel::WHITE);
+    let palette = [Pixel::BLACK, Pixel::WHITE];
+    for method in [DitherMethod::None, DitherMethod::FloydSteinberg, DitherMethod::Bayer] {
+        let d = img.dither(&palette, method);
+        assert!(d.pixels().all(|p| (p.r, p.g, p.b) == (0, 0, 0) || (p.r, p.g, p.b) == (255, 255, 255)));
+        // Average brightness of each half should roughly survive
+        let white = |x0: usize| (x0..x0 + 32).flat_map(|x| (0..16).map(move |y| (x, y)))
+            .filter(|&(x, y)| d.get(x, y).unwrap().r == 255).count() as f64 / (32.0*16.0);
+        if method != DitherMethod::None {
+            assert!((white(0) - 0.25).abs() < 0.08, "{method:?}: {}", white(0));
+            assert!((white(32) - 0.75).abs() < 0.08, "{method:?}: {}", white(32));
+        }
+        d.save_to_file(out(&format!("TEST_dither_{method:?}.ppm"))).unwrap();
+    }
+
+    let pbm = ImagePBM::from_dithered(&img, DitherMethod::FloydSteinberg);
+    assert!(!*pbm.get(0, 0).unwrap());
+    assert!(*pbm.get(63, 0).unwrap());
+    pbm.save_to_file(out("TEST_dither.pbm")).unwrap();
+}
+
+#[test]
+fn quantization() {
+    let mut img = ImagePPM::new(40, 20, Pixel::RED);
+    img.fill_rect(Coord::new(20, 0), Coord::new(39, 9), Pixel::BLUE);
+    img.fill_rect(Coord::new(0, 0), Coord::new(9, 9), Pixel::new(250, 5, 0));
+    let (q, palette) = img.quantize(2);
+    assert_eq!(palette.len(), 2);
+    assert!(q.pixels().all(|p| palette.iter().any(|c| (c.r, c.g, c.b) == (p.r, p.g, p.b))));
+    let (p1, p2) = (q.get(0, 19).unwrap(), q.get(39, 0).unwrap());
+    assert!(p1.r > 200 && p1.b < 50 && p2.b > 200 && p2.r < 50);
+
+    // Never more colors than the image has
+    assert_eq!(ImagePPM::new(5, 5, Pixel::GREEN).quantize(16).1.len(), 1);
+
+    let mut img = ImagePPM::new(128, 64, Pixel::BLACK);
+    for (Coord { x, y }, p) in img.enumerate_pixels_mut() { *p = Pixel::from_hsv(x as f64 * 360.0 / 128.0, 1.0, y as f64 / 63.0); }
+    let (q, palette) = img.quantize(8);
+    assert_eq!(palette.len(), 8);
+    q.save_to_file(out("TEST_quantize.ppm")).unwrap();
+}
+
+#[test]
+fn histogram_and_stats() {
+    let mut img = ImagePPM::new(4, 2, Pixel::new(100, 0, 50));
+    *img.get_mut(0, 0).unwrap() = Pixel::new(120, 10, 50);
+    *img.get_mut(3, 1).unwrap() = Pixel::new(80, 0, 60);
+    let h = img.histogram();
+    assert_eq!(h.total(), 8);
+    assert_eq!((h.r[100], h.r[120], h.r[80]), (6, 1, 1));
+    assert_eq!((h.g[0], h.g[10]), (7, 1));
+    let (min, max) = (img.min().unwrap(), img.max().unwrap());
+    assert_eq!((min.r, min.g, min.b), (80, 0, 50));
+    assert_eq!((max.r, max.g, max.b), (120, 10, 60));
+    assert_eq!(img.mean().unwrap(), [100.0, 1.25, 51.25]);
+    assert!(ImagePPM::new(0, 0, Pixel::BLACK).mean().is_none());
+
+    img.equalize_histogram();
+    let (min, max) = (img.min().unwrap(), img.max().unwrap());
+    assert_eq!((min.r, max.r, min.g, max.g), (0, 255, 0, 255));
+
+    // Flat channels stay put
+    let mut flat = ImagePPM::new(3, 3, Pixel::new(10, 20, 30));
+    flat.equalize_histogram();
+    assert!(flat.pixels().all(|p| (p.r, p.g, p.b) == (10, 20, 30)));
+
+    let mut dull = ImagePPM::new(128, 64, Pixel::BLACK);
+    dull.fill_radial_gradient((64.0, 32.0), 70.0, Pixel::new(140, 130, 120), Pixel::new(90, 100, 110));
+    dull.equalize_histogram();
+    dull.save_to_file(out("TEST_equalized.ppm")).unwrap();
+}
+
+#[test]
+fn tone_adjustments() {
+    let rgb = |img: &ImagePPM| { let p = img.get(0, 0).unwrap(); (p.r, p.g, p.b) };
+    let mut img = ImagePPM::new(2, 2, Pixel::new(10, 128, 250));
+    img.adjust_brightness(20);
+    assert_eq!(rgb(&img), (30, 148, 255));
+    img.adjust_brightness(-40);
+    assert_eq!(rgb(&img), (0, 108, 215));
+
+    let mut img = ImagePPM::new(2, 2, Pixel::new(100, 128, 200));
+    img.adjust_contrast(2.0);
+    assert_eq!(rgb(&img), (72, 128, 255));
+    img.adjust_contrast(0.0);
+    assert_eq!(rgb(&img), (128, 128, 128));
+
+    let mut img = ImagePPM::new(2, 2, Pixel::new(0, 64, 255));
+    img.apply_gamma(2.0);
+    assert_eq!(rgb(&img), (0, 128, 255));
+    img.apply_gamma(0.5);
+    assert_eq!(rgb(&img), (0, 64, 255));
+}
+
+#[test]
+fn whole_image_filters() {
+    let rgb = |img: &ImagePPM| { let p = img.get(0, 0).unwrap(); (p.r, p.g, p.b) };
+    let mut img = ImagePPM::new(2, 2, Pixel::new(255, 0, 0));
+    img.to_grayscale();
+    assert_eq!(rgb(&img), (76, 76, 76));
+    img.invert();
+    assert_eq!(rgb(&img), (179, 179, 179));
+
+    let mut img = ImagePPM::new(2, 2, Pixel::new(100, 100, 100));
+    img.sepia();
+    assert_eq!(rgb(&img), (135, 120, 94));
+    let mut white = ImagePPM::new(1, 1, Pixel::WHITE);
+    white.sepia();
+    assert_eq!(rgb(&white), (255, 255, 239));
+
+    let mut img = ImagePPM::new(128, 64, Pixel::BLACK);
+    for (Coord { x, y }, p) in img.enumerate_pixels_mut() { *p = Pixel::from_hsv(x as f64 * 360.0 / 128.0, 1.0, y as f64 / 63.0); }
+    img.sepia();
+    img.save_to_file(out("TEST_sepia.ppm")).unwrap();
+}
+
+#[test]
+fn frame_sequence() {
+    let dir = out("TEST_frames");
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut seq = FrameSequence::new(&dir).unwrap();
+    let mut frame = ImagePPM::new(16, 16, Pixel::BLACK);
+    for _ in 0..3 {
+        let path = seq.render(&mut frame, |img, n| { img.put(n as isize, n as isize, Pixel::WHITE); }).unwrap();
+        assert!(path.exists());
+    }
+    assert_eq!(seq.next_frame(), 4);
+    let last = ImagePPM::load_from_file(format!("{dir}/frame_0003.ppm")).unwrap();
+    // The buffer is reused, so earlier frames' pixels are still there
+    assert_eq!(last.pixels().filter(|p| p.r == 255).count(), 3);
+
+    let mut gray = FrameSequence::new(format!("{dir}/gray")).unwrap().with_prefix("g").with_digits(2).starting_at(9).with_binary(false);
+    gray.write(&ImagePGM::new(2, 2, 7)).unwrap();
+    let p = gray.write(&ImagePGM::new(2, 2, 7)).unwrap();
+    assert!(p.ends_with("g10.pgm"));
+    assert!(std::fs::read_to_string(format!("{dir}/gray/g09.pgm")).unwrap().starts_with("P2"));
+}
+
+/// Just enough of a GIF decoder to check `AnimatedGif`: returns every frame as palette colors
+fn decode_gif(data: &[u8]) -> Vec<Vec<(u8, u8, u8)>> {
+    assert_eq!(&data[..6], b"GIF89a");
+    let mut pos = 13;
+    let mut frames = vec![];
+    loop {
+        match data[pos] {
+            0x3B => return frames,
+            0x21 => {
+                pos += 2;
+                while data[pos] != 0 { pos += data[pos] as usize + 1; }
+                pos += 1;
+            },
+            0x2C => {
+                let flags = data[pos + 9];
+                let table: Vec<_> = data[pos + 10..pos + 10 + 3*(2 << (flags & 7))].chunks(3).map(|c| (c[0], c[1], c[2])).collect();
+                pos += 10 + table.len()*3;
+                let min_size = data[pos] as u32;
+                pos += 1;
+                let mut bytes = vec![];
+                while data[pos] != 0 { bytes.extend_from_slice(&data[pos + 1..pos + 1 + data[pos] as usize]); pos += data[pos] as usize + 1; }
+                pos += 1;
+
+                let (clear, end) = (1 << min_size, (1 << min_size) + 1);
+                let mut dict: Vec<Vec<u8>> = vec![];
+                let mut size = min_size + 1;
+                let (mut bit, mut prev): (usize, Option<Vec<u8>>) = (0, None);
+                let mut indices = vec![];
+                loop {
+                    let code = (0..size as usize).fold(0usize, |c, i| c | ((bytes[(bit + i)/8] >> ((bit + i)%8) & 1) as usize) << i);
+                    bit += size as usize;
+                    if code == clear {
+                        dict = (0..clear).map(|i| vec![i as u8]).chain([vec![], vec![]]).collect();
+                        size = min_size + 1;
+                        prev = None;
+                        continue;
+                    }
+                    if code == end { break; }
+                    let entry = match (dict.get(code), &prev) {
+                        (Some(e), _) => e.clone(),
+                        (None, Some(p)) => { let mut e = p.clone(); e.push(p[0]); e },
+                        (None, None) => panic!("bad code"),
+                    };
+                    if let Some(p) = prev {
+                        let mut e = p;
+                        e.push(entry[0]);
+                        dict.push(e);
+                        if dict.len() == 1 << size && size < 12 { size += 1; }
+                    }
+                    indices.extend_from_slice(&entry);
+                    prev = Some(entry);
+                }
+                frames.push(indices.iter().map(|&i| table[i as usize]).collect());
+            },
+            b => panic!("unexpected block {b:#x}"),
+        }
+    }
+}
+
+#[test]
+fn animated_gif() {
+    let mut frames = vec![];
+    let mut gif = AnimatedGif::new(vec![], 40, 30, 5).unwrap();
+    let mut img = ImagePPM::new(40, 30, Pixel::BLACK);
+    for i in 0..4 {
+        img.fill_rect(Coord::new(5*i, 3*i), Coord::new(5*i + 9, 3*i + 4), Pixel::new(60*i as u8, 255, 40));
+        gif.add_frame(&img).unwrap();
+        frames.push(img.atoms().iter().map(|p| (p.r, p.g, p.b)).collect::<Vec<_>>());
+    }
+    assert!(gif.add_frame(&ImagePPM::new(3, 3, Pixel::BLACK)).is_err());
+    let data = gif.finish().unwrap();
+    assert_eq!(*data.last().unwrap(), 0x3B);
+    let decoded = decode_gif(&data);
+    assert_eq!(decoded.len(), 4);
+    // Storage order is top row first, same as GIF
+    assert_eq!(decoded, frames);
+
+    // Lots of colors (quantized) and enough data to fill up the LZW table
+    let mut gif = AnimatedGif::create(out("TEST_anim.gif"), 128, 96, 4).unwrap();
+    for i in 0..8 {
+        let mut img = ImagePPM::new(128, 96, Pixel::BLACK);
+        for (Coord { x, y }, p) in img.enumerate_pixels_mut() { *p = Pixel::from_hsv((x + 10*i) as f64 * 3.0, 1.0, y as f64 / 95.0); }
+        gif.add_frame(&img).unwrap();
+    }
+    gif.finish().unwrap();
+    assert_eq!(decode_gif(&std::fs::read(out("TEST_anim.gif")).unwrap()).len(), 8);
+}
+
+#[test]
+fn video_streams() {
+    let mut img = ImagePPM::new(3, 2, Pixel::BLACK);
+    *img.get_mut(0, 1).unwrap() = Pixel::WHITE;
+    *img.get_mut(2, 0).unwrap() = Pixel::RED;
+
+    let mut raw = vec![];
+    img.write_raw_rgb24(&mut raw).unwrap();
+    assert_eq!(raw.len(), 18);
+    assert_eq!(&raw[..3], &[255, 255, 255]);
+    assert_eq!(&raw[15..], &[255, 0, 0]);
+
+    let mut y4m = Y4mWriter::new(vec![], 3, 2, 25).unwrap();
+    y4m.write_frame(&img).unwrap();
+    y4m.write_frame(&img).unwrap();
+    assert!(y4m.write_frame(&ImagePPM::new(2, 2, Pixel::BLACK)).is_err());
+    let data = y4m.finish().unwrap();
+    let header = b"YUV4MPEG2 W3 H2 F25:1 Ip A1:1 C444\n";
+    assert_eq!(&data[..header.len()], header);
+    let frame = &data[header.len()..];
+    assert_eq!(frame.len(), 2*(6 + 18));
+    assert_eq!(&frame[..6], b"FRAME\n");
+    // Y plane: white is 235, black is 16; U and V are neutral (128) for grays
+    assert_eq!(&frame[6..12], &[235, 16, 16, 16, 16, 81]);
+    assert_eq!((frame[12], frame[18]), (128, 128));
+    assert_eq!((frame[17], frame[23]), (90, 240));
+}
+
+#[test]
+fn pam_gray_alpha() {
+    let mut img = ImageGrayAlpha::new(3, 2, PixelGrayAlpha::TRANSPARENT);
+    *img.get_mut(0, 1).unwrap() = PixelGrayAlpha::new(200, 255);
+    *img.get_mut(2, 0).unwrap() = PixelGrayAlpha::new(100, 128);
+    let mut bytes = vec![];
+    img.write_binary_to(&mut bytes).unwrap();
+    let header = b"P7\nWIDTH 3\nHEIGHT 2\nDEPTH 2\nMAXVAL 255\nTUPLTYPE GRAYSCALE_ALPHA\nENDHDR\n";
+    assert_eq!(&bytes[..header.len()], header);
+    assert_eq!(&bytes[header.len()..], &[200, 255, 0, 0, 0, 0, 0, 0, 0, 0, 100, 128]);
+    let mut plain = vec![];
+    img.write_to(&mut plain).unwrap();
+    assert_eq!(plain, bytes);
+
+    let flat = img.flatten(50);
+    assert_eq!((*flat.get(0, 1).unwrap(), *flat.get(1, 1).unwrap(), *flat.get(2, 0).unwrap()), (200, 50, 75));
+    img.save_to_file(out("TEST_gray_alpha.pam")).unwrap();
+
+    // Blending composites like RGBA does
+    let p = PixelGrayAlpha::new(0, 255).blend(PixelGrayAlpha::new(255, 255), 0.5);
+    assert_eq!((p.v, p.a), (128, 255));
+}
+
+#[cfg(feature = "png")]
+#[test]
+fn png_export() {
+    let mut img = ImagePPM::new(97, 61, Pixel::BLACK);
+    for (Coord { x, y }, p) in img.enumerate_pixels_mut() { *p = Pixel::from_hsv(x as f64 * 3.7, 1.0, y as f64 / 60.0); }
+    img.fill_rect(Coord::new(10, 10), Coord::new(40, 30), Pixel::WHITE);
+    img.save_as_png(out("TEST_export.png")).unwrap();
+    img.save_to_file_binary(out("TEST_export_png_source.ppm")).unwrap();
+
+    let mut bytes = vec![];
+    img.write_png_to(&mut bytes).unwrap();
+    assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+    // IHDR: 97x61, 8 bits, RGB
+    assert_eq!(&bytes[8..16], b"\0\0\0\x0dIHDR");
+    assert_eq!(&bytes[16..29], &[0, 0, 0, 97, 0, 0, 0, 61, 8, 2, 0, 0, 0]);
+    assert_eq!(&bytes[bytes.len() - 12..], b"\0\0\0\0IEND\xae\x42\x60\x82");
+    assert!(bytes.len() < 97*61*3);
+    // Sides that don't fit in the header are an error rather than being cut short
+    let wide = ImagePPM::new(1 << 32, 0, Pixel::BLACK);
+    assert!(matches!(wide.write_png_to(&mut vec![]), Err(PpmError::InvalidDimensions(_))));
+
+    let mut rgba = ImageRGBA::new(4, 4, PixelRgba::TRANSPARENT);
+    rgba.fill_rect(Coord::new(1, 1), Coord::new(2, 2), PixelRgba::new(255, 0, 0, 128));
+    rgba.save_as_png(out("TEST_export_rgba.png")).unwrap();
+    ImagePGM::new(300, 2, 40).save_as_png(out("TEST_export_gray.png")).unwrap();
+    ImagePPM16::new(3, 3, Pixel16::new(65535, 0, 1000)).save_as_png(out("TEST_export_16.png")).unwrap();
+}
+
+#[test]
+fn bmp_export() {
+    let mut img = ImagePPM::new(3, 2, Pixel::BLACK);
+    *img.get_mut(0, 0).unwrap() = Pixel::new(1, 2, 3);
+    *img.get_mut(2, 1).unwrap() = Pixel::new(4, 5, 6);
+    let mut bytes = vec![];
+    img.write_bmp_to(&mut bytes).unwrap();
+    // 3 pixels are 9 bytes, padded to 12 per row
+    assert_eq!(bytes.len(), 54 + 2*12);
+    assert_eq!(&bytes[..2], b"BM");
+    assert_eq!(&bytes[2..6], &78u32.to_le_bytes());
+    assert_eq!(&bytes[18..26], &[3, 0, 0, 0, 2, 0, 0, 0]);
+    assert_eq!(&bytes[28..30], &24u16.to_le_bytes());
+    // Bottom row first, BGR
+    assert_eq!(&bytes[54..66], &[3, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(&bytes[66..78], &[0, 0, 0, 0, 0, 0, 6, 5, 4, 0, 0, 0]);
+
+    let mut img = ImagePPM::new(101, 50, Pixel::BLACK);
+    img.fill_linear_gradient((0.0, 0.0), (100.0, 49.0), Pixel::RED, Pixel::BLUE);
+    img.save_as_bmp(out("TEST_export.bmp")).unwrap();
+}
+
+#[cfg(feature = "qoi")]
+#[test]
+fn qoi_roundtrip() {
+    let rgb = |p: &Pixel| (p.r, p.g, p.b);
+    let mut img = ImagePPM::new(90, 70, Pixel::BLACK);
+    for (Coord { x, y }, p) in img.enumerate_pixels_mut() { *p = Pixel::from_hsv(x as f64 * 4.0, 0.8, y as f64 / 69.0); }
+    img.fill_rect(Coord::new(10, 10), Coord::new(80, 20), Pixel::new(12, 200, 99));
+    img.draw_line((0.0, 0.0), (89.0, 69.0), Pixel::WHITE);
+    let mut bytes = vec![];
+    img.write_qoi_to(&mut bytes).unwrap();
+    assert_eq!(&bytes[..14], &[b'q', b'o', b'i', b'f', 0, 0, 0, 90, 0, 0, 0, 70, 3, 0]);
+    assert_eq!(&bytes[bytes.len() - 8..], &[0, 0, 0, 0, 0, 0, 0, 1]);
+    assert!(bytes.len() < 90*70*3);
+    let back = ImagePPM::from_qoi_reader(&bytes[..]).unwrap();
+    assert!(img.pixels().zip(back.pixels()).all(|(a, b)| rgb(a) == rgb(b)));
+    img.save_as_qoi(out("TEST_export.qoi")).unwrap();
+    assert_eq!(ImagePPM::load_qoi(out("TEST_export.qoi")).unwrap().width(), 90);
+
+    // Runs, alpha changes and the spec's exact ops on a tiny image
+    let mut rgba = ImageRGBA::new(70, 1, PixelRgba::new(0, 0, 0, 255));
+    *rgba.get_mut(69, 0).unwrap() = PixelRgba::new(1, 0, 255, 128);
+    let mut bytes = vec![];
+    rgba.write_qoi_to(&mut bytes).unwrap();
+    assert_eq!(&bytes[14..bytes.len() - 8], &[0xFD, 0xC6, 0xFF, 1, 0, 255, 128]);
+    let back = ImageRGBA::from_qoi_reader(&bytes[..]).unwrap();
+    let p = back.get(69, 0).unwrap();
+    assert_eq!((p.r, p.g, p.b, p.a), (1, 0, 255, 128));
+    assert!(ImagePPM::from_qoi_reader(&b"qoif"[..]).is_err());
+}
+
+#[test]
+fn farbfeld() {
+    let mut img = ImageRGBA::new(2, 1, PixelRgba::TRANSPARENT);
+    *img.get_mut(0, 0).unwrap() = PixelRgba::new(255, 1, 0, 128);
+    let mut bytes = vec![];
+    img.write_farbfeld_to(&mut bytes).unwrap();
+    assert_eq!(&bytes[..16], b"farbfeld\0\0\0\x02\0\0\0\x01");
+    assert_eq!(&bytes[16..24], &[255, 255, 1, 1, 0, 0, 128, 128]);
+    assert_eq!(bytes.len(), 16 + 2*8);
+    let back = ImageRGBA::from_farbfeld_reader(&bytes[..]).unwrap();
+    let p = back.get(0, 0).unwrap();
+    assert_eq!((p.r, p.g, p.b, p.a), (255, 1, 0, 128));
+
+    let deep = ImagePPM16::new(3, 2, Pixel16::new(1000, 0, 0)).with_maxval(1000);
+    let mut bytes = vec![];
+    deep.write_farbfeld_to(&mut bytes).unwrap();
+    let back = ImagePPM16::from_farbfeld_reader(&bytes[..]).unwrap();
+    assert_eq!((back.maxval(), back.get(2, 1).unwrap().r), (65535, 65535));
+
+    let mut img = ImagePPM::new(64, 32, Pixel::BLACK);
+    img.fill_radial_gradient((32.0, 16.0), 30.0, Pixel::PURPLE, Pixel::BLACK);
+    img.save_as_farbfeld(out("TEST_export.ff")).unwrap();
+    let back = ImagePPM::load_farbfeld(out("TEST_export.ff")).unwrap();
+    assert!(img.pixels().zip(back.pixels()).all(|(a, b)| (a.r, a.g, a.b) == (b.r, b.g, b.b)));
+
+    assert!(ImagePPM::from_farbfeld_reader(&b"farbfeld\0\0\0\x01\0\0\0\x01\0\0"[..]).is_err());
+}
+
+#[test]
+fn rgb_byte_buffers() {
+    let mut img = ImagePPM::new(2, 2, Pixel::BLACK);
+    *img.get_mut(0, 1).unwrap() = Pixel::new(1, 2, 3);
+    *img.get_mut(1, 0).unwrap() = Pixel::new(4, 5, 6);
+    let bytes = img.to_rgb_bytes();
+    // Top row first, like every other library expects
+    assert_eq!(bytes, vec![1, 2, 3, 0, 0, 0, 0, 0, 0, 4, 5, 6]);
+    let back = ImagePPM::from_rgb_bytes(2, 2, &bytes).unwrap();
+    let p = back.get(1, 0).unwrap();
+    assert_eq!((p.r, p.g, p.b), (4, 5, 6));
+    // The first bytes are the top left pixel
+    let p = back.get(0, 1).unwrap();
+    assert_eq!((p.r, p.g, p.b), (1, 2, 3));
+    assert!(ImagePPM::from_rgb_bytes(2, 2, &bytes[1..]).is_none());
+    assert!(ImagePPM::from_rgb_bytes(usize::MAX, 2, &bytes).is_none());
+}
+
+#[test]
+fn parallel_fill() {
+    let f = |Coord { x, y }: Coord| Pixel::new(x as u8, y as u8, (x*y % 256) as u8);
+    let img = ImagePPM::par_from_fn(37, 53, f);
+    assert_eq!((img.width(), img.height()), (37, 53));
+    assert!(img.enumerate_pixels().all(|(c, p)| { let e = f(c); (p.r, p.g, p.b) == (e.r, e.g, e.b) }));
+
+    let mut gray = ImagePGM::par_from_fn(5, 1, |c| c.x as u8 * 10);
+    gray.par_apply_pixels(|v| v + 1);
+    assert_eq!(gray.pixels().copied().collect::<Vec<_>>(), vec![1, 11, 21, 31, 41]);
+    assert_eq!(ImagePBM::par_from_fn(0, 0, |_| true).atoms().len(), 0);
+
+    let mandel = ImagePPM::par_from_fn(300, 200, |Coord { x, y }| {
+        let c = (x as f64 / 100.0 - 2.0, y as f64 / 100.0 - 1.0);
+        let mut z = (0.0, 0.0);
+        let n = (0..64).take_while(|_| { z = (z.0*z.0 - z.1*z.1 + c.0, 2.0*z.0*z.1 + c.1); z.0*z.0 + z.1*z.1 < 4.0 }).count();
+        Gradient::viridis().sample(n as f64 / 64.0)
+    });
+    mandel.save_to_file_binary(out("TEST_par_mandelbrot.ppm")).unwrap();
+}
+
+#[test]
+fn plain_number_formatting() {
+    let mut img = ImagePPM::new(2, 1, Pixel::new(0, 7, 42));
+    *img.get_mut(1, 0).unwrap() = Pixel::new(100, 255, 9);
+    let mut s = vec![];
+    img.write_to(&mut s).unwrap();
+    assert_eq!(String::from_utf8(s).unwrap(), "P3\n2 1\n255\n  0   7  42\n100 255   9\n");
+
+    let mut s = vec![];
+    ImagePGM::new(1, 2, 5).write_to(&mut s).unwrap();
+    assert_eq!(String::from_utf8(s).unwrap(), "P2\n1 2\n255\n  5\n  5\n");
+
+    let mut s = vec![];
+    ImagePPM16::new(1, 1, Pixel16::new(65535, 0, 1234)).write_to(&mut s).unwrap();
+    assert_eq!(String::from_utf8(s).unwrap(), "P3\n1 1\n65535\n65535 0 1234\n");
+}
+
+#[test]
+fn error_type() {
+    let img = ImagePPM::new(10, 5, Pixel::BLACK);
+    assert!(img.try_crop(Coord::new(2, 1), 8, 4).is_ok());
+    match img.try_crop(Coord::new(2, 1), 9, 4) {
+        Err(PpmError::OutOfBounds { coord, width: 10, height: 5 }) => assert_eq!((coord.x, coord.y), (10, 4)),
+        other => panic!("unexpected {other:?}"),
+    }
+    assert!(matches!(img.try_crop(Coord::new(0, 0), 0, 3), Err(PpmError::InvalidDimensions(_))));
+    assert!(matches!(ImagePPM::try_new(usize::MAX, 2, Pixel::BLACK), Err(PpmError::InvalidDimensions(_))));
+    assert_eq!(ImagePGM::try_new(3, 4, 0).unwrap().atoms().len(), 12);
+
+    let err = ImagePPM::from_reader(&b"P9\n1 1\n255\n"[..]).unwrap_err();
+    assert!(matches!(err, PpmError::Parse(_)));
+    assert!(err.to_string().starts_with("parse error"));
+    // Sizes whose sample count overflows are rejected instead of panicking
+    let err = ImagePPM::from_reader(&b"P3\n18446744073709551615 2\n255\n1 2 3"[..]).unwrap_err();
+    assert!(matches!(err, PpmError::Parse(_)));
+    assert!(matches!(ImagePPM::from_reader(&b"P6\n6148914691236517206 1\n255\n\0\0\0"[..]), Err(PpmError::Parse(_))));
+    let err = ImagePPM::load_from_file("test_outputs/there_is_no_such_file.ppm").unwrap_err();
+    assert!(matches!(err, PpmError::Io(_)));
+    assert_eq!(std::io::Error::from(err).kind(), std::io::ErrorKind::NotFound);
+    assert!(matches!(AnimatedGif::new(vec![], 70000, 1, 1), Err(PpmError::InvalidDimensions(_))));
+}
+
+#[test]
+fn save_borrows() {
+    let mut img = ImagePPM::new(8, 8, Pixel::BLACK);
+    for i in 0..3 {
+        img.put(i, i, Pixel::WHITE);
+        img.save_to_file(out(&format!("TEST_save_borrows_{i}.ppm"))).unwrap();
+        img.save_to_file_binary(out(&format!("TEST_save_borrows_{i}_bin.ppm"))).unwrap();
+    }
+    let last = ImagePPM::load_from_file(out("TEST_save_borrows_2_bin.ppm")).unwrap();
+    assert_eq!(last.pixels().filter(|p| p.r == 255).count(), 3);
+}
 
+#[test]
+fn image_builder() {
+    let img = ImagePPM::builder().width(4).height(3).background(Pixel::RED).build().unwrap();
+    assert_eq!((img.width(), img.height(), img.get(3, 2).unwrap().r), (4, 3, 255));
+    let blank = ImageRGBA::builder().size(2, 2).build().unwrap();
+    assert_eq!(blank.get(1, 1).unwrap().a, 0);
+    let deep = ImagePPM16::builder().size(1, 1).maxval(1023).build().unwrap();
+    assert_eq!(deep.maxval(), 1023);
+
+    assert!(matches!(ImagePGM::builder().width(5).build(), Err(PpmError::InvalidDimensions(_))));
+    assert!(matches!(ImagePBM::builder().size(usize::MAX, usize::MAX).build(), Err(PpmError::InvalidDimensions(_))));
+    assert!(matches!(ImagePPM16::builder().size(1, 1).maxval(0).build(), Err(PpmError::InvalidDimensions(_))));
+}
+
+#[test]
+fn top_left_origin() {
+    let mut img = TopLeft(ImagePGM::new(3, 2, 0));
+    assert_eq!(img.origin(), Origin::TopLeft);
+    img.put(0, 0, 10);
+    img[Coord::new(2, 1)] = 20;
+    // Storage is top row first, so it's the same as the logical order
+    assert_eq!(img.atoms(), &vec![10, 0, 0, 0, 0, 20]);
+    assert_eq!(img.pixels().copied().collect::<Vec<_>>(), vec![10, 0, 0, 0, 0, 20]);
+
+    // Same pixel, seen from the usual bottom left origin
+    let plain = img.clone().into_inner();
+    assert_eq!(plain.get(0, 1), Some(&10));
+
+    // Transforms keep the wrapper, and with it the origin
+    let flipped = img.flip_vertical();
+    assert_eq!(flipped.get(0, 1), Some(&10));
+
+    // Text reads upright either way: the top row of an 'I' has ink in both
+    let mut up = ImagePGM::new(8, 8, 0);
+    up.draw_text(Coord::new(0, 0), "I", 1, 255);
+    let mut down = TopLeft(ImagePGM::new(8, 8, 0));
+    down.draw_text(Coord::new(0, 0), "I", 1, 255);
+    assert_eq!(up.atoms(), down.atoms());
+
+    let built = ImagePPM::builder().size(2, 2).build_top_left().unwrap();
+    assert_eq!(built.origin(), Origin::TopLeft);
+}
+
+#[test]
+fn row_access() {
+    let mut img = ImagePGM::new(3, 3, 0);
+    img.row_mut(0).unwrap().copy_from_slice(&[1, 2, 3]);
+    assert_eq!(img.get(2, 0), Some(&3));
+    assert_eq!(img.row(0), Some(&[1, 2, 3][..]));
+    assert_eq!(img.row(3), None);
+    for (y, row) in img.rows_mut().enumerate() { row[1] = 10 + y as u8; }
+    assert_eq!(img.rows().map(|r| r[1]).collect::<Vec<_>>(), vec![10, 11, 12]);
+    assert_eq!(img.get(1, 2), Some(&12));
+
+    // Rows follow the origin too
+    let mut top = TopLeft(img);
+    assert_eq!(top.row(0), Some(&[0, 12, 0][..]));
+    top.row_mut(2).unwrap()[0] = 7;
+    assert_eq!(top.rows().last().unwrap(), &[7, 10, 3]);
+}
+
+#[test]
+fn region_views() {
+    let mut img = ImagePGM::new(6, 4, 0);
+    {
+        let mut v = img.view_mut(Coord::new(1, 1), 3, 2).unwrap();
+        assert_eq!((v.width(), v.height()), (3, 2));
+        // Drawing gets clipped to the region, not just to the image
+        v.fill_rect(Coord::new(0, 0), Coord::new(10, 10), 9);
+        v.put(0, 0, 1);
+        Canvas::new(&mut v).draw_line((2.0, 1.0), (2.0, 1.0), 5);
+    }
+    assert_eq!(img.get(1, 1), Some(&1));
+    assert_eq!(img.get(3, 2), Some(&5));
+    assert_eq!(img.get(4, 1), Some(&0));
+    assert_eq!(img.get(1, 3), Some(&0));
+    assert_eq!(img.pixels().filter(|&&p| p != 0).count(), 6);
+
+    let v = img.view(Coord::new(1, 1), 3, 2).unwrap();
+    assert_eq!(v.get(2, 1), Some(&5));
+    assert_eq!(v.row(0), Some(&[1, 9, 9][..]));
+    assert_eq!(v.to_image().atoms(), &vec![9, 9, 5, 1, 9, 9]);
+    assert!(img.view(Coord::new(4, 0), 3, 1).is_none());
+    assert!(img.view(Coord::new(0, 0), 0, 1).is_none());
+
+    // With a top left origin the corner is the top left one
+    let mut top = TopLeft(ImagePGM::new(2, 3, 0));
+    let mut v: ImageViewMut<u8> = top.view_mut(Coord::new(0, 1), 2, 2).unwrap();
+    v.put(0, 0, 7);
+    assert_eq!(top.get(0, 1), Some(&7));
+}
+
+#[test]
+fn tiles_across_threads() {
+    let mut img = ImagePGM::new(5, 7, 0);
+    let mut tiles = img.split_into_tiles(3);
+    assert_eq!(tiles.iter().map(|t| (t.corner().y, t.height())).collect::<Vec<_>>(), vec![(0, 1), (1, 3), (4, 3)]);
+    std::thread::scope(|s| {
+        for tile in &mut tiles {
+            s.spawn(move || {
+                let y0 = tile.corner().y;
+                for y in 0..tile.height() { for x in 0..tile.width() { tile.put(x as isize, y as isize, (y0 + y) as u8 * 10 + x as u8); } }
+            });
+        }
+    });
+    drop(tiles);
+    assert!(img.enumerate_pixels().all(|(c, &p)| p == c.y as u8 * 10 + c.x as u8));
+
+    assert_eq!(img.split_into_tiles(20).len(), 7);
+    let mut top = TopLeft(ImagePGM::new(2, 4, 0));
+    assert_eq!(top.split_into_tiles(2).iter().map(|t| t.corner().y).collect::<Vec<_>>(), vec![0, 2]);
+}
+
+#[test]
+fn turtle_square() {
+    let mut img = ImagePPM::new(12, 12, Pixel::BLACK);
+    let mut t = Turtle::new(&mut img, (1.0, 1.0), Pixel::WHITE);
+    for _ in 0..4 {
+        t.forward(9.0);
+        t.left(90.0);
+    }
+    let end = t.position();
+    assert!((end.x - 1.0).abs() < 1e-9 && (end.y - 1.0).abs() < 1e-9);
+
+    t.push();
+    t.pen_up();
+    t.forward(5.0);
+    t.pop();
+    assert!(t.is_pen_down());
+    assert_eq!(t.position().x, end.x);
+    t.set_color(Pixel::RED);
+    t.jump_to((5.0, 5.0));
+    t.right(90.0);
+    t.forward(0.0);
+    assert_eq!(t.heading(), 270.0);
+
+    for c in [(1, 1), (10, 1), (10, 10), (1, 10), (5, 1), (1, 6)] { assert_eq!(img.get(c.0, c.1).unwrap().r, 255, "{c:?}"); }
+    assert_eq!(img.get(5, 5).map(|p| (p.r, p.g)), Some((255, 0)));
+    assert_eq!(img.get(5, 6).unwrap().r, 0);
+}
+
+#[test]
+fn paths() {
+    // A square with a square hole
+    let ring = Path::new()
+        .move_to((1.0, 1.0)).line_to((9.0, 1.0)).line_to((9.0, 9.0)).line_to((1.0, 9.0)).close()
+        .move_to((3.0, 3.0)).line_to((7.0, 3.0)).line_to((7.0, 7.0)).line_to((3.0, 7.0)).close();
+    assert_eq!(ring.contours().count(), 2);
+    let mut img = ImagePGM::new(10, 10, 0);
+    img.fill_path(&ring, 255);
+    assert_eq!((img[(2, 2)], img[(5, 5)], img[(8, 8)], img[(0, 0)]), (255, 0, 255, 0));
+
+    // Same outline as a polygon
+    let mut poly = ImagePGM::new(10, 10, 0);
+    poly.fill_polygon(&[(1.0, 1.0), (9.0, 1.0), (9.0, 9.0), (1.0, 9.0)], 255);
+    let mut square = ImagePGM::new(10, 10, 0);
+    square.fill_path(&Path::new().move_to((1.0, 1.0)).line_to((9.0, 1.0)).line_to((9.0, 9.0)).line_to((1.0, 9.0)), 255);
+    assert_eq!(poly.atoms(), square.atoms());
+
+    // Curves end where they're told to, and strokes are as wide as asked
+    let curve = Path::new().move_to((0.0, 10.0)).curve_to((10.0, 30.0), (30.0, -10.0), (40.0, 10.0)).quad_to((45.0, 20.0), (50.0, 10.0));
+    let (points, closed) = curve.contours().next().unwrap();
+    assert!(!closed && points.len() > 10 && points.last().unwrap().distance(CoordF::new(50.0, 10.0)) < 1e-9);
+    let mut img = ImagePGM::new(60, 30, 0);
+    img.stroke_path(&Path::new().move_to((10.0, 15.0)).line_to((50.0, 15.0)), 5.0, 255);
+    assert_eq!((13..18).map(|y| img[(30, y)]).collect::<Vec<_>>(), vec![255; 5]);
+    assert_eq!((img[(30, 12)], img[(30, 18)], img[(7, 15)]), (0, 0, 0));
+    img.stroke_path(&curve, 1.0, 100);
+    assert_eq!(img[(0, 10)], 100);
+
+    let mut c = ImagePGM::new(10, 10, 0);
+    Canvas::new(&mut c).stroke_path(&Path::new().move_to((2.0, 2.0)).line_to((2.0, 7.0)), 1.0, 9);
+    assert_eq!(c[(2, 5)], 9);
+}
+
+#[test]
+fn stroke_styles() {
+    let row = |img: &ImagePGM| (0..img.width()).map(|x| (img[(x, 1)] != 0) as u8).collect::<Vec<_>>();
+    let mut img = ImagePGM::new(20, 3, 0);
+    img.draw_line_styled((0.0, 1.0), (19.0, 1.0), StrokeStyle::dashed(3.0, 2.0), 255);
+    assert_eq!(row(&img), [1, 1, 1, 1, 0, 1, 1, 1, 1, 0, 1, 1, 1, 1, 0, 1, 1, 1, 1, 0]);
+
+    let mut img = ImagePGM::new(20, 3, 0);
+    img.draw_line_styled((0.0, 1.0), (19.0, 1.0), StrokeStyle::dotted(4.0), 255);
+    assert_eq!(row(&img), [1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+
+    // The pattern goes around corners instead of starting over (36 pixels around, a dot every 3)
+    let mut img = ImagePGM::new(12, 12, 0);
+    let square = Path::new().move_to((1.0, 1.0)).line_to((10.0, 1.0)).line_to((10.0, 10.0)).line_to((1.0, 10.0)).close();
+    img.stroke_path_styled(&square, 1.0, StrokeStyle::dotted(3.0), 255);
+    assert_eq!(img.pixels().filter(|&&p| p != 0).count(), 12);
+
+    let mut solid = ImagePGM::new(20, 3, 0);
+    solid.draw_line_styled((0.0, 1.0), (19.0, 1.0), StrokeStyle::Solid, 255);
+    assert_eq!(row(&solid), [1; 20]);
+}
+
+#[test]
+fn arcs_and_pies() {
+    use std::f64::consts::{FRAC_PI_2, PI};
+    let mut img = ImagePGM::new(21, 21, 0);
+    img.draw_arc((10.0, 10.0), 8.0, 0.0, FRAC_PI_2, 255);
+    assert_eq!((img[(18, 10)], img[(10, 18)], img[(2, 10)], img[(10, 2)]), (255, 255, 0, 0));
+
+    // A quarter of a circle, give or take its rim
+    let mut pie = ImagePGM::new(21, 21, 0);
+    pie.fill_pie((10.0, 10.0), 8.0, PI, 3.0*FRAC_PI_2, 255);
+    let area = pie.pixels().filter(|&&p| p != 0).count() as f64;
+    assert!((area - PI*64.0/4.0).abs() < 8.0, "{area}");
+    assert_eq!((pie[(6, 6)], pie[(14, 14)], pie[(14, 6)]), (255, 0, 0));
+}
+
+#[test]
+fn rounded_rects() {
+    let mut filled = ImagePGM::new(12, 10, 0);
+    filled.fill_rounded_rect(Coord::new(1, 1), 10, 8, 3.0, 255);
+    let mut outline = ImagePGM::new(12, 10, 0);
+    outline.draw_rounded_rect(Coord::new(1, 1), 10, 8, 3.0, 255);
+    for img in [&filled, &outline] {
+        // Straight sides reach the edges of the box, corners are cut off
+        assert_eq!((img[(1, 5)], img[(10, 5)], img[(5, 1)], img[(5, 8)]), (255, 255, 255, 255));
+        assert_eq!((img[(1, 1)], img[(10, 8)], img[(0, 5)], img[(11, 5)], img[(5, 9)]), (0, 0, 0, 0, 0));
+    }
+    assert_eq!((filled[(5, 5)], outline[(5, 5)]), (255, 0));
+
+    // No radius, no rounding
+    let mut sharp = ImagePGM::new(12, 10, 0);
+    sharp.fill_rounded_rect(Coord::new(1, 1), 10, 8, 0.0, 255);
+    let mut rect = ImagePGM::new(12, 10, 0);
+    rect.fill_rect(Coord::new(1, 1), Coord::new(10, 8), 255);
+    assert_eq!(sharp.atoms(), rect.atoms());
+}
+
+#[test]
+fn sprites() {
+    // 4x2 sheet: a top row of red tiles and a bottom row of blue ones, each with a magenta corner
+    let mut sheet = ImagePPM::new(4, 2, Pixel::BLUE);
+    sheet.fill_rect(Coord::new(0, 1), Coord::new(3, 1), Pixel::RED);
+    for x in [0, 2] { for y in [0, 1] { sheet[(x, y)] = Pixel::new(255, 0, 255); } }
+    let tiles = Sprite::sheet(&sheet, 2, 1, Some(Pixel::new(255, 0, 255)));
+    assert_eq!(tiles.len(), 4);
+    assert_eq!((tiles[0].image()[(1, 0)].r, tiles[3].image()[(1, 0)].b), (255, 255));
+
+    let mut img = ImagePPM::new(4, 4, Pixel::BLACK);
+    tiles[0].stamp(&mut img, ICoord::new(1, 1));
+    // The keyed pixel lets the background through
+    assert_eq!((img[(1, 1)].r, img[(2, 1)].r), (0, 255));
+    // Hanging off the edge is fine
+    Sprite::new(ImagePPM::new(3, 3, Pixel::GREEN)).stamp(&mut img, ICoord::new(-2, 2));
+    assert_eq!((img[(0, 3)].g, img[(1, 3)].g), (255, 0));
+}
+
+#[test]
+fn styled_fills() {
+    let mut img = ImagePPM::new(10, 10, Pixel::BLACK);
+    let ramp = FillStyle::Linear { from: CoordF::new(0.0, 0.0), to: CoordF::new(9.0, 0.0), gradient: Gradient::grayscale() };
+    img.fill_rect_styled(Coord::new(0, 0), Coord::new(9, 4), &ramp);
+    assert_eq!((img[(0, 0)].r, img[(9, 4)].r, img[(0, 5)].r), (0, 255, 0));
+    assert!(img[(4, 2)].r > 100 && img[(4, 2)].r < 130);
+
+    // A 2x2 checker texture repeats across the whole shape
+    let mut checker = ImagePPM::new(2, 2, Pixel::BLACK);
+    checker[(0, 0)] = Pixel::WHITE;
+    checker[(1, 1)] = Pixel::WHITE;
+    let mut img = ImagePPM::new(10, 10, Pixel::RED);
+    img.fill_polygon_styled(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)], &FillStyle::Texture(checker));
+    assert!(img.enumerate_pixels().all(|(c, p)| p.g == if (c.x + c.y) % 2 == 0 { 255 } else { 0 }));
+
+    let mut img = ImagePPM::new(11, 11, Pixel::BLACK);
+    img.fill_ellipse_styled(Coord::new(5, 5), 4, 4, &FillStyle::Radial { center: CoordF::new(5.0, 5.0), radius: 4.0, gradient: Gradient::heat() });
+    assert_eq!((img[(5, 5)].r, img[(0, 0)].r), (0, 0));
+    img.fill_path_styled(&Path::new().rounded_rect((0.0, 0.0), 2.0, 2.0, 0.0), &FillStyle::Solid(Pixel::GREEN));
+    assert_eq!(img[(1, 1)].g, 255);
+}
+
+#[test]
+fn test_patterns() {
+    let board = ImagePGM::checkerboard(6, 4, 2, 0, 255);
+    assert_eq!((board[(0, 0)], board[(1, 1)], board[(2, 0)], board[(2, 2)], board[(5, 3)]), (0, 0, 255, 0, 255));
+
+    let bars = ImagePPM::color_bars(14, 2);
+    let tops: Vec<_> = (0..7).map(|i| { let p = bars[(2*i + 1, 1)]; (p.r, p.g, p.b) }).collect();
+    assert_eq!(tops, [(255, 255, 255), (255, 255, 0), (0, 255, 255), (0, 255, 0), (255, 0, 255), (255, 0, 0), (0, 0, 255)]);
+
+    let ramps = ImagePPM::gradient_test(256, 8);
+    let at = |x, y| { let p = ramps[(x, y)]; (p.r, p.g, p.b) };
+    assert_eq!((at(0, 7), at(255, 7), at(128, 5), at(255, 3), at(255, 0)), ((0, 0, 0), (255, 255, 255), (128, 0, 0), (0, 255, 0), (0, 0, 255)));
+
+    let f = ImagePGM::from_fn(3, 2, |c| (c.x + 10*c.y) as u8);
+    assert_eq!((f[(2, 0)], f[(1, 1)]), (2, 11));
+}
+
+#[test]
+fn noise() {
+    let (a, b) = (Noise::new(7), Noise::new(8));
+    let samples: Vec<CoordF> = (0..400).map(|i| CoordF::new(i as f64 * 0.137, i as f64 * 0.071)).collect();
+    for f in [Noise::perlin, Noise::simplex] {
+        let vals: Vec<f64> = samples.iter().map(|&p| f(&a, p)).collect();
+        assert!(vals.iter().all(|v| v.abs() <= 1.0));
+        assert!(vals.iter().any(|&v| v > 0.2) && vals.iter().any(|&v| v < -0.2));
+        // Smooth: close points get close values
+        assert!(samples.iter().all(|&p| (f(&a, p) - f(&a, p + CoordF::new(0.01, 0.0))).abs() < 0.1));
+    }
+    // Zero at lattice points, and seeded
+    assert_eq!(a.perlin((3.0, 5.0)), 0.0);
+    assert!(samples.iter().any(|&p| a.perlin(p) != b.perlin(p)));
+    assert_eq!(Noise::new(7).fbm((1.3, 2.7), 4), a.fbm((1.3, 2.7), 4));
+
+    let img = ImagePPM::from_noise(32, 16, 8.0, 7, &Gradient::grayscale());
+    assert_eq!(img.to_rgb_bytes(), ImagePPM::from_noise(32, 16, 8.0, 7, &Gradient::grayscale()).to_rgb_bytes());
+    img.save_to_file_binary(out("TEST_noise.ppm")).unwrap();
+}
+
+#[test]
+fn scalar_fields() {
+    let field = [10.0, 20.0, 30.0, f64::NAN, 15.0, 30.0];
+    let img = ImagePPM::from_scalar_field(3, 2, &field, &Gradient::grayscale()).unwrap();
+    assert_eq!([(0, 0), (1, 0), (2, 0), (0, 1), (1, 1)].map(|c| img[c].r), [0, 128, 255, 0, 64]);
+
+    let fixed = ImagePPM::from_scalar_field_in(3, 2, &field, 0.0, 20.0, &Gradient::grayscale()).unwrap();
+    assert_eq!([(0, 0), (1, 0), (2, 0)].map(|c| fixed[c].r), [128, 255, 255]);
+
+    assert!(matches!(ImagePPM::from_scalar_field(2, 2, &field, &Gradient::grayscale()), Err(PpmError::InvalidDimensions(_))));
+    let flat = ImagePPM::from_scalar_field(2, 1, &[3.0, 3.0], &Gradient::heat()).unwrap();
+    assert_eq!(flat[(1, 0)].r, 0);
+}
+
+#[test]
+fn fractals() {
+    use ppmitzador::fractal::{julia, mandelbrot, Viewport};
+    let img = mandelbrot(70, 50, Viewport::MANDELBROT, 100, &Gradient::viridis());
+    let rgb = |p: Pixel| (p.r, p.g, p.b);
+    // The origin is in the set, the corners are far out of it
+    let mb = Viewport::MANDELBROT;
+    let at = |x: f64, y: f64| (((x - mb.min.x) / 3.5 * 70.0) as usize, ((y - mb.min.y) / 2.5 * 50.0) as usize);
+    assert_eq!(rgb(img[at(0.0, 0.0)]), (0, 0, 0));
+    assert_ne!(rgb(img[(0, 0)]), (0, 0, 0));
+    // Symmetric across the real axis
+    assert!((0..70).all(|x| rgb(img[(x, 10)]) == rgb(img[(x, 39)])));
+    img.save_to_file_binary(out("TEST_mandelbrot.ppm")).unwrap();
+
+    let view = Viewport::centered((0.0, 0.0), 3.0, 40, 20);
+    assert_eq!((view.max.x - view.min.x, view.max.y - view.min.y), (3.0, 1.5));
+    let j = julia(40, 20, view, (-0.8, 0.156), 80, &Gradient::heat());
+    // Julia sets are symmetric around the center
+    assert!((0..40).all(|x| rgb(j[(x, 3)]) == rgb(j[(39 - x, 16)])));
+}
+
+#[test]
+fn function_plots() {
+    use ppmitzador::plot::Plot;
+    let mut img = ImagePGM::new(101, 51, 0);
+    let mut plot = Plot::new(&mut img, -5.0..5.0, -1.0..4.0);
+    assert_eq!(plot.to_pixel((0.0, 0.0)), CoordF::new(50.0, 10.0));
+    plot.draw_grid(1.0, 1.0, StrokeStyle::dotted(2.0), 50);
+    plot.draw_axes(255);
+    plot.plot_fn(-5.0..5.0, |x| 1.0 / x, StrokeStyle::Solid, 100);
+    plot.plot_fn(-2.0..2.0, |x| x*x, StrokeStyle::Solid, 200);
+    assert_eq!((img[(50, 30)], img[(0, 10)], img[(100, 10)]), (255, 255, 255));
+    // x² at x = 1 and x = 2
+    assert_eq!((img[(60, 20)], img[(70, 50)]), (200, 200));
+    // Only drawn over the range asked for
+    assert_eq!(img[(80, 50)], 50);
+    img.save_to_file(out("TEST_plot.pgm")).unwrap();
+
+    let mut img = ImagePGM::new(21, 11, 0);
+    let plot = Plot::for_fn(&mut img, 0.0..std::f64::consts::TAU, f64::sin);
+    let y = plot.y_range();
+    assert!((y.start + 1.1).abs() < 1e-3 && (y.end - 1.1).abs() < 1e-3);
+}
+
+#[test]
+fn charts() {
+    use ppmitzador::plot::{MarkerShape, Plot};
+    let mut img = ImagePGM::new(41, 41, 0);
+    let mut plot = Plot::new(&mut img, 0.0..4.0, 0.0..4.0);
+    plot.scatter(&[(1.0, 1.0), (f64::NAN, 2.0)], MarkerShape::Square, 5.0, 255);
+    plot.scatter(&[(3.0, 3.0)], MarkerShape::Circle, 8.0, 255);
+    plot.line_chart(&[(0.0, 4.0), (2.0, 4.0), (f64::NAN, 0.0), (2.0, 0.0), (4.0, 0.0)], StrokeStyle::Solid, Some((MarkerShape::Dot, 3.0)), 100);
+    // Square marker is filled, circle marker is just its outline
+    assert_eq!((img[(10, 10)], img[(12, 12)], img[(13, 13)]), (255, 255, 0));
+    assert_eq!((img[(30, 30)], img[(34, 30)]), (0, 255));
+    // The NaN splits the line in two
+    assert_eq!((img[(10, 40)], img[(30, 0)], img[(30, 20)]), (100, 100, 0));
+    assert_eq!(img[(1, 40)], 100);
+}
+
+#[test]
+fn markers() {
+    use ppmitzador::MarkerShape;
+    let lit = |shape, size| {
+        let mut img = ImagePGM::new(21, 21, 0);
+        img.draw_marker((10.0, 10.0), shape, size, 255);
+        img
+    };
+    let plus = lit(MarkerShape::Plus, 8.0);
+    assert_eq!((plus[(6, 10)], plus[(14, 10)], plus[(10, 6)], plus[(10, 14)], plus[(7, 7)]), (255, 255, 255, 255, 0));
+    let cross = lit(MarkerShape::Cross, 8.0);
+    assert_eq!((cross[(6, 6)], cross[(14, 14)], cross[(6, 14)], cross[(10, 14)]), (255, 255, 255, 0));
+    let diamond = lit(MarkerShape::Diamond, 10.0);
+    assert_eq!((diamond[(10, 10)], diamond[(10, 14)], diamond[(14, 14)]), (255, 255, 0));
+    let star = lit(MarkerShape::Star, 16.0);
+    // Tip at the top, notch between the two upper arms
+    assert_eq!((star[(10, 10)], star[(10, 17)], star[(6, 15)], star[(10, 3)]), (255, 255, 0, 0));
+    // Symmetric left to right
+    assert!((0..21).all(|y| (0..21).all(|x| star[(x, y)] == star[(20 - x, y)])));
+}
+
+#[test]
+fn compositing() {
+    let rgb = |p: Pixel| (p.r, p.g, p.b);
+    let base = Pixel::new(200, 100, 0);
+    let top = Pixel::new(100, 100, 255);
+    let blended = |mode| {
+        let mut img = ImagePPM::new(2, 2, base);
+        img.composite(&ImagePPM::new(2, 2, top), mode);
+        assert!(img.pixels().all(|&p| rgb(p) == rgb(img[(0, 0)])));
+        rgb(img[(0, 0)])
+    };
+    assert_eq!(blended(BlendMode::Normal), rgb(top));
+    assert_eq!(blended(BlendMode::Add), (255, 200, 255));
+    assert_eq!(blended(BlendMode::Multiply), (78, 39, 0));
+    assert_eq!(blended(BlendMode::Screen), (222, 161, 255));
+    assert_eq!(blended(BlendMode::Overlay), (188, 78, 0));
+    assert_eq!(blended(BlendMode::Difference), (100, 0, 255));
+
+    // Multiplying by white and screening with black change nothing
+    let mut img = ImagePPM::gradient_test(16, 8);
+    let original = img.clone();
+    img.composite(&ImagePPM::new(16, 8, Pixel::new(255, 255, 255)), BlendMode::Multiply);
+    img.composite(&ImagePPM::new(16, 8, Pixel::new(0, 0, 0)), BlendMode::Screen);
+    assert!(img.pixels().zip(original.pixels()).all(|(&a, &b)| rgb(a) == rgb(b)));
+}
+
+#[test]
+fn pixel_maps() {
+    let img = ImagePPM::gradient_test(8, 4);
+    let gray: ImagePGM = img.map_pixels(|p| p.luminance());
+    assert_eq!((gray.width(), gray.height()), (8, 4));
+    assert!((0..4).all(|y| (0..8).all(|x| gray[(x, y)] == img.get(x, y).unwrap().luminance())));
+
+    // Coordinates are the same ones `get` takes
+    let coords = img.map_pixels_with_coord(|c, _| (c.x*10 + c.y) as u8);
+    assert_eq!((coords[(3, 0)], coords[(0, 2)], coords[(7, 3)]), (30, 2, 73));
+
+    let inverted = img.map_pixels(|p| Pixel::new(255 - p.r, 255 - p.g, 255 - p.b));
+    let sums: ImagePGM = img.zip_map(&inverted, |a, b| ((a.r as u16 + b.r as u16) / 2) as u8);
+    assert!(sums.pixels().all(|&v| v == 127));
+}
+
+#[test]
+fn image_comparison() {
+    let img = ImagePPM::gradient_test(32, 24);
+    assert_eq!(img.psnr(&img), f64::INFINITY);
+    assert!((img.ssim(&img) - 1.0).abs() < 1e-9);
+
+    let mut touched = img.clone();
+    touched[(5, 5)] = Pixel::new(255, 0, 0);
+    touched[(20, 10)] = Pixel::new(0, 255, 0);
+    let diff = touched.diff(&img);
+    let red = |p: Pixel| (p.r, p.g, p.b) == (255, 0, 0);
+    assert!(red(diff[(5, 5)]) && red(diff[(20, 10)]));
+    assert_eq!(diff.pixels().filter(|&&p| red(p)).count(), 2);
+
+    // A little noise is closer than a lot of it, by both measures
+    let noisy = |amount: i16| img.map_pixels_with_coord(|c, p| {
+        let n = if (c.x*7 + c.y*3) % 2 == 0 { amount } else { -amount };
+        let f = |v: u8| (v as i16 + n).clamp(0, 255) as u8;
+        Pixel::new(f(p.r), f(p.g), f(p.b))
+    });
+    let (slight, heavy) = (noisy(2), noisy(40));
+    assert!(img.psnr(&slight) > 40.0 && img.psnr(&heavy) < 25.0);
+    assert!(img.ssim(&slight) > 0.95);
+    assert!(img.ssim(&heavy) < img.ssim(&slight));
+    assert!(img.ssim(&ImagePPM::new(32, 24, Pixel::new(128, 128, 128))) < 0.5);
+}
+
+#[test]
+fn reference_images() {
+    let img = ImagePPM::gradient_test(16, 16);
+    img.save_to_file_binary(out("TEST_reference.ppm")).unwrap();
+    img.assert_matches_reference(out("TEST_reference.ppm"), 0);
+
+    let mut close = img.clone();
+    close[(3, 3)].r = close[(3, 3)].r.saturating_add(2);
+    close.assert_matches_reference(out("TEST_reference.ppm"), 2);
+
+    let mut off = img.clone();
+    off[(8, 8)] = Pixel::new(255, 0, 255);
+    let _ = std::fs::remove_file(out("TEST_reference.diff.ppm"));
+    let failure = std::panic::catch_unwind(|| off.assert_matches_reference(out("TEST_reference.ppm"), 2)).unwrap_err();
+    assert!(failure.downcast_ref::<String>().unwrap().starts_with("1 of 256 pixels differ"));
+    let diff = ImagePPM::load_from_file(out("TEST_reference.diff.ppm")).unwrap();
+    assert_eq!((diff[(8, 8)].r, diff[(8, 8)].g), (255, 0));
+}
+
+#[test]
+fn seeded_randomness() {
+    use ppmitzador::random::{scatter_points, Rng};
+    let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+    let draws: Vec<u64> = (0..5).map(|_| a.next_u64()).collect();
+    assert_eq!(draws, (0..5).map(|_| b.next_u64()).collect::<Vec<_>>());
+    assert_ne!(draws[0], Rng::new(8).next_u64());
+
+    let mut rng = Rng::new(1);
+    assert!((0..1000).all(|_| (0.0..1.0).contains(&rng.next_f64())));
+    assert!((0..1000).all(|_| rng.below(3) < 3));
+    assert!((0..1000).all(|_| (-2.0..5.0).contains(&rng.range(-2.0..5.0))));
+    let heads = (0..10_000).filter(|_| rng.chance(0.25)).count();
+    assert!((2200..2800).contains(&heads));
+    assert_eq!(rng.choose::<u8>(&[]), None);
+    assert!([1, 2, 3].contains(rng.choose(&[1, 2, 3]).unwrap()));
+
+    let points = scatter_points(200, 40, 30, 99);
+    let xy = |ps: &[Coord]| ps.iter().map(|c| (c.x, c.y)).collect::<Vec<_>>();
+    assert_eq!(xy(&points), xy(&scatter_points(200, 40, 30, 99)));
+    assert!(points.iter().all(|c| c.x < 40 && c.y < 30));
+    // Spread over the whole image, not bunched in a corner
+    assert!(points.iter().any(|c| c.x < 10 && c.y < 10) && points.iter().any(|c| c.x >= 30 && c.y >= 20));
+
+    let mut img = ImagePPM::new(40, 30, Pixel::new(0, 0, 0));
+    for &c in &points { img[c] = rng.random_color(); }
+    img.save_to_file(out("TEST_scatter_points.ppm")).unwrap();
+}
+
+#[test]
+fn voronoi_diagrams() {
+    use ppmitzador::voronoi::voronoi;
+    let sites = [Coord::new(5, 5), Coord::new(30, 5), Coord::new(18, 25)];
+    let colors = [Pixel::new(255, 0, 0), Pixel::new(0, 255, 0), Pixel::new(0, 0, 255)];
+    let rgb = |p: Pixel| (p.r, p.g, p.b);
+
+    let plain = voronoi(36, 30, &sites, &colors, None);
+    assert_eq!(rgb(plain[(0, 0)]), (255, 0, 0));
+    assert_eq!(rgb(plain[(35, 0)]), (0, 255, 0));
+    assert_eq!(rgb(plain[(18, 29)]), (0, 0, 255));
+    // Every pixel is closest to the site whose color it got
+    for y in 0..30 { for x in 0..36 {
+        let c = Coord::new(x, y);
+        let i = colors.iter().position(|&col| rgb(col) == rgb(plain[c])).unwrap();
+        assert!(sites.iter().all(|s| c.distance(sites[i]) <= c.distance(*s)));
+    }}
+
+    let white = Pixel::new(255, 255, 255);
+    let bordered = voronoi(36, 30, &sites, &colors, Some(white));
+    bordered.save_to_file(out("TEST_voronoi.ppm")).unwrap();
+    // The left and right cells split down the middle, between x = 17 and 18
+    assert_eq!(rgb(bordered[(17, 0)]), rgb(white));
+    assert_eq!((rgb(bordered[(16, 0)]), rgb(bordered[(18, 0)])), ((255, 0, 0), (0, 255, 0)));
+    assert!(rgb(bordered[(5, 5)]) != rgb(white));
+
+    // Fewer colors than sites wrap around
+    let two = voronoi(36, 30, &sites, &colors[..2], None);
+    assert_eq!(rgb(two[(18, 29)]), (255, 0, 0));
+}
+
+#[test]
+fn delaunay_meshes() {
+    use ppmitzador::{delaunay::Triangulation, random::scatter_points};
+    // A square with its center: four triangles meeting in the middle
+    let square = Triangulation::new(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0), (5.0, 5.0)]);
+    assert_eq!(square.triangles().len(), 4);
+    assert!(square.triangles().iter().all(|t| t.contains(&4)));
+    assert_eq!(square.edges().len(), 8);
+    assert!(Triangulation::new(&[(0.0, 0.0), (1.0, 1.0)]).triangles().is_empty());
+    assert!(Triangulation::new(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 3.0)]).triangles().is_empty());
+
+    let points: Vec<CoordF> = scatter_points(60, 80, 60, 3).iter().map(|&c| c.into()).collect();
+    let mesh = Triangulation::new(&points);
+    // No point inside any triangle's circumcircle
+    for t in mesh.triangles() {
+        let [a, b, c] = t.map(|i| points[i]);
+        assert!((b - a).cross(c - a) > 0.0, "triangles are counterclockwise");
+        for (i, &p) in points.iter().enumerate() {
+            if t.contains(&i) { continue; }
+            let m = |q: CoordF| { let d = q - p; [d.x, d.y, d.dot(d)] };
+            let ([ax, ay, ad], [bx, by, bd], [cx, cy, cd]) = (m(a), m(b), m(c));
+            let det = ax*(by*cd - bd*cy) - ay*(bx*cd - bd*cx) + ad*(bx*cy - by*cx);
+            assert!(det <= 1e-6, "{p:?} is inside the circumcircle of {t:?}");
+        }
+    }
+
+    // Filled triangles tile the points' hull without gaps, and the edges go on top
+    let mut img = ImagePPM::new(80, 60, Pixel::new(0, 0, 0));
+    let rect = Triangulation::new(&[(10.0, 10.0), (70.0, 10.0), (70.0, 50.0), (10.0, 50.0), (35.0, 25.0), (50.0, 40.0)]);
+    rect.fill(&mut img, |[a, b, c]| Pixel::new(((a.x + b.x + c.x) / 3.0) as u8 * 3, 100, 200));
+    assert!((10..50).all(|y| (10..70).all(|x| img[(x, y)].b == 200)));
+    assert_eq!(img[(5, 5)].b, 0);
+    mesh.draw_edges(&mut img, Pixel::new(255, 255, 255));
+    img.save_to_file(out("TEST_delaunay.ppm")).unwrap();
+}
+
+#[test]
+fn line_clipping() {
+    use ppmitzador::clip::{clip_line, Rect};
+    let rect = Rect::new((0.0, 0.0), (10.0, 10.0));
+    assert_eq!(clip_line((2.0, 3.0), (8.0, 5.0), rect), Some((CoordF::new(2.0, 3.0), CoordF::new(8.0, 5.0))));
+    assert_eq!(clip_line((-5.0, 5.0), (15.0, 5.0), rect), Some((CoordF::new(0.0, 5.0), CoordF::new(10.0, 5.0))));
+    assert_eq!(clip_line((5.0, 20.0), (5.0, -20.0), rect), Some((CoordF::new(5.0, 10.0), CoordF::new(5.0, 0.0))));
+    assert_eq!(clip_line((-4.0, -2.0), (6.0, 8.0), rect), Some((CoordF::new(0.0, 2.0), CoordF::new(6.0, 8.0))));
+    assert_eq!(clip_line((-5.0, -5.0), (-1.0, 20.0), rect), None);
+    // Both ends outside different sides, but the segment passes the corner by
+    assert_eq!(clip_line((-1.0, 9.5), (3.0, 13.5), rect), None);
+
+    // Lines far outside the image are drawn the same as their visible part, and quickly
+    let mut img = ImagePGM::new(20, 20, 0);
+    img.draw_line((-1e12, 10.0), (1e12, 10.0), 255);
+    assert!((0..20).all(|x| img[(x, 10)] == 255));
+    assert_eq!(img.pixels().filter(|&&v| v == 255).count(), 20);
+    img.draw_line((-100.0, -100.0), (119.5, 119.5), 128);
+    assert!((0..20).all(|i| img[(i, i)] == 128));
+    img.draw_line((-5.0, 30.0), (30.0, 25.0), 1);
+    assert!(!img.pixels().any(|&v| v == 1));
+}
+
+#[test]
+fn polygon_clipping() {
+    use ppmitzador::clip::{clip_polygon, Rect};
+    let rect = Rect::new((0.0, 0.0), (10.0, 10.0));
+    let xy = |ps: Vec<CoordF>| ps.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+    // Inside already: untouched
+    let tri = [(1.0, 1.0), (8.0, 2.0), (4.0, 7.0)];
+    assert_eq!(xy(clip_polygon(&tri, rect)), vec![(1.0, 1.0), (8.0, 2.0), (4.0, 7.0)]);
+    // Bigger than the rectangle all round: the rectangle itself
+    let mut big = xy(clip_polygon(&[(-5.0, -5.0), (15.0, -5.0), (15.0, 15.0), (-5.0, 15.0)], rect));
+    big.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(big, vec![(0.0, 0.0), (0.0, 10.0), (10.0, 0.0), (10.0, 10.0)]);
+    assert!(clip_polygon(&[(20.0, 20.0), (30.0, 20.0), (25.0, 30.0)], rect).is_empty());
+    // A corner poking out gets cut off
+    let cut = clip_polygon(&[(5.0, 5.0), (15.0, 5.0), (5.0, 15.0)], rect);
+    assert_eq!(cut.len(), 5);
+    assert!(cut.iter().all(|&p| rect.contains(p)));
+
+    // Fills of partially (or hugely) offscreen polygons match the same polygon drawn inside
+    let mut clipped = ImagePGM::new(30, 30, 0);
+    clipped.fill_polygon(&[(-1e9, 5.0), (20.0, 5.0), (20.0, 1e9)], 255);
+    let mut direct = ImagePGM::new(30, 30, 0);
+    direct.fill_polygon(&[(-40.0, 5.0), (20.0, 5.0), (20.0, 65.0)], 255);
+    assert!(clipped.pixels().zip(direct.pixels()).all(|(a, b)| a == b));
+    assert_eq!((clipped[(0, 5)], clipped[(19, 29)], clipped[(20, 10)], clipped[(5, 4)]), (255, 255, 0, 0));
+    // A concave shape whose notch is offscreen still fills both arms
+    let mut arms = ImagePGM::new(30, 30, 0);
+    arms.fill_polygon(&[(5.0, 0.0), (10.0, 0.0), (10.0, 40.0), (20.0, 40.0), (20.0, 0.0), (25.0, 0.0), (25.0, 50.0), (5.0, 50.0)], 255);
+    assert_eq!((arms[(7, 20)], arms[(15, 20)], arms[(22, 20)]), (255, 0, 255));
+    arms.fill_polygon(&[(40.0, 40.0), (50.0, 40.0), (45.0, 50.0)], 128);
+    assert!(!arms.pixels().any(|&v| v == 128));
+}
+
+#[test]
+fn scenes() {
+    use ppmitzador::scene::{Scene, Shape};
+    let (red, blue, white) = (Pixel::new(255, 0, 0), Pixel::new(0, 0, 255), Pixel::new(255, 255, 255));
+    let rgb = |p: Pixel| (p.r, p.g, p.b);
+    let mut scene = Scene::new(40.0, 30.0, Pixel::new(0, 0, 0));
+    scene
+        .add(1, Shape::Circle { center: CoordF::new(20.0, 15.0), radius: 8.0 }, blue)
+        .add(0, Shape::Rect { a: CoordF::new(4.0, 4.0), b: CoordF::new(24.0, 20.0) }, red)
+        .add(2, Shape::Polygon(vec![CoordF::new(30.0, 2.0), CoordF::new(38.0, 2.0), CoordF::new(34.0, 10.0)]), white)
+        .add(2, Shape::Text { origin: CoordF::new(1.0, 22.0), text: "Hi".into(), scale: 1.0 }, white);
+
+    let small = scene.render(40, 30);
+    small.save_to_file(out("TEST_scene.ppm")).unwrap();
+    // The circle was added first but sits on a higher layer, so it covers the rectangle
+    assert_eq!(rgb(small[(18, 15)]), rgb(blue));
+    assert_eq!(rgb(small[(6, 6)]), rgb(red));
+    assert_eq!(rgb(small[(34, 4)]), rgb(white));
+    assert_eq!(rgb(small[(39, 29)]), (0, 0, 0));
+    assert!((22..30).any(|y| (1..17).any(|x| rgb(small[(x, y)]) == rgb(white))));
+
+    // Same scene at twice the resolution: everything twice as far
+    let big = scene.render(80, 60);
+    assert_eq!(rgb(big[(36, 30)]), rgb(blue));
+    assert_eq!(rgb(big[(12, 12)]), rgb(red));
+    assert_eq!(rgb(big[(68, 8)]), rgb(white));
+    let count = |img: &ImagePPM, col: Pixel| img.pixels().filter(|&&p| rgb(p) == rgb(col)).count();
+    assert!((count(&big, blue) as f64 / count(&small, blue) as f64 - 4.0).abs() < 0.2);
+
+    // Moving the rectangle above the circle changes what's on top
+    scene.items_mut()[1].layer = 5;
+    assert_eq!(rgb(scene.render(40, 30)[(18, 15)]), rgb(red));
+}
+
+#[test]
+fn svg_export() {
+    use ppmitzador::scene::{Scene, Shape};
+    let path = Path::new().move_to((0.0, 0.0)).line_to((10.0, 0.5)).line_to((2.25, 7.0)).close().move_to((1.0, 1.0)).line_to((2.0, 2.0));
+    assert_eq!(path.to_svg_data(), "M0 0 L10 0.5 L2.25 7 Z M1 1 L2 2");
+
+    let mut scene = Scene::new(100.0, 50.0, Pixel::new(255, 255, 255));
+    scene
+        .add(1, Shape::Circle { center: CoordF::new(20.0, 10.0), radius: 5.0 }, Pixel::new(255, 0, 0))
+        .add(0, Shape::Rect { a: CoordF::new(10.0, 5.0), b: CoordF::new(30.0, 15.0) }, Pixel::new(0, 128, 255))
+        .add(2, Shape::Polygon(vec![CoordF::new(50.0, 0.0), CoordF::new(60.0, 0.0), CoordF::new(55.0, 10.0)]), Pixel::new(0, 0, 0))
+        .add(2, Shape::Path(Path::new().move_to((70.0, 10.0)).line_to((80.0, 10.0)).line_to((75.0, 20.0)).close()), Pixel::new(1, 2, 3))
+        .add(3, Shape::Text { origin: CoordF::new(5.0, 40.0), text: "a<b\nc".into(), scale: 2.0 }, Pixel::new(0, 0, 0));
+    let svg = scene.to_svg();
+    scene.save_svg(out("TEST_scene.svg")).unwrap();
+    assert_eq!(std::fs::read_to_string(out("TEST_scene.svg")).unwrap(), svg);
+
+    assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50" viewBox="0 0 100 50">"#));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    let lines: Vec<&str> = svg.lines().collect();
+    // Background, then items by layer with y flipped
+    assert_eq!(lines[1], r##"<rect width="100%" height="100%" fill="#ffffff"/>"##);
+    assert_eq!(lines[2], r##"<rect x="10" y="35" width="20" height="10" fill="#0080ff"/>"##);
+    assert_eq!(lines[3], r##"<circle cx="20" cy="40" r="5" fill="#ff0000"/>"##);
+    assert_eq!(lines[4], r##"<polygon points="50,50 60,50 55,40" fill="#000000" fill-rule="evenodd"/>"##);
+    assert_eq!(lines[5], r##"<path d="M70 40 L80 40 L75 30 Z" fill="#010203" fill-rule="evenodd"/>"##);
+    assert_eq!(lines[6], r##"<text x="5" y="10" font-family="monospace" font-size="16" fill="#000000">a&lt;b</text>"##);
+    assert_eq!(lines[7], r##"<text x="5" y="26" font-family="monospace" font-size="16" fill="#000000">c</text>"##);
+
+    // The raster has the path too
+    assert_eq!(scene.render(100, 50)[(75, 12)].r, 1);
+}
+
+#[test]
+fn wireframes() {
+    use ppmitzador::render3d::{Camera, Mesh, Vec3};
+    let cube = Mesh::cube();
+    assert_eq!((cube.vertices.len(), cube.faces.len(), cube.edges().len()), (8, 12, 18));
+    // Faces wind counterclockwise from outside, so their normals point away from the center
+    for f in &cube.faces {
+        let [a, b, c] = f.map(|i| cube.vertices[i]);
+        let normal = (b - a).cross(c - a);
+        assert!(normal.dot(a) > 0.0);
+    }
+
+    let camera = Camera::new((0.0, 0.0, 5.0), (0.0, 0.0, 0.0)).with_fov(90f64.to_radians());
+    let (center, depth) = camera.project(Vec3::new(0.0, 0.0, 0.0), 100, 80).unwrap();
+    assert_eq!((center, depth), (CoordF::new(50.0, 40.0), 5.0));
+    // With a 90 degree fov, something as far up as it is in front is at the top edge
+    let (top, _) = camera.project(Vec3::new(0.0, 4.0, 1.0), 100, 80).unwrap();
+    assert!((top.y - 80.0).abs() < 1e-9);
+    let (right, _) = camera.project(Vec3::new(1.0, 0.0, 0.0), 100, 80).unwrap();
+    assert!(right.x > 50.0);
+    assert!(camera.project(Vec3::new(0.0, 0.0, 6.0), 100, 80).is_none());
+
+    let mut img = ImagePPM::new(100, 80, Pixel::new(0, 0, 0));
+    let lit = |img: &ImagePPM, x: usize, y: usize| img[(x, y)].g == 255;
+    img.draw_wireframe(&cube, &camera, Pixel::new(0, 255, 0));
+    img.save_to_file(out("TEST_wireframe.ppm")).unwrap();
+    // Front face corners at (±1, ±1) from 4 away land 10 pixels from the center, back ones 6.67
+    assert!(lit(&img, 60, 40) && lit(&img, 40, 40) && lit(&img, 50, 50) && lit(&img, 50, 30));
+    assert!(lit(&img, 56, 40) && lit(&img, 50, 46));
+    // Triangle diagonals are edges too, but nothing crosses this spot
+    assert!(lit(&img, 50, 40) && !lit(&img, 53, 38) && !lit(&img, 0, 0));
+
+    // Edges reaching behind the camera get cut instead of wrapping around
+    let mut inside = ImagePPM::new(100, 80, Pixel::new(0, 0, 0));
+    inside.draw_wireframe(&cube.transformed(|v| v * 10.0), &camera, Pixel::new(0, 255, 0));
+    assert!(inside.pixels().any(|p| p.g == 255));
+}
+
+#[test]
+fn depth_buffers() {
+    use ppmitzador::render3d::{Camera, DepthBuffer, Mesh, Vec3};
+    let (red, blue) = (Pixel::new(255, 0, 0), Pixel::new(0, 0, 255));
+    // Two overlapping triangles, the far one drawn last: the depth test keeps the near one on top
+    let mut img = ImagePPM::new(40, 40, Pixel::new(0, 0, 0));
+    let mut depth = DepthBuffer::new(40, 40);
+    let tri = [CoordF::new(5.0, 5.0), CoordF::new(35.0, 5.0), CoordF::new(20.0, 35.0)];
+    img.fill_triangle_depth(&mut depth, tri, [2.0, 2.0, 2.0], red);
+    img.fill_triangle_depth(&mut depth, tri.map(|p| p + CoordF::new(3.0, 0.0)), [5.0, 5.0, 5.0], blue);
+    assert_eq!((img[(20, 15)].r, img[(20, 15)].b), (255, 0));
+    assert_eq!(img[(36, 6)].b, 255);
+    assert_eq!(depth.get(20, 15), Some(2.0));
+    assert_eq!(depth.get(0, 39), Some(f64::INFINITY));
+    assert_eq!(depth.get(40, 0), None);
+    depth.clear();
+    assert_eq!(depth.get(20, 15), Some(f64::INFINITY));
+
+    // Depth is interpolated in 3D, so halfway across the screen isn't halfway in depth
+    let mut img = ImagePPM::new(40, 40, Pixel::new(0, 0, 0));
+    img.fill_triangle_depth(&mut depth, [CoordF::new(0.0, 0.0), CoordF::new(40.0, 0.0), CoordF::new(0.0, 40.0)], [1.0, 3.0, 1.0], red);
+    let z = depth.get(19, 0).unwrap();
+    assert!(z > 1.0 && z < 2.0);
+
+    // A cube in front of a bigger one: only the front one's faces show where they overlap
+    let camera = Camera::new((0.0, 0.0, 8.0), (0.0, 0.0, 0.0));
+    let front = Mesh::cube();
+    let back = Mesh::cube().transformed(|v| v * 2.0 + Vec3::new(0.0, 0.0, -6.0));
+    let mut scene = Mesh::new(back.vertices.clone(), back.faces.clone());
+    let offset = scene.vertices.len();
+    scene.vertices.extend(&front.vertices);
+    scene.faces.extend(front.faces.iter().map(|f| f.map(|i| i + offset)));
+    let color = |i: usize| if i < 12 { blue } else { red };
+
+    let mut zbuf = ImagePPM::new(60, 60, Pixel::new(0, 0, 0));
+    zbuf.fill_mesh(&scene, &camera, Some(&mut DepthBuffer::new(60, 60)), color);
+    zbuf.save_to_file(out("TEST_depth_buffer.ppm")).unwrap();
+    let mut painter = ImagePPM::new(60, 60, Pixel::new(0, 0, 0));
+    painter.fill_mesh(&scene, &camera, None, color);
+    for img in [&zbuf, &painter] {
+        assert_eq!(img[(30, 30)].r, 255);
+        assert_eq!(img[(30, 30)].b, 0);
+        assert!(img.pixels().any(|p| p.b == 255));
+        assert_eq!(img[(0, 0)].r + img[(0, 0)].b, 0);
+    }
+}
+
+#[test]
+fn obj_meshes() {
+    use ppmitzador::render3d::{Camera, Mesh};
+    let pyramid = "\
+# A square pyramid
+o pyramid
+v -1 0 -1
+v 1 0 -1
+v 1.0 0.0 1.0
+v -1 0 1
+v 0 1.5 0 1.0
+vt 0.5 0.5
+vn 0 1 0
+f 4 3 2 1
+f 1/1 2/1 5/1
+f 2//1 3//1 5//1
+f -3/1/1 -2/1/1 -1/1/1
+f 4 1 5
+";
+    let mesh = Mesh::from_obj_reader(pyramid.as_bytes()).unwrap();
+    assert_eq!(mesh.vertices.len(), 5);
+    assert_eq!(mesh.vertices[4].y, 1.5);
+    // The square base becomes two triangles
+    assert_eq!(mesh.faces, vec![[3, 2, 1], [3, 1, 0], [0, 1, 4], [1, 2, 4], [2, 3, 4], [3, 0, 4]]);
+    assert_eq!(mesh.edges().len(), 9);
+
+    std::fs::write(out("TEST_pyramid.obj"), pyramid).unwrap();
+    let loaded = Mesh::load_obj(out("TEST_pyramid.obj")).unwrap();
+    assert_eq!(loaded, mesh);
+    let mut img = ImagePPM::new(60, 60, Pixel::new(0, 0, 0));
+    img.draw_wireframe(&loaded, &Camera::new((3.0, 2.0, 4.0), (0.0, 0.5, 0.0)), Pixel::new(255, 255, 255));
+    assert!(img.pixels().any(|p| p.r == 255));
+
+    for bad in ["v 1 2\n", "v 1 x 3\n", "v 0 0 0\nf 1 2 3\n", "v 0 0 0\nv 1 0 0\nf 1 2\n", "f a b c\n"] {
+        assert!(matches!(Mesh::from_obj_reader(bad.as_bytes()), Err(PpmError::Parse(_))), "{bad:?}");
+    }
+    match Mesh::from_obj_reader("v 0 0 0\n\nf 1 1 9\n".as_bytes()) {
+        Err(PpmError::Parse(msg)) => assert!(msg.starts_with("line 3")),
+        other => panic!("{other:?}"),
+    }
+}
+
+#[test]
+fn shading() {
+    use ppmitzador::render3d::{Camera, DepthBuffer, Light, Mesh, Shading, Vec3};
+    // Gouraud blends the corners' colors across the triangle
+    let mut img = ImagePPM::new(40, 40, Pixel::new(0, 0, 0));
+    let (red, green, blue) = (Pixel::new(255, 0, 0), Pixel::new(0, 255, 0), Pixel::new(0, 0, 255));
+    img.fill_triangle_gouraud([CoordF::new(0.0, 0.0), CoordF::new(40.0, 0.0), CoordF::new(0.0, 40.0)], [red, green, blue]);
+    let near_red = img[(0, 0)];
+    assert!(near_red.r > 240 && near_red.g < 15 && near_red.b < 15);
+    assert!(img[(38, 0)].g > 230 && img[(0, 38)].b > 230);
+    let middle = img[(13, 13)];
+    assert!([middle.r, middle.g, middle.b].iter().all(|&c| (70..100).contains(&c)));
+    assert_eq!(img[(39, 39)].r, 0);
+
+    let light = Light::new((0.0, 0.0, 1.0)).with_ambient(0.25);
+    assert_eq!(light.intensity(Vec3::new(0.0, 0.0, 5.0)), 1.0);
+    assert_eq!(light.intensity(Vec3::new(0.0, 0.0, -1.0)), 0.25);
+    assert_eq!(light.shade(Vec3::new(1.0, 0.0, 0.0), Pixel::new(200, 100, 0)).r, 50);
+
+    let cube = Mesh::cube();
+    assert_eq!(cube.face_normal(0), Vec3::new(0.0, 0.0, -1.0));
+    let corner = cube.vertex_normals()[7];
+    assert!((corner.x - corner.y).abs() < 1e-9 && (corner.y - corner.z).abs() < 1e-9 && corner.x > 0.0);
+
+    // Looking at a corner of the cube lit from the upper right: three differently lit faces
+    let camera = Camera::new((4.0, 3.0, 5.0), (0.0, 0.0, 0.0));
+    let light = Light::new((1.0, 2.0, 0.5));
+    let white = Pixel::new(255, 255, 255);
+    let mut flat = ImagePPM::new(80, 80, Pixel::new(0, 0, 0));
+    flat.render_mesh(&cube, &camera, Some(&mut DepthBuffer::new(80, 80)), &light, white, Shading::Flat);
+    flat.save_to_file(out("TEST_flat_shading.ppm")).unwrap();
+    let mut shades: Vec<u8> = flat.pixels().map(|p| p.r).filter(|&r| r > 0).collect();
+    shades.sort_unstable();
+    shades.dedup();
+    assert_eq!(shades.len(), 3);
+
+    let mut smooth = ImagePPM::new(80, 80, Pixel::new(0, 0, 0));
+    smooth.render_mesh(&cube, &camera, None, &light, white, Shading::Gouraud);
+    smooth.save_to_file(out("TEST_gouraud_shading.ppm")).unwrap();
+    let mut shades: Vec<u8> = smooth.pixels().map(|p| p.r).filter(|&r| r > 0).collect();
+    shades.sort_unstable();
+    shades.dedup();
+    assert!(shades.len() > 10);
+    // Same silhouette either way
+    assert!(flat.pixels().zip(smooth.pixels()).all(|(a, b)| (a.r > 0) == (b.r > 0)));
+}
+
+#[test]
+fn textured_triangles() {
+    use ppmitzador::render3d::DepthBuffer;
+    let texture = ImagePPM::checkerboard(4, 4, 2, Pixel::new(255, 255, 255), Pixel::new(0, 0, 0));
+    let rgb = |p: Pixel| (p.r, p.g, p.b);
+    // Sampling: exact on pixels, blended between them, clamped past the edges
+    let ramp = ImagePPM::from_fn(3, 1, |c| Pixel::new(c.x as u8 * 100, 0, 0));
+    assert_eq!(ramp.sample((1.0, 0.0), ResizeFilter::Bilinear).r, 100);
+    assert_eq!(ramp.sample((1.5, 0.0), ResizeFilter::Bilinear).r, 150);
+    assert_eq!(ramp.sample((1.4, 0.0), ResizeFilter::Nearest).r, 100);
+    assert_eq!(ramp.sample((-3.0, 9.0), ResizeFilter::Bilinear).r, 0);
+    assert_eq!(ramp.sample((7.0, 0.0), ResizeFilter::Nearest).r, 200);
+    assert_eq!(ramp.sample_uv((0.5, 0.5), ResizeFilter::Nearest).r, 100);
+
+    // Two triangles making a square show the whole texture, scaled up 8 times
+    let mut img = ImagePPM::new(32, 32, Pixel::new(255, 0, 0));
+    let (a, b, c, d) = (CoordF::new(0.0, 0.0), CoordF::new(32.0, 0.0), CoordF::new(32.0, 32.0), CoordF::new(0.0, 32.0));
+    let (ua, ub, uc, ud) = (CoordF::new(0.0, 0.0), CoordF::new(1.0, 0.0), CoordF::new(1.0, 1.0), CoordF::new(0.0, 1.0));
+    img.fill_triangle_textured([a, b, c], [ua, ub, uc], &texture, ResizeFilter::Nearest);
+    img.fill_triangle_textured([a, c, d], [ua, uc, ud], &texture, ResizeFilter::Nearest);
+    img.save_to_file(out("TEST_textured.ppm")).unwrap();
+    assert!(img.pixels().all(|&p| p.g == p.r));
+    for y in 0..32 { for x in 0..32 {
+        assert_eq!(rgb(img[(x, y)]), rgb(texture[(x / 8, y / 8)]));
+    }}
+
+    // Bilinear sampling makes soft edges between the checks
+    let mut soft = ImagePPM::new(32, 32, Pixel::new(0, 0, 0));
+    soft.fill_triangle_textured([a, b, c], [ua, ub, uc], &texture, ResizeFilter::Bilinear);
+    assert!(soft.pixels().any(|p| p.r > 50 && p.r < 200));
+
+    // With depth, nearer textured triangles win
+    let mut zimg = ImagePPM::new(32, 32, Pixel::new(0, 0, 0));
+    let mut depth = DepthBuffer::new(32, 32);
+    let plain = ImagePPM::new(1, 1, Pixel::new(0, 255, 0));
+    zimg.fill_triangle_textured_depth(&mut depth, [a, b, c], [1.0; 3], [ua, ub, uc], &plain, ResizeFilter::Nearest);
+    zimg.fill_triangle_textured_depth(&mut depth, [a, b, c], [2.0; 3], [ua, ub, uc], &texture, ResizeFilter::Nearest);
+    assert_eq!(rgb(zimg[(30, 2)]), (0, 255, 0));
+}
+
+#[test]
+fn warping() {
+    let img = ImagePPM::gradient_test(40, 30);
+    let rgb = |p: &Pixel| (p.r, p.g, p.b);
+    // The identity mapping changes nothing, and shifting by whole pixels just moves them
+    let same = img.warp(|c| c.into(), ResizeFilter::Bilinear);
+    assert!(same.pixels().zip(img.pixels()).all(|(a, b)| rgb(a) == rgb(b)));
+    let shifted = img.warp(|c| CoordF::new(c.x as f64 + 3.0, c.y as f64), ResizeFilter::Nearest);
+    assert_eq!(rgb(&shifted[(0, 5)]), rgb(&img[(3, 5)]));
+    // Past the edge, the edge repeats
+    assert_eq!(rgb(&shifted[(39, 5)]), rgb(&img[(39, 5)]));
+    let mirrored = img.warp(|c| CoordF::new(39.0 - c.x as f64, c.y as f64), ResizeFilter::Nearest);
+    assert_eq!(rgb(&mirrored[(10, 20)]), rgb(&img[(29, 20)]));
+
+    // A swirl leaves the center and far corners alone and twists what's in between
+    let center = CoordF::new(20.0, 15.0);
+    let swirl = img.warp(|c| {
+        let d = CoordF::from(c) - center;
+        let angle = 2.0 * (1.0 - d.length() / 15.0).max(0.0);
+        center + d.rotate(angle)
+    }, ResizeFilter::Bilinear);
+    swirl.save_to_file(out("TEST_swirl.ppm")).unwrap();
+    assert_eq!(rgb(&swirl[(20, 15)]), rgb(&img[(20, 15)]));
+    assert_eq!(rgb(&swirl[(0, 0)]), rgb(&img[(0, 0)]));
+    assert_ne!(rgb(&swirl[(26, 15)]), rgb(&img[(26, 15)]));
+    // Empty images come back empty, and the mapping is never called
+    let empty = ImagePPM::new(0, 4, Pixel::BLACK).warp(|_| panic!("nothing to map"), ResizeFilter::Bilinear);
+    assert_eq!((empty.width(), empty.height()), (0, 4));
+}
+
+#[test]
+fn shadows_and_glows() {
+    let (bg, shape, black) = (Pixel::new(255, 255, 255), Pixel::new(200, 0, 0), Pixel::new(0, 0, 0));
+    let mut img = ImagePPM::new(40, 40, bg);
+    img.fill_rect(Coord::new(10, 15), Coord::new(19, 24), shape);
+    let mask = img.mask_of(bg);
+    assert_eq!(mask.pixels().filter(|&&b| b).count(), 100);
+
+    let mut shadowed = img.clone();
+    shadowed.drop_shadow(&mask, ICoord::new(4, -4), 2, black);
+    shadowed.save_to_file(out("TEST_drop_shadow.ppm")).unwrap();
+    // The shape itself is untouched, the shadow shows below and to its right
+    assert_eq!(shadowed[(15, 20)].r, 200);
+    assert!(shadowed[(22, 13)].r < 60);
+    // Soft edge, and nothing on the far side
+    let edge = shadowed[(24, 16)].r;
+    assert!(edge > 60 && edge < 230, "{edge}");
+    assert_eq!(shadowed[(8, 26)].r, 255);
+    assert_eq!(shadowed[(35, 35)].r, 255);
+
+    let mut sharp = img.clone();
+    sharp.drop_shadow(&mask, ICoord::new(4, -4), 0, black);
+    assert_eq!((sharp[(22, 13)].r, sharp[(24, 11)].r), (0, 255));
+
+    let mut glowing = ImagePPM::new(40, 40, black);
+    glowing.fill_rect(Coord::new(15, 15), Coord::new(24, 24), shape);
+    let mask = glowing.mask_of(black);
+    glowing.outer_glow(&mask, 5, Pixel::new(0, 255, 0));
+    glowing.save_to_file(out("TEST_outer_glow.ppm")).unwrap();
+    assert_eq!((glowing[(20, 20)].r, glowing[(20, 20)].g), (200, 0));
+    // Bright next to the shape, fading with distance, gone past the radius
+    let glow = |x: usize| glowing[(x, 20)].g;
+    assert!(glow(25) > glow(27) && glow(27) > glow(29) && glow(29) > 0);
+    assert_eq!(glow(31), 0);
+    assert_eq!(glowing[(20, 9)].g, 0);
+}
+
+#[test]
+fn post_effects() {
+    let gray = Pixel::new(200, 200, 200);
+    let mut img = ImagePPM::new(41, 31, gray);
+    img.vignette(0.5);
+    img.save_to_file(out("TEST_vignette.ppm")).unwrap();
+    assert_eq!(img[(20, 15)].r, 200);
+    assert_eq!((img[(0, 0)].r, img[(40, 30)].r), (100, 100));
+    assert!(img[(10, 15)].r < 200 && img[(10, 15)].r > img[(0, 15)].r);
+    let mut untouched = ImagePPM::new(8, 8, gray);
+    untouched.vignette(0.0);
+    assert!(untouched.pixels().all(|p| p.r == 200));
+
+    let grain = |seed| {
+        let mut img = ImagePPM::new(32, 32, Pixel::new(100, 150, 250));
+        img.add_film_grain(10, seed);
+        img
+    };
+    let (a, b, c) = (grain(1), grain(1), grain(2));
+    assert!(a.pixels().zip(b.pixels()).all(|(p, q)| (p.r, p.g, p.b) == (q.r, q.g, q.b)));
+    assert!(a.pixels().zip(c.pixels()).any(|(p, q)| p.r != q.r));
+    // Monochrome and within bounds, clamping at white
+    assert!(a.pixels().all(|p| (90..=110).contains(&p.r) && p.g as i16 - p.r as i16 == 50 && p.b >= 240));
+    assert!(a.pixels().any(|p| p.r < 100) && a.pixels().any(|p| p.r > 100));
+}
+
+#[test]
+fn retro_effects() {
+    let rgb = |p: Pixel| (p.r, p.g, p.b);
+    // A white bar on black: red fringes on one side, blue on the other
+    let mut img = ImagePPM::new(20, 4, Pixel::new(0, 0, 0));
+    img.fill_rect(Coord::new(8, 0), Coord::new(11, 3), Pixel::new(255, 255, 255));
+    img.chromatic_aberration(1);
+    assert_eq!(rgb(img[(7, 1)]), (255, 0, 0));
+    assert_eq!(rgb(img[(9, 1)]), (255, 255, 255));
+    assert_eq!(rgb(img[(12, 1)]), (0, 0, 255));
+    assert_eq!(rgb(img[(14, 1)]), (0, 0, 0));
+    // Edges repeat instead of wrapping or panicking
+    let mut edge = ImagePPM::from_fn(3, 1, |c| Pixel::new(c.x as u8 * 100, 0, c.x as u8 * 100));
+    edge.chromatic_aberration(5);
+    assert_eq!((edge[(0, 0)].r, edge[(2, 0)].b), (200, 0));
+
+    let mut lines = ImagePPM::new(4, 6, Pixel::new(200, 100, 50));
+    lines.scanlines(0.5);
+    // The top row is y = 5
+    let reds: Vec<u8> = (0..6).rev().map(|y| lines[(0, y)].r).collect();
+    assert_eq!(reds, vec![200, 100, 200, 100, 200, 100]);
+    assert_eq!(rgb(lines[(3, 4)]), (100, 50, 25));
+
+    let mut blocks = ImagePPM::from_fn(5, 5, |c| Pixel::new((c.x * 50) as u8, 0, 0));
+    blocks.pixelate(2);
+    // Blocks start at the top left, so the top row is y = 4
+    assert_eq!((blocks[(0, 4)].r, blocks[(1, 3)].r), (25, 25));
+    assert_eq!((blocks[(2, 4)].r, blocks[(3, 3)].r), (125, 125));
+    // The leftover column and row make smaller blocks
+    assert_eq!((blocks[(4, 4)].r, blocks[(4, 0)].r, blocks[(0, 0)].r), (200, 200, 25));
+    let mut one = ImagePPM::gradient_test(6, 6);
+    let before = one.clone();
+    one.pixelate(1);
+    assert!(one.pixels().zip(before.pixels()).all(|(&a, &b)| rgb(a) == rgb(b)));
+}
+
+#[test]
+fn connected_components() {
+    let mut img = ImagePGM::new(20, 12, 0);
+    img.fill_rect(Coord::new(1, 1), Coord::new(4, 3), 255);
+    // An L shape, one blob
+    img.fill_rect(Coord::new(10, 2), Coord::new(10, 8), 200);
+    img.fill_rect(Coord::new(10, 2), Coord::new(15, 2), 200);
+    // Only touches the L diagonally, so it's a blob of its own
+    img[(11, 9)] = 255;
+    img[(19, 11)] = 255;
+
+    let regions = img.connected_components(|v| v > 100);
+    assert_eq!(regions.len(), 4);
+    let r = regions[0];
+    assert_eq!((r.label, r.min.x, r.min.y, r.max.x, r.max.y, r.pixel_count), (0, 1, 1, 4, 3, 12));
+    assert_eq!(regions[1].pixel_count, 12);
+    assert_eq!((regions[1].width(), regions[1].height()), (6, 7));
+    assert_eq!((regions[2].min.x, regions[2].min.y, regions[2].pixel_count), (11, 9, 1));
+    assert_eq!((regions[3].max.x, regions[3].max.y), (19, 11));
+    assert_eq!(regions.iter().map(|r| r.label).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+
+    // The background is one region too, wrapping around everything
+    let background = img.connected_components(|v| v == 0);
+    assert_eq!(background.len(), 1);
+    assert_eq!(background[0].pixel_count, 20*12 - 26);
+
+    // Works on any pixel type, and big blobs don't blow the stack
+    let big = ImagePPM::new(600, 600, Pixel::new(1, 2, 3));
+    assert_eq!(big.connected_components(|p| p.g == 2)[0].pixel_count, 360_000);
+    assert!(ImagePBM::new(5, 5, false).connected_components(|b| b).is_empty());
+}
+
+#[test]
+fn morphology() {
+    use ppmitzador::analysis::StructuringElement;
+    assert_eq!(StructuringElement::square(1).offsets().len(), 9);
+    assert_eq!(StructuringElement::cross(2).offsets().len(), 9);
+    assert_eq!(StructuringElement::disc(2).offsets().len(), 13);
+
+    // A square with a speck next to it and a hole in it
+    let mut mask = ImagePBM::new(20, 20, false);
+    mask.fill_rect(Coord::new(5, 5), Coord::new(14, 14), true);
+    mask[(9, 9)] = false;
+    mask[(2, 17)] = true;
+    let count = |m: &ImagePBM| m.pixels().filter(|&&b| b).count();
+    let se = StructuringElement::square(1);
+
+    let eroded = mask.erode(&se);
+    assert!(!eroded[(2, 17)] && !eroded[(5, 5)] && eroded[(6, 6)]);
+    // The hole grows to 3x3
+    assert!(!eroded[(10, 10)] && eroded[(11, 11)]);
+    assert_eq!(count(&eroded), 64 - 9);
+    let dilated = mask.dilate(&se);
+    assert!(dilated[(4, 4)] && dilated[(9, 9)] && dilated[(3, 18)] && !dilated[(3, 3)]);
+
+    let opened = mask.open(&se);
+    assert!(!opened[(2, 17)] && opened[(5, 5)] && opened[(14, 14)]);
+    let closed = mask.close(&se);
+    assert!(closed[(9, 9)] && closed[(2, 17)]);
+    assert_eq!(count(&closed), 101);
+
+    // Grayscale: erosion is a local min, dilation a local max
+    let ramp = ImagePGM::from_fn(5, 1, |c| c.x as u8 * 10);
+    let line = StructuringElement::new(vec![ICoord::new(-1, 0), ICoord::new(0, 0), ICoord::new(1, 0)]);
+    assert_eq!(ramp.erode(&line).pixels().copied().collect::<Vec<_>>(), vec![0, 0, 10, 20, 30]);
+    assert_eq!(ramp.dilate(&line).pixels().copied().collect::<Vec<_>>(), vec![10, 20, 30, 40, 40]);
+}
+
+#[test]
+fn thresholding() {
+    let img = ImagePPM::from_fn(10, 1, |c| { let v = c.x as u8 * 25; Pixel::new(v, v, v) });
+    let mask = img.threshold(100);
+    assert_eq!(mask.pixels().copied().collect::<Vec<_>>(), (0..10).map(|x| x >= 4).collect::<Vec<_>>());
+
+    // Two clusters of brightness: Otsu splits between them
+    let two = ImagePPM::from_fn(20, 20, |c| {
+        let v = if (c.x + c.y) % 3 == 0 { 200 + (c.x % 5) as u8 } else { 40 + (c.y % 7) as u8 };
+        Pixel::new(v, v, v)
+    });
+    let level = two.otsu_level();
+    assert!((47..=200).contains(&level), "{level}");
+    let fg = two.threshold_otsu();
+    assert!((0..20).all(|y| (0..20).all(|x| fg[(x, y)] == ((x + y) % 3 == 0))));
+    assert_eq!(ImagePPM::new(4, 4, Pixel::new(9, 9, 9)).otsu_level(), 0);
+
+    // Text on a background that gets darker to the right: no single level works, adaptive does
+    let page = ImagePPM::from_fn(60, 20, |c| {
+        let bg = 230 - c.x as u8 * 2;
+        let ink = c.y % 5 == 2 && c.x % 4 != 0;
+        let v = if ink { bg - 50 } else { bg };
+        Pixel::new(v, v, v)
+    });
+    let adaptive = page.threshold_adaptive(9);
+    page.save_to_file(out("TEST_uneven_page.ppm")).unwrap();
+    // Background counts as foreground (bright) and ink doesn't, all across the page
+    assert!((0..60).all(|x| adaptive[(x, 0)] && !adaptive[(x | 1, 2)]));
+    // Ink on the left is brighter than paper on the right, so any fixed level gets something wrong
+    let fixed = page.threshold(page.otsu_level());
+    assert!((0..60).any(|x| !fixed[(x, 0)] || fixed[(x | 1, 2)]));
+}
+
+#[test]
+fn distance_transforms() {
+    let mut mask = ImagePBM::new(30, 20, false);
+    mask[(5, 5)] = true;
+    mask[(25, 15)] = true;
+    let d = mask.distance_transform();
+    let at = |x: usize, y: usize| d[x + y*30];
+    assert_eq!((at(5, 5), at(25, 15)), (0.0, 0.0));
+    assert_eq!(at(8, 9), 5.0);
+    assert_eq!(at(0, 5), 5.0);
+    assert!((at(29, 19) - 32f64.sqrt()).abs() < 1e-9);
+    // Matches brute force everywhere
+    for y in 0..20 { for x in 0..30 {
+        let brute = [(5.0, 5.0), (25.0, 15.0)].iter().map(|&(px, py)| CoordF::new(x as f64, y as f64).distance(CoordF::new(px, py))).fold(f64::INFINITY, f64::min);
+        assert!((at(x, y) - brute).abs() < 1e-9);
+    }}
+
+    // A filled shape: inside is 0, distances grow outwards from its edge
+    let mut blob = ImagePBM::new(20, 20, false);
+    blob.fill_rect(Coord::new(5, 5), Coord::new(10, 10), true);
+    let d = blob.distance_transform();
+    assert_eq!((d[7 + 7*20], d[13 + 7*20], d[7 + 2*20]), (0.0, 3.0, 3.0));
+    assert!(ImagePBM::new(4, 3, false).distance_transform().iter().all(|v| v.is_infinite()));
+
+    // Straight into a heatmap
+    let heat = ImagePPM::from_scalar_field(30, 20, &mask.distance_transform(), &Gradient::evenly_spaced(&[Pixel::new(255, 255, 255), Pixel::new(0, 0, 0)])).unwrap();
+    heat.save_to_file(out("TEST_distance_transform.ppm")).unwrap();
+    assert_eq!(heat[(5, 5)].r, 255);
+}
+
+#[test]
+fn seam_carving() {
+    // Flat background with a detailed stripe: every seam should come out of the flat parts
+    let stripe = |x: usize, y: usize| if (x + y).is_multiple_of(2) { Pixel::new(255, 0, 0) } else { Pixel::new(0, 0, 255) };
+    let img = ImagePPM::from_fn(40, 20, |c| if (10..14).contains(&c.x) { stripe(c.x, c.y) } else { Pixel::new(90, 160, 90) });
+    let carved = img.seam_carve(28);
+    assert_eq!((carved.width(), carved.height()), (28, 20));
+    carved.save_to_file(out("TEST_seam_carving.ppm")).unwrap();
+    let rgb = |p: Pixel| (p.r, p.g, p.b);
+    for y in 0..20 {
+        let detailed: Vec<usize> = (0..28).filter(|&x| rgb(carved[(x, y)]) != (90, 160, 90)).collect();
+        assert_eq!(detailed.len(), 4, "row {y}");
+        assert_eq!(detailed[3] - detailed[0], 3, "stripe stays contiguous on row {y}");
+        let x0 = detailed[0];
+        assert_eq!(rgb(carved[(x0, y)]), rgb(img[(10, y)]));
+    }
+
+    // Same width is a copy, and flat images just lose columns
+    let same = img.seam_carve(40);
+    assert!((0..40).all(|x| rgb(same[(x, 7)]) == rgb(img[(x, 7)])));
+    let flat = ImagePPM::new(10, 5, Pixel::new(1, 2, 3)).seam_carve(1);
+    assert_eq!((flat.width(), rgb(flat[(0, 4)])), (1, (1, 2, 3)));
+}
+
+#[test]
+fn integral_images() {
+    let img = ImagePPM::from_fn(23, 17, |c| Pixel::new((c.x*11 % 256) as u8, (c.y*13 % 256) as u8, ((c.x*c.y) % 256) as u8));
+    let table: IntegralImage<3> = img.integral_image();
+    assert_eq!((table.width(), table.height()), (23, 17));
+    // Any rectangle matches adding it up by hand
+    for (min, max) in [((0, 0), (23, 17)), ((3, 4), (9, 15)), ((22, 16), (23, 17)), ((5, 5), (5, 9))] {
+        let (min, max) = (Coord::new(min.0, min.1), Coord::new(max.0, max.1));
+        let mut brute = [0u64; 3];
+        for y in min.y..max.y { for x in min.x..max.x {
+            let p = img[(x, y)];
+            brute[0] += p.r as u64; brute[1] += p.g as u64; brute[2] += p.b as u64;
+        }}
+        assert_eq!(table.sum(min, max), brute);
+    }
+    // Clipped to the image, and empty rectangles have no mean
+    assert_eq!(table.sum(Coord::new(20, 10), Coord::new(100, 100)), table.sum(Coord::new(20, 10), Coord::new(23, 17)));
+    assert_eq!(table.mean(Coord::new(5, 5), Coord::new(5, 9)), None);
+    assert_eq!(ImagePGM::from(&img).integral_image().mean(Coord::new(0, 0), Coord::new(1, 1)), Some([img[(0, 0)].luminance() as f64]));
+
+    // Fast box blur agrees with the kernel away from the edges
+    let blurred = img.box_blur_sat(3);
+    let reference = img.apply_kernel(&Kernel::box_blur(3));
+    blurred.save_to_file(out("TEST_box_blur_sat.ppm")).unwrap();
+    for y in 3..14 { for x in 3..20 {
+        let (a, b) = (blurred[(x, y)], reference[(x, y)]);
+        assert!(a.r.abs_diff(b.r) <= 1 && a.g.abs_diff(b.g) <= 1 && a.b.abs_diff(b.b) <= 1, "({x}, {y})");
+    }}
+    // Huge radii are just as cheap: everything becomes the average colour
+    let flat = img.box_blur_sat(1000);
+    let [r, g, b] = table.mean(Coord::new(0, 0), Coord::new(23, 17)).unwrap().map(|v| v.round() as u8);
+    assert!((0..17).all(|y| (0..23).all(|x| { let p = flat[(x, y)]; (p.r, p.g, p.b) == (r, g, b) })));
+}
+
+#[test]
+fn pyramids() {
+    let img = ImagePPM::from_fn(37, 20, |c| if (c.x / 4 + c.y / 4).is_multiple_of(2) { Pixel::new(255, 255, 255) } else { Pixel::new(0, 0, 0) });
+    let levels = img.pyramid(3);
+    let sizes: Vec<(usize, usize)> = levels.iter().map(|l| (l.width(), l.height())).collect();
+    assert_eq!(sizes, vec![(37, 20), (19, 10), (10, 5), (5, 3)]);
+    for (i, level) in levels.iter().enumerate() { level.save_to_file(out(&format!("TEST_pyramid_{i}.ppm"))).unwrap(); }
+    // 2x2 blocks are averaged: the 4x4 checker survives one halving and greys out after two
+    let rgb = |p: Pixel| (p.r, p.g, p.b);
+    assert_eq!((rgb(levels[1][(0, 0)]), rgb(levels[1][(2, 0)])), ((255, 255, 255), (0, 0, 0)));
+    assert_eq!(rgb(levels[2][(0, 0)]), (255, 255, 255));
+    let grey = ImagePPM::from_fn(2, 2, |c| Pixel::new(if c.x == c.y { 200 } else { 100 }, 0, 0)).half_size();
+    assert_eq!((grey.width(), rgb(grey[(0, 0)])), (1, (150, 0, 0)));
+    // Odd edges only average the pixels that exist
+    assert_eq!(rgb(levels[1][(18, 0)]), rgb(img[(36, 0)]));
+
+    // Stops once it reaches a single pixel
+    let tiny = ImagePPM::new(5, 3, Pixel::new(9, 9, 9)).pyramid(10);
+    assert_eq!(tiny.iter().map(|l| (l.width(), l.height())).collect::<Vec<_>>(), vec![(5, 3), (3, 2), (2, 1), (1, 1)]);
+}
+
+#[test]
+fn wrap_around_drawing() {
+    let (bg, red) = (Pixel::new(0, 0, 0), Pixel::new(255, 0, 0));
+    let is_red = |p: &Pixel| p.r == 255;
+    let mut img = Tiled(ImagePPM::new(20, 10, bg));
+    assert_eq!((img.wrap_mode(), ImagePPM::new(1, 1, bg).wrap_mode()), (WrapMode::Wrap, WrapMode::Clip));
+
+    // Past the right edge comes back in on the left
+    img.fill_rect(Coord::new(15, 2), Coord::new(24, 2), red);
+    assert!((0..20).all(|x| is_red(&img[Coord::new(x, 2)]) != (5..15).contains(&x)));
+    // And past the left (or bottom) edge on the right (or top)
+    img.draw_line(CoordF::new(-3.0, 5.0), CoordF::new(3.0, 5.0), red);
+    assert!((0..20).all(|x| is_red(&img[Coord::new(x, 5)]) == (x <= 3 || x >= 17)));
+    img.fill_ellipse(Coord::new(0, 0), 1, 1, red);
+    assert!([(19, 0), (0, 9), (1, 0), (0, 1)].iter().all(|&(x, y)| is_red(&img[Coord::new(x, y)])));
+    img.fill_polygon(&[CoordF::new(8.0, -2.0), CoordF::new(12.0, -2.0), CoordF::new(10.0, 1.0)], red);
+    assert!(is_red(&img[Coord::new(10, 8)]) && is_red(&img[Coord::new(10, 9)]));
+    img.draw_circle_outline(Coord::new(10, 9), 3, red);
+    assert!(is_red(&img[Coord::new(10, 2)]));
+    img.0.save_to_file(out("TEST_wrap_around.ppm")).unwrap();
+
+    // Shapes that fit are drawn exactly like on a plain image
+    let draw = |img: &mut dyn FnMut(&Path)| img(&Path::new().move_to((3.0, 3.0)).line_to((15.0, 4.0)).line_to((9.0, 8.0)).close());
+    let mut plain = ImagePPM::new(20, 10, bg);
+    let mut tiled = Tiled(ImagePPM::new(20, 10, bg));
+    draw(&mut |p| plain.fill_path(p, red));
+    draw(&mut |p| tiled.fill_path(p, red));
+    draw(&mut |p| plain.stroke_path(p, 2.0, Pixel::WHITE));
+    draw(&mut |p| tiled.stroke_path(p, 2.0, Pixel::WHITE));
+    assert_eq!(plain.atoms().iter().map(|p| (p.r, p.g, p.b)).collect::<Vec<_>>(), tiled.atoms().iter().map(|p| (p.r, p.g, p.b)).collect::<Vec<_>>());
+
+    // A texture drawn across the seams tiles seamlessly: every circle is whole on the torus
+    let mut tex = Tiled(ImagePPM::new(16, 16, bg));
+    for (x, y) in [(0, 0), (15, 8), (7, 15)] { tex.fill_ellipse(Coord::new(x, y), 3, 3, red); }
+    let count = tex.pixels().filter(|p| is_red(p)).count();
+    let mut single = ImagePPM::new(16, 16, bg);
+    single.fill_ellipse(Coord::new(8, 8), 3, 3, red);
+    assert_eq!(count, 3 * single.pixels().filter(|p| is_red(p)).count());
+
+    // Blits and text wrap as well, and it composes with a top left origin
+    let mut tl = Tiled(TopLeft(ImagePPM::new(20, 10, bg)));
+    tl.blit(&Tiled(TopLeft(ImagePPM::new(4, 4, red))), Coord::new(18, 8));
+    assert!(is_red(&tl[Coord::new(1, 1)]) && is_red(&tl[Coord::new(18, 9)]) && !is_red(&tl[Coord::new(2, 2)]));
+    assert!(is_red(&tl.into_inner().0[(1, 8)]));
+    let mut text = Tiled(ImagePPM::new(20, 10, bg));
+    text.draw_text(Coord::new(16, 1), "II", 1, red);
+    // The second letter starts at x = 24, which is x = 4
+    let mut plain = ImagePPM::new(20, 10, bg);
+    plain.draw_text(Coord::new(4, 1), "I", 1, red);
+    assert!((0..10).all(|y| (4..12).all(|x| is_red(&text[Coord::new(x, y)]) == is_red(&plain[(x, y)]))));
+    assert!(plain.pixels().any(is_red));
+
+    // Huge shapes cost no more than the image: this would walk 10^10 pixels otherwise
+    let mut huge = Tiled(ImagePPM::new(16, 16, bg));
+    huge.fill_rect(Coord::new(0, 0), Coord::new(100_000, 100_000), red);
+    assert!(huge.pixels().all(is_red));
+}
+
+#[test]
+fn row_by_row_writers() {
+    let bytes = |write: &dyn Fn(&mut Vec<u8>)| { let mut buf = Vec::new(); write(&mut buf); buf };
+
+    // Bottom row is y = 0, so it comes last in the file
+    let mut ppm = ImagePPM::new(2, 2, Pixel::BLACK);
+    *ppm.get_mut(0, 1).unwrap() = Pixel::new(1, 22, 255);
+    *ppm.get_mut(1, 0).unwrap() = Pixel::new(7, 8, 9);
+    assert_eq!(bytes(&|b| ppm.write_to(b).unwrap()), b"P3\n2 2\n255\n  1  22 255\n  0   0   0\n  0   0   0\n  7   8   9\n");
+    assert_eq!(bytes(&|b| ppm.write_binary_to(b).unwrap()), b"P6\n2 2\n255\n\x01\x16\xff\0\0\0\0\0\0\x07\x08\x09");
+
+    let mut pgm = ImagePGM::new(3, 2, 0);
+    *pgm.get_mut(0, 1).unwrap() = 10;
+    *pgm.get_mut(2, 0).unwrap() = 200;
+    assert_eq!(bytes(&|b| pgm.write_to(b).unwrap()), b"P2\n3 2\n255\n 10\n  0\n  0\n  0\n  0\n200\n");
+    assert_eq!(bytes(&|b| pgm.write_binary_to(b).unwrap()), b"P5\n3 2\n255\n\x0a\0\0\0\0\xc8");
+
+    // 10 wide: every P4 row is padded to two bytes. PBM's 1 is black, so false (black) sets bits
+    let mut pbm = ImagePBM::new(10, 2, true);
+    *pbm.get_mut(0, 1).unwrap() = false;
+    *pbm.get_mut(9, 1).unwrap() = false;
+    *pbm.get_mut(1, 0).unwrap() = false;
+    assert_eq!(bytes(&|b| pbm.write_to(b).unwrap()), b"P1\n10 2\n10000000010100000000");
+    assert_eq!(bytes(&|b| pbm.write_binary_to(b).unwrap()), b"P4\n10 2\n\x80\x40\x40\x00");
+}
+
+#[test]
+fn transforms_keep_maxval() {
+    let img = ImagePPM16::new(6, 4, Pixel16::new(1, 2, 3)).with_maxval(1023);
+    let transformed = [
+        img.crop(Coord::new(1, 1), 3, 2),
+        img.sub_image(Coord::new(2, 0), 10, 10).unwrap(),
+        img.rotate90(), img.rotate180(), img.rotate270(),
+        img.flip_horizontal(), img.flip_vertical(),
+        img.resize(12, 2, ResizeFilter::Bilinear),
+        img.rotate(0.3, Pixel16::new(0, 0, 0)),
+        img.pad(1, 1, 2, 2, Pixel16::new(0, 0, 0)),
+        img.map_pixels(|p| Pixel16::new(p.b, p.g, p.r)),
+        img.map_pixels_with_coord(|c, p| Pixel16::new(c.x as u16, p.g, p.r)),
+        img.zip_map(&img, |a, b| Pixel16::new(a.r + b.r, a.g, a.b)),
+    ];
+    for (i, t) in transformed.iter().enumerate() { assert_eq!(t.maxval(), 1023, "transform {i}"); }
+    assert_eq!(TopLeft(img.clone()).rotate90().into_inner().maxval(), 1023);
+    // Changing the kind of pixel goes back to that kind's default
+    assert_eq!(img.map_pixels(|p| p.r as u8).maxval(), 255);
+}
+
+#[test]
+fn resizing_empty_images() {
+    let empty = ImagePPM::new(0, 0, Pixel::RED);
+    let same = empty.resize(0, 0, ResizeFilter::Bilinear);
+    assert_eq!((same.width(), same.height()), (0, 0));
+    // Nothing to stretch, so the new pixels are blank
+    let grown = ImagePGM::new(0, 3, 9).resize(2, 2, ResizeFilter::Nearest);
+    assert_eq!(grown.atoms(), &vec![0; 4]);
+    let shrunk = ImagePPM::new(4, 4, Pixel::RED).resize(0, 7, ResizeFilter::Nearest);
+    assert_eq!((shrunk.width(), shrunk.height(), shrunk.atoms().len()), (0, 7, 0));
+    assert_eq!(TopLeft(ImagePBM::new(0, 0, true)).resize(1, 1, ResizeFilter::Nearest).atoms(), &vec![false]);
+}
+
+#[test]
+fn degenerate_gaussian_kernels() {
+    let white = ImagePPM::new(5, 5, Pixel::WHITE);
+    for sigma in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        let k = Kernel::gaussian(sigma);
+        assert_eq!((k.width(), k.height(), k.weights()), (1, 1, &[1.0][..]));
+        assert!(white.apply_kernel(&k).pixels().all(|p| (p.r, p.g, p.b) == (255, 255, 255)));
+    }
 }